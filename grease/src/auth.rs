@@ -38,6 +38,16 @@ impl User {
         })
     }
 
+    /// The event types that a member has a permission for specifically,
+    /// not counting a general grant of it.
+    pub fn permitted_event_types(&self, permission_name: &str) -> Vec<&str> {
+        self.permissions
+            .iter()
+            .filter(|permission| permission.name == permission_name)
+            .filter_map(|permission| permission.event_type.as_deref())
+            .collect()
+    }
+
     /// Extract a member from a request.
     ///
     /// Checks for a header named "token" containing the API token
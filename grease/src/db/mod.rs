@@ -6,17 +6,23 @@ pub mod schema;
 pub use self::schema::*;
 
 use self::schema::{
-    absence_request, active_semester, announcement, attendance, carpool, event, event_type, fee,
-    gig, gig_request, gig_song, google_docs, media_type, member, member_role, minutes, permission,
-    rides_in, role, role_permission, semester, session, song, song_link, todo, transaction,
-    transaction_type, uniform, variable, AbsenceRequestState, Enrollment, GigRequestStatus,
-    PermissionType, Pitch, SongMode, StorageType,
+    absence_request, active_semester, announcement, announcement_read, attendance, calendar_feed,
+    carpool, check_in_attempt, check_in_window, email_attachment, email_outbox, event,
+    event_series, event_type, fee, final_grade, finalization_log, gig, gig_request, gig_song,
+    google_docs, grade_dispute, grade_dispute_entry, grading_policy, grading_rule, imported_event,
+    job, job_run, media_type, member, member_role, minutes, notification_preference, permission,
+    rides_in, role, role_permission, semester, semester_finalization, session, song, song_link,
+    todo, transaction, transaction_type, uniform, variable, webhook, webhook_delivery,
+    AbsenceRequestState, BonusRule, EmailStatus, Enrollment, FinalizationAction, GigRequestStatus,
+    GradeDisputeAction, GradeDisputeState, JobRunStatus, NotificationCategory, NotificationChannel,
+    Period, PermissionType, Pitch, SongMode, StorageType, WebhookDeliveryStatus, WebhookKind,
 };
 use crate::util::FileUpload;
 use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
 use diesel::{Connection, MysqlConnection};
 use error::{GreaseError, GreaseResult};
 use serde::{de::Error as _, de::Unexpected, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
 pub fn connect_to_db() -> GreaseResult<MysqlConnection> {
     let db_url = std::env::var("DATABASE_URL")
@@ -330,6 +336,316 @@ pub struct NewSemester {
     pub gig_requirement: i32,
 }
 
+/// The model for how grades are calculated during a semester.
+///
+/// Semesters without a policy use the [default](crate::db::models::GradingPolicy::default_for).
+/// Which event types are bonus events, and which don't cost points for
+/// lateness or absence, are set by each [GradingRule](crate::db::models::GradingRule).
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE grading_policy (
+///   semester varchar(32) NOT NULL PRIMARY KEY,
+///   start_grade float NOT NULL DEFAULT '100',
+///   max_grade float NOT NULL DEFAULT '100',
+///   default_event_minutes int NOT NULL DEFAULT '60',
+///   use_type_weights boolean NOT NULL DEFAULT '0',
+///   sectional_substitution boolean NOT NULL DEFAULT '1',
+///   rehearsal_required_for_gigs boolean NOT NULL DEFAULT '1',
+///
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "semester": string,
+///     "startGrade": number,
+///     "maxGrade": number,
+///     "defaultEventMinutes": integer,
+///     "useTypeWeights": boolean,
+///     "sectionalSubstitution": boolean,
+///     "rehearsalRequiredForGigs": boolean
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Insertable, Serialize, Clone)]
+#[table_name = "grading_policy"]
+#[primary_key(semester)]
+#[serde(rename_all = "camelCase")]
+pub struct GradingPolicy {
+    /// The semester this policy grades
+    pub semester: String,
+    /// The grade everyone starts the semester with
+    pub start_grade: f32,
+    /// The highest grade anyone can have
+    pub max_grade: f32,
+    /// How long events without a release time are assumed to last
+    pub default_event_minutes: i32,
+    /// Whether events are worth their type's weight instead of their own points
+    pub use_type_weights: bool,
+    /// Whether attending any sectional in a week excuses missing the others,
+    /// and extra sectionals earn a bonus
+    pub sectional_substitution: bool,
+    /// Whether missing rehearsal in a week costs the points and credit
+    /// for that week's gigs
+    pub rehearsal_required_for_gigs: bool,
+}
+
+/// The model for how events of a type count towards grades in a semester.
+///
+/// Event types without a rule are never bonus events, and cost points
+/// for both lateness and absence.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE grading_rule (
+///   semester varchar(32) NOT NULL,
+///   event_type varchar(32) NOT NULL,
+///   bonus enum('never', 'always', 'when_optional') NOT NULL DEFAULT 'never',
+///   penalize_lateness boolean NOT NULL DEFAULT '1',
+///   penalize_absence boolean NOT NULL DEFAULT '1',
+///
+///   PRIMARY KEY (semester, event_type),
+///   FOREIGN KEY (semester) REFERENCES grading_policy (semester) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (event_type) REFERENCES event_type (name) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "semester": string,
+///     "eventType": string,
+///     "bonus": "never" | "always" | "whenOptional",
+///     "penalizeLateness": boolean,
+///     "penalizeAbsence": boolean
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Insertable, Serialize, Clone)]
+#[table_name = "grading_rule"]
+#[primary_key(semester, event_type)]
+#[serde(rename_all = "camelCase")]
+pub struct GradingRule {
+    /// The semester this rule applies during
+    pub semester: String,
+    /// The type of event this rule applies to
+    pub event_type: String,
+    /// Whether attending earns points, either always or only when not
+    /// expected to attend
+    pub bonus: BonusRule,
+    /// Whether arriving late or leaving early costs points
+    pub penalize_lateness: bool,
+    /// Whether missing the event costs points
+    pub penalize_absence: bool,
+}
+
+/// The required format for setting a semester's grading policy.
+///
+/// ## Expected Format:
+///
+/// |          Field           |  Type   | Required? |                Comments                 |
+/// |--------------------------|---------|:---------:|-----------------------------------------|
+/// | startGrade               | number  |     ✓     |                                         |
+/// | maxGrade                 | number  |     ✓     | must be at least `startGrade`           |
+/// | defaultEventMinutes      | integer |     ✓     | must be positive                        |
+/// | useTypeWeights           | boolean |     ✓     |                                         |
+/// | sectionalSubstitution    | boolean |     ✓     |                                         |
+/// | rehearsalRequiredForGigs | boolean |     ✓     |                                         |
+/// | rules                    | array   |     ✓     | a [GradingRuleForm] for each event type |
+///
+/// The rules replace all of the semester's existing rules.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradingPolicyForm {
+    pub start_grade: f32,
+    pub max_grade: f32,
+    pub default_event_minutes: i32,
+    pub use_type_weights: bool,
+    pub sectional_substitution: bool,
+    pub rehearsal_required_for_gigs: bool,
+    pub rules: Vec<GradingRuleForm>,
+}
+
+/// The required format for a rule in a [GradingPolicyForm].
+///
+/// ## Expected Format:
+///
+/// |      Field       |  Type   | Required? |                  Comments                   |
+/// |------------------|---------|:---------:|---------------------------------------------|
+/// | eventType        | string  |     ✓     |                                             |
+/// | bonus            | string  |     ✓     | "never", "always", or "whenOptional"        |
+/// | penalizeLateness | boolean |     ✓     |                                             |
+/// | penalizeAbsence  | boolean |     ✓     |                                             |
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradingRuleForm {
+    pub event_type: String,
+    pub bonus: BonusRule,
+    pub penalize_lateness: bool,
+    pub penalize_absence: bool,
+}
+
+/// The model for a semester whose grades have been finalized.
+///
+/// While a semester is finalized, its attendance can't be changed until
+/// an officer reopens it.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE semester_finalization (
+///   semester varchar(32) NOT NULL PRIMARY KEY,
+///   finalized_by varchar(50) DEFAULT NULL,
+///   finalized_at datetime NOT NULL,
+///
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (finalized_by) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "semester": string,
+///     "finalizedBy": string?,
+///     "finalizedAt": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "semester_finalization"]
+#[primary_key(semester)]
+#[serde(rename_all = "camelCase")]
+pub struct SemesterFinalization {
+    /// The semester that was finalized
+    pub semester: String,
+    /// The email of the officer who finalized it (null if they were later deleted)
+    pub finalized_by: Option<String>,
+    /// When the semester was finalized
+    #[serde(with = "naivedatetime_posix")]
+    pub finalized_at: NaiveDateTime,
+}
+
+/// The model for a member's grade as it was when their semester was finalized.
+///
+/// Only members enrolled in the class get a final grade.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE final_grade (
+///   member varchar(50) NOT NULL,
+///   semester varchar(32) NOT NULL,
+///   grade float NOT NULL,
+///   letter varchar(2) NOT NULL,
+///   volunteer_gigs_attended int NOT NULL,
+///   breakdown longtext NOT NULL,
+///
+///   PRIMARY KEY (member, semester),
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "member": string,
+///     "semester": string,
+///     "grade": number,
+///     "letter": string,
+///     "volunteerGigsAttended": integer,
+///     "breakdown": [EventWithGradeChange]
+/// }
+/// ```
+///
+/// The breakdown is the `eventsWithChanges` of the member's
+/// [Grades](crate::db::models::grades::Grades) when they were finalized.
+#[derive(Identifiable, Queryable, Insertable)]
+#[table_name = "final_grade"]
+#[primary_key(member, semester)]
+pub struct FinalGrade {
+    /// The email of the member
+    pub member: String,
+    /// The semester the grade is for
+    pub semester: String,
+    /// The member's final score
+    pub grade: f32,
+    /// The letter grade for the score
+    pub letter: String,
+    /// How many volunteer gigs the member attended
+    pub volunteer_gigs_attended: i32,
+    /// The grade changes for each event, as JSON
+    pub breakdown: String,
+}
+
+/// The model for the record of a semester being finalized or reopened.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE finalization_log (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   semester varchar(32) NOT NULL,
+///   member varchar(50) DEFAULT NULL,
+///   action enum('finalized', 'reopened') NOT NULL,
+///   reason text DEFAULT NULL,
+///   time datetime NOT NULL,
+///
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "semester": string,
+///     "member": string?,
+///     "action": "finalized" | "reopened",
+///     "reason": string?,
+///     "time": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "finalization_log"]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizationLog {
+    /// The ID of the log entry
+    pub id: i32,
+    /// The semester that was finalized or reopened
+    pub semester: String,
+    /// The email of the officer who did it (null if they were later deleted)
+    pub member: Option<String>,
+    /// Whether the semester was finalized or reopened
+    pub action: FinalizationAction,
+    /// Why the semester was reopened
+    pub reason: Option<String>,
+    /// When it happened
+    #[serde(with = "naivedatetime_posix")]
+    pub time: NaiveDateTime,
+}
+
+/// The required format for reopening a finalized semester.
+///
+/// ## Expected Format:
+///
+/// | Field  |  Type  | Required? |              Comments              |
+/// |--------|--------|:---------:|------------------------------------|
+/// | reason | string |     ✓     | why the semester is being reopened |
+#[derive(Deserialize)]
+pub struct ReopenSemesterForm {
+    pub reason: String,
+}
+
 /// The model for roles within the organization (a.k.a. officer positions).
 ///
 /// ## Database Format:
@@ -471,10 +787,12 @@ pub struct EventType {
 ///   gig_count boolean NOT NULL DEFAULT '1',
 ///   default_attend boolean NOT NULL DEFAULT '1',
 ///   section varchar(20) DEFAULT NULL,
+///   series int DEFAULT NULL,
 ///
 ///   FOREIGN KEY (semester) REFERENCES semester (name) ON UPDATE CASCADE ON DELETE CASCADE,
 ///   FOREIGN KEY (`type`) REFERENCES event_type (name) ON UPDATE CASCADE ON DELETE CASCADE,
-///   FOREIGN KEY (section) REFERENCES section_type (name) ON UPDATE CASCADE ON DELETE SET NULL
+///   FOREIGN KEY (section) REFERENCES section_type (name) ON UPDATE CASCADE ON DELETE SET NULL,
+///   FOREIGN KEY (series) REFERENCES event_series (id) ON UPDATE CASCADE ON DELETE SET NULL
 /// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
 /// ```
 ///
@@ -518,6 +836,77 @@ pub struct Event {
     /// this denotes which one (e.g. old sectionals)
     #[serde(deserialize_with = "deser_opt_string")]
     pub section: Option<String>,
+    /// The ID of the [EventSeries](struct.EventSeries.html) this event was
+    /// created as a part of, if it was repeated
+    #[serde(default)]
+    pub series: Option<i32>,
+}
+
+/// The model for a set of events that were created together by repeating one event.
+///
+/// Each of the events in a series is still its own event with its own
+/// attendance, but they can be updated or deleted together
+/// (see [EventScope](event/enum.EventScope.html)).
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE event_series (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   semester varchar(32) NOT NULL,
+///   period enum('no', 'daily', 'weekly', 'biweekly', 'monthly', 'yearly') NOT NULL,
+///   repeat_until date NOT NULL,
+///   created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///   rule varchar(255) DEFAULT NULL,
+///   exceptions text DEFAULT NULL,
+///
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON UPDATE CASCADE ON DELETE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "semester": string,
+///     "period": string,
+///     "repeatUntil": date,
+///     "created": datetime,
+///     "rule": string?,
+///     "exceptions": string?
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "event_series"]
+#[serde(rename_all = "camelCase")]
+pub struct EventSeries {
+    /// The ID of the series
+    pub id: i32,
+    /// The semester the events in the series belong to
+    pub semester: String,
+    /// How often the events repeated when the series was created
+    pub period: Period,
+    /// The last day the events were repeated until
+    #[serde(with = "naivedate_posix")]
+    pub repeat_until: NaiveDate,
+    /// When the series was created
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+    /// The `RRULE` the events repeated by, if they didn't repeat by a period
+    pub rule: Option<String>,
+    /// The days skipped when repeating, like `20200310,20200312`
+    pub exceptions: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "event_series"]
+pub struct NewEventSeries {
+    pub semester: String,
+    pub period: Period,
+    pub repeat_until: NaiveDate,
+    pub rule: Option<String>,
+    pub exceptions: Option<String>,
 }
 
 /// The required format for adding events.
@@ -536,17 +925,30 @@ pub struct Event {
 /// | location      | string   |           |                               |
 /// | gigCount      | boolean  |     ✓     | for volunteer gigs            |
 /// | defaultAttend | boolean  |     ✓     | assume members should go      |
-/// | repeat        | string   |     ✓     | see [Period](event/enum.Period.html) |
+/// | section       | string   |           | only expect this section      |
+/// | repeat        | string   |           | see [Period](event/enum.Period.html) |
 /// | repeatUntil   | datetime |           | needed if `repeat` isn't "no" |
+/// | rrule         | string   |           | an iCalendar `RRULE`, instead of `repeat` |
+/// | exdates       | [string] |           | days to skip, like "20200310" |
+/// | strict        | boolean  |           | fail instead of warning about overlaps |
+///
+/// An `rrule` without a `COUNT` or `UNTIL` repeats until `repeatUntil`.
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewEvent {
     #[serde(flatten)]
     pub fields: NewEventFields,
     pub gig: Option<NewGig>,
+    #[serde(default)]
     pub repeat: Period,
     #[serde(rename = "repeatUntil", with = "optional_naivedate_posix")]
     pub repeat_until: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub rrule: Option<String>,
+    #[serde(default)]
+    pub exdates: Vec<String>,
+    #[serde(default)]
+    pub strict: bool,
 }
 
 #[derive(Insertable, AsChangeset, Deserialize, Clone, Debug)]
@@ -570,16 +972,10 @@ pub struct NewEventFields {
     pub gig_count: Option<bool>,
     #[serde(rename = "defaultAttend")]
     pub default_attend: bool,
-}
-
-#[derive(Deserialize, PartialEq)]
-pub enum Period {
-    No,
-    Daily,
-    Weekly,
-    Biweekly,
-    Monthly,
-    Yearly,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub section: Option<String>,
+    #[serde(skip_deserializing)]
+    pub series: Option<i32>,
 }
 
 /// The required format for updating events.
@@ -608,6 +1004,7 @@ pub enum Period {
 /// | public           | boolean        | for events with gigs | show on external site |
 /// | summary          | string         |                      | public event summary  |
 /// | description      | string         |                      | public event summary  |
+/// | strict           | boolean        |                      | fail on overlaps      |
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct EventUpdate {
@@ -616,6 +1013,93 @@ pub struct EventUpdate {
     pub fields: NewEventFields,
     // gig fields
     pub gig: Option<NewGig>,
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// The model for events that were imported from iCalendar files, so that
+/// importing the same file again doesn't create them twice.
+///
+/// Recurring events are recorded by the first event in their series.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE imported_event (
+///   uid varchar(255) NOT NULL PRIMARY KEY,
+///   event int NOT NULL,
+///   imported timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///
+///   FOREIGN KEY (event) REFERENCES event (id) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "uid": string,
+///     "event": integer,
+///     "imported": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "imported_event"]
+#[primary_key(uid)]
+pub struct ImportedEvent {
+    /// The `UID` of the event in the iCalendar file
+    pub uid: String,
+    /// The ID of the event it was imported as
+    pub event: i32,
+    /// When it was last imported
+    #[serde(with = "naivedatetime_posix")]
+    pub imported: NaiveDateTime,
+}
+
+/// The required format for importing events from an iCalendar file.
+///
+/// ## Expected Format:
+///
+/// |      Field       |  Type   | Required? |                   Comments                    |
+/// |------------------|---------|:---------:|-----------------------------------------------|
+/// | calendar         | string  |     ✓     | the contents of the `.ics` file               |
+/// | semester         | string  |           | defaults to the current semester              |
+/// | categories       | object  |           | category names to CategoryMappings            |
+/// | default          | object  |           | a CategoryMapping for unmapped events         |
+/// | updateDuplicates | boolean |           | update events imported before, or skip them   |
+/// | defaultAttend    | boolean |           | defaults to true                              |
+/// | confirm          | boolean |           | create the events instead of previewing them  |
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventImport {
+    pub calendar: String,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub semester: Option<String>,
+    #[serde(default)]
+    pub categories: HashMap<String, CategoryMapping>,
+    #[serde(default)]
+    pub default: Option<CategoryMapping>,
+    #[serde(default, rename = "updateDuplicates")]
+    pub update_duplicates: bool,
+    #[serde(default, rename = "defaultAttend")]
+    pub default_attend: Option<bool>,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Which type of event, and how many points, imported events in a category become.
+///
+/// ## Expected Format:
+///
+/// | Field  |  Type   | Required? |                Comments                 |
+/// |--------|---------|:---------:|-----------------------------------------|
+/// | type   | string  |     ✓     | the event type                          |
+/// | points | integer |           | defaults to the weight of the type      |
+#[derive(Deserialize, Clone, Debug)]
+pub struct CategoryMapping {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub points: Option<i32>,
 }
 
 /// The model for member's requests for absence from events.
@@ -629,6 +1113,7 @@ pub struct EventUpdate {
 ///   `time` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
 ///   reason varchar(500) NOT NULL,
 ///   state enum('pending', 'approved', 'denied') NOT NULL DEFAULT 'pending',
+///   comment varchar(500) DEFAULT NULL,
 ///
 ///   PRIMARY KEY (member, event),
 ///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
@@ -644,7 +1129,8 @@ pub struct EventUpdate {
 ///     "event": integer,
 ///     "time": datetime,
 ///     "reason": string,
-///     "state": string
+///     "state": string,
+///     "comment": string?
 /// }
 /// ```
 #[derive(Identifiable, Queryable, Serialize, Clone)]
@@ -663,6 +1149,8 @@ pub struct AbsenceRequest {
     pub reason: String,
     /// The current state of the request (See [AbsenceRequestState](enum.AbsenceRequestState.html))
     pub state: AbsenceRequestState,
+    /// A comment from the officer that approved or denied the request
+    pub comment: Option<String>,
 }
 
 /// The required format for new absence requests.
@@ -677,6 +1165,175 @@ pub struct NewAbsenceRequest {
     pub reason: String,
 }
 
+/// The format for approving or denying absence requests.
+///
+/// The body can also be left empty.
+///
+/// |  Field  |  Type  | Required? | Comments                       |
+/// |---------|--------|:---------:|--------------------------------|
+/// | comment | string |           | included in the decision email |
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsenceRequestDecision {
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub comment: Option<String>,
+}
+
+/// The model for a member disputing how their attendance at an event was graded.
+///
+/// Disputes belong to the member's attendance at the event, and are
+/// deleted along with it.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE grade_dispute (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   member varchar(50) NOT NULL,
+///   event int NOT NULL,
+///   message text NOT NULL,
+///   disputed_reason varchar(255) DEFAULT NULL,
+///   disputed_change float DEFAULT NULL,
+///   state enum('open', 'resolved', 'rejected') NOT NULL DEFAULT 'open',
+///   created datetime NOT NULL,
+///
+///   FOREIGN KEY (member, event) REFERENCES attendance (member, event) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "member": string,
+///     "event": integer,
+///     "message": string,
+///     "disputedReason": string?,
+///     "disputedChange": number?,
+///     "state": "open" | "resolved" | "rejected",
+///     "created": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize, Clone)]
+#[table_name = "grade_dispute"]
+#[serde(rename_all = "camelCase")]
+pub struct GradeDispute {
+    /// The ID of the dispute
+    pub id: i32,
+    /// The email of the member disputing their grade
+    pub member: String,
+    /// The ID of the event whose grade change is disputed
+    pub event: i32,
+    /// Why the member thinks the grade change is wrong
+    pub message: String,
+    /// The reason given for the grade change when the dispute was opened
+    pub disputed_reason: Option<String>,
+    /// How many points the grade changed by when the dispute was opened
+    pub disputed_change: Option<f32>,
+    /// Whether the dispute is still open, or how it was closed
+    pub state: GradeDisputeState,
+    /// When the dispute was opened
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+}
+
+/// The model for a comment on a grade dispute, or an officer closing it.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE grade_dispute_entry (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   dispute int NOT NULL,
+///   author varchar(50) DEFAULT NULL,
+///   action enum('commented', 'resolved', 'rejected') NOT NULL,
+///   content text DEFAULT NULL,
+///   time datetime NOT NULL,
+///
+///   FOREIGN KEY (dispute) REFERENCES grade_dispute (id) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (author) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "dispute": integer,
+///     "author": string?,
+///     "action": "commented" | "resolved" | "rejected",
+///     "content": string?,
+///     "time": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "grade_dispute_entry"]
+#[serde(rename_all = "camelCase")]
+pub struct GradeDisputeEntry {
+    /// The ID of the entry
+    pub id: i32,
+    /// The ID of the dispute
+    pub dispute: i32,
+    /// The email of who wrote the entry (null if they were later deleted)
+    pub author: Option<String>,
+    /// Whether this is a comment, or the dispute being resolved or rejected
+    pub action: GradeDisputeAction,
+    /// What the author said, if anything
+    pub content: Option<String>,
+    /// When the entry was made
+    #[serde(with = "naivedatetime_posix")]
+    pub time: NaiveDateTime,
+}
+
+/// The required format for disputing a grade change.
+///
+/// ## Expected Format:
+///
+/// |  Field  |  Type  | Required? |               Comments              |
+/// |---------|--------|:---------:|-------------------------------------|
+/// | message | string |     ✓     | why the grade change might be wrong |
+#[derive(Deserialize)]
+pub struct NewGradeDispute {
+    pub message: String,
+}
+
+/// The required format for commenting on or rejecting a grade dispute.
+///
+/// ## Expected Format:
+///
+/// |  Field  |  Type  | Required? | Comments |
+/// |---------|--------|:---------:|----------|
+/// | content | string |     ✓     |          |
+#[derive(Deserialize)]
+pub struct GradeDisputeComment {
+    pub content: String,
+}
+
+/// The required format for resolving a grade dispute.
+///
+/// ## Expected Format:
+///
+/// |     Field      |      Type      | Required? |                     Comments                    |
+/// |----------------|----------------|:---------:|-------------------------------------------------|
+/// | comment        | string         |           | included in the email to the member             |
+/// | attendance     | AttendanceForm |           | the member's corrected attendance               |
+/// | approveAbsence | boolean        |           | approve an absence for the event, retroactively |
+///
+/// Either `attendance` or `approveAbsence` must be given. See
+/// [AttendanceForm](crate::db::models::AttendanceForm) for its format.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeDisputeResolution {
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub attendance: Option<AttendanceForm>,
+    #[serde(default)]
+    pub approve_absence: bool,
+}
+
 /// The model that records which semesters a member has been active during.
 ///
 /// ## Database Format:
@@ -725,10 +1382,10 @@ pub struct ActiveSemester {
 ///
 /// ## Expected Format:
 ///
-/// |   Field    |  Type  | Required? | Comments |
-/// |------------|--------|:---------:|----------|
-/// | enrollment | string |     ✓     |          |
-/// | section    | string |           |          |
+/// |   Field    |  Type  | Required? | Comments                    |
+/// |------------|--------|:---------:|-----------------------------|
+/// | enrollment | string |     ✓     |                             |
+/// | section    | string |           | left out to clear a section |
 #[derive(Deserialize, AsChangeset)]
 #[table_name = "active_semester"]
 #[serde(rename_all = "camelCase")]
@@ -741,6 +1398,11 @@ pub struct ActiveSemesterUpdate {
 
 /// The model for announcements made to the club.
 ///
+/// Announcements can be scheduled to appear (and disappear) at a later time,
+/// and can be targeted at only the members of a section, enrollment, or
+/// role. If none of those are set, the announcement is for everyone active
+/// in its semester.
+///
 /// ## Database Format:
 ///
 /// ```sql
@@ -751,9 +1413,19 @@ pub struct ActiveSemesterUpdate {
 ///   `time` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
 ///   content longtext NOT NULL,
 ///   archived bool NOT NULL DEFAULT '0',
+///   publish_at datetime DEFAULT NULL,
+///   expires_at datetime DEFAULT NULL,
+///   section varchar(20) DEFAULT NULL,
+///   enrollment enum('class', 'club') DEFAULT NULL,
+///   role varchar(20) DEFAULT NULL,
+///   send_email bool NOT NULL DEFAULT '0',
+///   emailed bool NOT NULL DEFAULT '0',
+///   posted bool NOT NULL DEFAULT '0',
 ///
 ///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE,
-///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE
+///   FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (section) REFERENCES section_type (name) ON DELETE SET NULL ON UPDATE CASCADE,
+///   FOREIGN KEY (role) REFERENCES role (name) ON DELETE SET NULL ON UPDATE CASCADE
 /// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
 /// ```
 ///
@@ -766,7 +1438,15 @@ pub struct ActiveSemesterUpdate {
 ///     "semester": string,
 ///     "time": datetime,
 ///     "content": string,
-///     "archived": boolean
+///     "archived": boolean,
+///     "publishAt": datetime?,
+///     "expiresAt": datetime?,
+///     "section": string?,
+///     "enrollment": ("Class" | "Club")?,
+///     "role": string?,
+///     "sendEmail": boolean,
+///     "emailed": boolean,
+///     "posted": boolean
 /// }
 /// ```
 #[derive(Identifiable, Queryable, Serialize, Deserialize)]
@@ -787,19 +1467,82 @@ pub struct Announcement {
     pub content: String,
     /// Whether an officer archived the announcement
     pub archived: bool,
+    /// When the announcement becomes visible (immediately if null)
+    #[serde(with = "optional_naivedatetime_posix")]
+    pub publish_at: Option<NaiveDateTime>,
+    /// When the announcement stops being visible (never if null)
+    #[serde(with = "optional_naivedatetime_posix")]
+    pub expires_at: Option<NaiveDateTime>,
+    /// Only show the announcement to members of this section
+    pub section: Option<String>,
+    /// Only show the announcement to members with this enrollment
+    pub enrollment: Option<Enrollment>,
+    /// Only show the announcement to members holding this role
+    pub role: Option<String>,
+    /// Whether to email the announcement to its audience when it is published
+    pub send_email: bool,
+    /// Whether the announcement has been emailed to its audience yet
+    pub emailed: bool,
+    /// Whether the announcement has been sent to webhooks yet
+    pub posted: bool,
 }
 
 /// The required format for making new announcements.
 ///
 /// ## Expected Format:
 ///
-/// |  Field  |  Type  | Required? | Comments |
-/// |---------|--------|:---------:|----------|
-/// | content | string |     ✓     |          |
+/// |   Field    |   Type   | Required? | Comments                         |
+/// |------------|----------|:---------:|----------------------------------|
+/// | content    | string   |     ✓     |                                  |
+/// | publishAt  | datetime |           | publish immediately if missing   |
+/// | expiresAt  | datetime |           | never expires if missing         |
+/// | section    | string   |           | only for members of this section |
+/// | enrollment | string   |           | "class" or "club"                |
+/// | role       | string   |           | only for members with this role  |
+/// | sendEmail  | boolean  |           | defaults to false                |
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NewAnnouncement {
     pub content: String,
+    #[serde(default, with = "optional_naivedatetime_posix")]
+    pub publish_at: Option<NaiveDateTime>,
+    #[serde(default, with = "optional_naivedatetime_posix")]
+    pub expires_at: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub section: Option<String>,
+    #[serde(default, deserialize_with = "deser_enrollment")]
+    pub enrollment: Option<Enrollment>,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub send_email: bool,
+}
+
+/// The model for which announcements members have already read.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE announcement_read (
+///   member varchar(50) NOT NULL,
+///   announcement int NOT NULL,
+///   `time` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///
+///   PRIMARY KEY (member, announcement),
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (announcement) REFERENCES announcement (id) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+#[derive(Identifiable, Queryable)]
+#[table_name = "announcement_read"]
+#[primary_key(member, announcement)]
+pub struct AnnouncementRead {
+    /// The email of the member
+    pub member: String,
+    /// The ID of the announcement they read
+    pub announcement: i32,
+    /// When they marked it as read
+    pub time: NaiveDateTime,
 }
 
 /// The model for member attendance.
@@ -813,6 +1556,10 @@ pub struct NewAnnouncement {
 ///   did_attend boolean NOT NULL DEFAULT '0',
 ///   confirmed boolean NOT NULL DEFAULT '0',
 ///   minutes_late int NOT NULL DEFAULT '0',
+///   minutes_left_early int NOT NULL DEFAULT '0',
+///   lateness_excused boolean NOT NULL DEFAULT '0',
+///   early_departure_excused boolean NOT NULL DEFAULT '0',
+///   excuse_reason varchar(255) DEFAULT NULL,
 ///
 ///   PRIMARY KEY (member, event),
 ///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
@@ -828,7 +1575,11 @@ pub struct NewAnnouncement {
 ///     "shouldAttend": boolean,
 ///     "didAttend": boolean,
 ///     "confirmed": boolean,
-///     "minutesLate": integer
+///     "minutesLate": integer,
+///     "minutesLeftEarly": integer,
+///     "latenessExcused": boolean,
+///     "earlyDepartureExcused": boolean,
+///     "excuseReason": string?
 /// }
 /// ```
 #[derive(Identifiable, Queryable, Serialize, Deserialize, PartialEq, Clone)]
@@ -851,18 +1602,32 @@ pub struct Attendance {
     /// How late the member was if they attended
     #[serde(rename = "minutesLate")]
     pub minutes_late: i32,
+    /// How long before release the member left if they attended
+    pub minutes_left_early: i32,
+    /// Whether the member's lateness doesn't count against their grade
+    pub lateness_excused: bool,
+    /// Whether the member leaving early doesn't count against their grade
+    pub early_departure_excused: bool,
+    /// Why the member's lateness or early departure was excused
+    pub excuse_reason: Option<String>,
 }
 
 /// The required format for updating a member's attendance.
 ///
 /// ## Expected Format:
 ///
-/// |    Field     |  Type   | Required? | Comments |
-/// |--------------|---------|:---------:|----------|
-/// | shouldAttend | boolean |     ✓     |          |
-/// | didAttend    | boolean |     ✓     |          |
-/// | minutesLate  | integer |     ✓     |          |
-/// | confirmed    | boolean |     ✓     |          |
+/// |         Field         |  Type   | Required? |                Comments                |
+/// |-----------------------|---------|:---------:|----------------------------------------|
+/// | shouldAttend          | boolean |     ✓     |                                        |
+/// | didAttend             | boolean |     ✓     |                                        |
+/// | minutesLate           | integer |     ✓     |                                        |
+/// | confirmed             | boolean |     ✓     |                                        |
+/// | minutesLeftEarly      | integer |           | left unchanged if missing              |
+/// | latenessExcused       | boolean |           | left unchanged if missing              |
+/// | earlyDepartureExcused | boolean |           | left unchanged if missing              |
+/// | excuseReason          | string  |           | left unchanged if missing, cleared if  |
+/// |                       |         |           | empty or null; required if either      |
+/// |                       |         |           | excuse is given                        |
 #[derive(Deserialize, AsChangeset)]
 #[table_name = "attendance"]
 #[serde(rename_all = "camelCase")]
@@ -874,6 +1639,79 @@ pub struct AttendanceForm {
     #[serde(rename = "minutesLate")]
     pub minutes_late: i32,
     pub confirmed: bool,
+    #[serde(default)]
+    pub minutes_left_early: Option<i32>,
+    #[serde(default)]
+    pub lateness_excused: Option<bool>,
+    #[serde(default)]
+    pub early_departure_excused: Option<bool>,
+    #[serde(default, deserialize_with = "deser_clearable_string")]
+    pub excuse_reason: Option<Option<String>>,
+}
+
+/// The required format for updating many members' attendance at once.
+///
+/// ## Expected Format:
+///
+/// |    Field     |  Type   | Required? |         Comments          |
+/// |--------------|---------|:---------:|---------------------------|
+/// | member       | string  |     ✓     | the email of the member   |
+/// | shouldAttend | boolean |     ✓     |                           |
+/// | didAttend    | boolean |     ✓     |                           |
+/// | minutesLate  | integer |     ✓     |                           |
+/// | confirmed    | boolean |     ✓     |                           |
+///
+/// Along with the optional fields of an [AttendanceForm](crate::db::models::AttendanceForm).
+#[derive(Deserialize)]
+pub struct MemberAttendanceForm {
+    pub member: String,
+    #[serde(flatten)]
+    pub form: AttendanceForm,
+}
+
+/// The required format for importing attendance from a sign-in sheet.
+///
+/// ## Expected Format:
+///
+/// |  Field  |  Type   | Required? |                   Comments                    |
+/// |---------|---------|:---------:|-----------------------------------------------|
+/// | csv     | string  |     ✓     | the contents of the CSV file, with a header   |
+/// | confirm | boolean |           | update attendance instead of previewing it    |
+///
+/// The CSV needs either an "Email" column or name columns ("Name", or
+/// "First Name" and "Last Name"). It can also have an "Attended" column
+/// (everyone listed attended otherwise) and a "Minutes Late" column.
+#[derive(Deserialize)]
+pub struct AttendanceImport {
+    pub csv: String,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// The required format for a hypothetical change to a member's attendance,
+/// used to see what their grade would be.
+///
+/// ## Expected Format:
+///
+/// |      Field       |  Type   | Required? |                  Comments                  |
+/// |------------------|---------|:---------:|--------------------------------------------|
+/// | event            | integer |     ✓     | the id of the event                        |
+/// | didAttend        | boolean |           | leaves attendance as it is if missing      |
+/// | minutesLate      | integer |           | leaves lateness as it is if missing        |
+/// | minutesLeftEarly | integer |           | leaves early departure as it is if missing |
+/// | absenceApproved  | boolean |           | approve or remove an absence request       |
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedAttendance {
+    pub event: i32,
+    #[serde(default)]
+    pub did_attend: Option<bool>,
+    #[serde(default)]
+    pub minutes_late: Option<i32>,
+    #[serde(default)]
+    pub minutes_left_early: Option<i32>,
+    #[serde(default)]
+    pub absence_approved: Option<bool>,
 }
 
 #[derive(Insertable, Deserialize)]
@@ -886,6 +1724,115 @@ pub struct NewAttendance {
     pub member: String,
 }
 
+/// The model for when members can check themselves in to an event.
+///
+/// While the window is open, members check in with a short code derived
+/// from the secret that changes every 30 seconds.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE check_in_window (
+///   event int NOT NULL PRIMARY KEY,
+///   secret varchar(64) NOT NULL,
+///   opened_by varchar(50) NOT NULL,
+///   opened timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///   closes datetime NOT NULL,
+///
+///   FOREIGN KEY (event) REFERENCES event (id) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (opened_by) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "event": integer,
+///     "openedBy": string,
+///     "opened": datetime,
+///     "closes": datetime
+/// }
+/// ```
+///
+/// The secret is never returned.
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "check_in_window"]
+#[primary_key(event)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckInWindow {
+    /// The ID of the event members are checking in to
+    pub event: i32,
+    /// What the rotating codes are generated from
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// The email of the officer who opened the window
+    pub opened_by: String,
+    /// When the window was opened
+    #[serde(with = "naivedatetime_posix")]
+    pub opened: NaiveDateTime,
+    /// When members can no longer check in
+    #[serde(with = "naivedatetime_posix")]
+    pub closes: NaiveDateTime,
+}
+
+/// The format for opening a check-in window.
+///
+/// The body can also be left empty.
+///
+/// |  Field  |  Type   | Required? |                   Comments                    |
+/// |---------|---------|:---------:|-----------------------------------------------|
+/// | minutes | integer |           | how long it stays open, or until the event ends |
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckInWindowForm {
+    #[serde(default)]
+    pub minutes: Option<i64>,
+}
+
+/// The format for members checking in to an event.
+///
+/// | Field |  Type  | Required? |             Comments             |
+/// |-------|--------|:---------:|----------------------------------|
+/// | code  | string |     ✓     | the code shown by the officer    |
+#[derive(Deserialize)]
+pub struct CheckIn {
+    pub code: String,
+}
+
+/// How many wrong codes a member has entered for an event's check-in window.
+///
+/// Members are locked out of a window after too many wrong codes. The
+/// count goes away with the window, so opening a new one starts it over.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE check_in_attempt (
+///   event int NOT NULL,
+///   member varchar(50) NOT NULL,
+///   failures int NOT NULL DEFAULT 0,
+///   last_failure datetime NOT NULL,
+///
+///   PRIMARY KEY (event, member),
+///   FOREIGN KEY (event) REFERENCES check_in_window (event) ON DELETE CASCADE ON UPDATE CASCADE,
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+#[derive(Identifiable, Queryable)]
+#[table_name = "check_in_attempt"]
+#[primary_key(event, member)]
+pub struct CheckInAttempt {
+    /// The ID of the event the member tried to check in to
+    pub event: i32,
+    /// The email of the member
+    pub member: String,
+    /// How many wrong codes they have entered
+    pub failures: i32,
+    /// When they last entered a wrong code
+    pub last_failure: NaiveDateTime,
+}
+
 /// The model for recording who is driving who for events.
 ///
 /// ## Datbase Format:
@@ -1109,7 +2056,7 @@ pub struct NewUniform {
 ///
 /// `Gig`s are not directly serialized, see [to_json](event/struct.EventWithGig.html#method.to_json)
 /// for how `Gig`s get serialized with `Event`s.
-#[derive(Identifiable, Insertable, Queryable, Serialize)]
+#[derive(Identifiable, Insertable, Queryable, Serialize, Clone)]
 #[table_name = "gig"]
 #[primary_key(event)]
 #[serde(rename_all = "camelCase")]
@@ -1920,6 +2867,46 @@ pub struct TransactionBatch {
     pub description: String,
 }
 
+/// The model for members' personal calendar subscriptions.
+///
+/// Anyone with the token can read the member's calendar, so it can be
+/// revoked or replaced with a new one at any time.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE calendar_feed (
+///   member varchar(50) NOT NULL PRIMARY KEY,
+///   token varchar(64) NOT NULL UNIQUE,
+///   created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "member": string,
+///     "token": string,
+///     "created": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "calendar_feed"]
+#[primary_key(member)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    /// The email of the member whose calendar this is
+    pub member: String,
+    /// The secret that identifies the feed in its URL
+    pub token: String,
+    /// When the token was generated
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+}
+
 /// The model for login sessions for members.
 ///
 /// ## Database Format:
@@ -2018,6 +3005,385 @@ pub struct NewValue {
     pub value: String,
 }
 
+/// The model for emails waiting to be sent (or already sent) by the API.
+///
+/// Emails are queued here by request handlers and delivered later by the
+/// `deliver-emails` job, which retries failed deliveries with exponential backoff.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE email_outbox (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   to_address varchar(255) NOT NULL,
+///   subject varchar(255) NOT NULL,
+///   html longtext NOT NULL,
+///   `text` longtext DEFAULT NULL,
+///   reply_to varchar(255) DEFAULT NULL,
+///   status enum('pending', 'sent', 'failed') NOT NULL DEFAULT 'pending',
+///   attempts int NOT NULL DEFAULT '0',
+///   last_error text DEFAULT NULL,
+///   created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///   next_attempt datetime NOT NULL,
+///   sent_at datetime DEFAULT NULL
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "toAddress": string,
+///     "subject": string,
+///     "replyTo": string?,
+///     "status": "Pending" | "Sent" | "Failed",
+///     "attempts": integer,
+///     "lastError": string?,
+///     "created": datetime,
+///     "nextAttempt": datetime,
+///     "sentAt": datetime?
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "email_outbox"]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEmail {
+    /// The ID of the queued email
+    pub id: i32,
+    /// Who the email is being sent to
+    pub to_address: String,
+    /// The subject line of the email
+    pub subject: String,
+    /// The HTML body of the email
+    #[serde(skip_serializing)]
+    pub html: String,
+    /// The optional plain-text body of the email
+    #[serde(skip_serializing)]
+    pub text: Option<String>,
+    /// Where replies should be sent, if not to the sender
+    pub reply_to: Option<String>,
+    /// Whether the email is still waiting to be sent, was sent, or was given up on
+    pub status: EmailStatus,
+    /// How many times delivery has been attempted
+    pub attempts: i32,
+    /// The error from the most recent failed delivery attempt
+    pub last_error: Option<String>,
+    /// When the email was queued
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+    /// The earliest time the next delivery attempt will be made
+    #[serde(with = "naivedatetime_posix")]
+    pub next_attempt: NaiveDateTime,
+    /// When the email was successfully sent
+    #[serde(with = "optional_naivedatetime_posix")]
+    pub sent_at: Option<NaiveDateTime>,
+}
+
+/// The model for files attached to queued emails.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE email_attachment (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   email int NOT NULL,
+///   file_name varchar(255) NOT NULL,
+///   content_type varchar(100) NOT NULL,
+///   content longblob NOT NULL,
+///
+///   FOREIGN KEY (email) REFERENCES email_outbox (id) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+#[derive(Identifiable, Queryable)]
+#[table_name = "email_attachment"]
+pub struct EmailAttachment {
+    /// The ID of the attachment
+    pub id: i32,
+    /// The ID of the queued email it is attached to
+    pub email: i32,
+    /// The name of the file as the recipient will see it
+    pub file_name: String,
+    /// The MIME type of the file
+    pub content_type: String,
+    /// The raw contents of the file
+    pub content: Vec<u8>,
+}
+
+/// The model for how members want to be notified about things.
+///
+/// Members without a saved preference for a category get the
+/// [default](crate::db::NotificationPreference::default_for) for it.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE notification_preference (
+///   member varchar(50) NOT NULL,
+///   category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes', 'officer_digests', 'grade_disputes') NOT NULL,
+///   channel enum('email', 'none') NOT NULL DEFAULT 'email',
+///   lead_time int NOT NULL DEFAULT '0', -- hours
+///
+///   PRIMARY KEY (member, category),
+///   FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "member": string,
+///     "category": "EventReminders" | "Announcements" | "AbsenceDecisions"
+///         | "DuesReminders" | "CarpoolChanges" | "OfficerDigests" | "GradeDisputes",
+///     "channel": "Email" | "None",
+///     "leadTime": integer
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Insertable, Serialize, Clone, Debug)]
+#[table_name = "notification_preference"]
+#[primary_key(member, category)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPreference {
+    /// The email of the member
+    pub member: String,
+    /// What kind of notification this preference is for
+    pub category: NotificationCategory,
+    /// How the member wants to be notified, if at all
+    pub channel: NotificationChannel,
+    /// How many hours in advance to send reminders (only used by event reminders)
+    pub lead_time: i32,
+}
+
+/// The required format for updating a notification preference.
+///
+/// ## Expected Format:
+///
+/// |  Field   |  Type   | Required? | Comments                      |
+/// |----------|---------|:---------:|-------------------------------|
+/// | category | string  |     ✓     | see NotificationPreference    |
+/// | channel  | string  |     ✓     | "Email" or "None"             |
+/// | leadTime | integer |           | hours, defaults to category's |
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPreferenceUpdate {
+    pub category: NotificationCategory,
+    pub channel: NotificationChannel,
+    #[serde(default)]
+    pub lead_time: Option<i32>,
+}
+
+/// The model for the state of scheduled jobs.
+///
+/// Rows are created the first time a job is run. See [jobs](crate::jobs)
+/// for the jobs themselves.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE job (
+///   name varchar(50) NOT NULL PRIMARY KEY,
+///   last_success datetime DEFAULT NULL,
+///   locked_until datetime DEFAULT NULL,
+///   lock_token varchar(36) DEFAULT NULL
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+#[derive(Identifiable, Queryable)]
+#[table_name = "job"]
+#[primary_key(name)]
+pub struct JobState {
+    /// The name of the job
+    pub name: String,
+    /// When the most recent successful run of the job started
+    pub last_success: Option<NaiveDateTime>,
+    /// When the lock held by the current run expires, if the job is running
+    pub locked_until: Option<NaiveDateTime>,
+    /// A token identifying the run holding the lock
+    pub lock_token: Option<String>,
+}
+
+/// The model for the history of scheduled job runs.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE job_run (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   job varchar(50) NOT NULL,
+///   started datetime NOT NULL,
+///   finished datetime DEFAULT NULL,
+///   status enum('running', 'succeeded', 'failed') NOT NULL DEFAULT 'running',
+///   output text DEFAULT NULL,
+///
+///   FOREIGN KEY (job) REFERENCES job (name) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+#[derive(Identifiable, Queryable)]
+#[table_name = "job_run"]
+pub struct JobRun {
+    /// The ID of the run
+    pub id: i32,
+    /// The name of the job that was run
+    pub job: String,
+    /// When the run started
+    pub started: NaiveDateTime,
+    /// When the run finished, if it has
+    pub finished: Option<NaiveDateTime>,
+    /// Whether the run is still going, succeeded, or failed
+    pub status: JobRunStatus,
+    /// What the job reported, or the error it failed with
+    pub output: Option<String>,
+}
+
+/// The model for webhook subscriptions.
+///
+/// Each subscription is sent a signed POST request whenever something it
+/// is subscribed to happens (see [WebhookKind](crate::db::schema::WebhookKind)).
+/// The kinds each webhook is subscribed to are stored in a separate table:
+///
+/// ```sql
+/// CREATE TABLE webhook_kind (
+///   webhook int NOT NULL,
+///   kind enum('event_created', 'event_updated', 'event_deleted', 'gig_request_submitted',
+///     'absence_request_submitted', 'absence_request_decided', 'announcement_posted',
+///     'transaction_created') NOT NULL,
+///
+///   PRIMARY KEY (webhook, kind),
+///   FOREIGN KEY (webhook) REFERENCES webhook (id) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE webhook (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   url varchar(255) NOT NULL,
+///   secret varchar(64) NOT NULL,
+///   active bool NOT NULL DEFAULT '1',
+///   created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "url": string,
+///     "active": boolean,
+///     "created": datetime
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "webhook"]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    /// The ID of the webhook
+    pub id: i32,
+    /// Where the webhook's requests are sent
+    pub url: String,
+    /// The key used to sign the webhook's requests
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Whether requests are currently being sent to the webhook
+    pub active: bool,
+    /// When the webhook was created
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+}
+
+/// The required format for creating or updating webhooks.
+///
+/// ## Expected Format:
+///
+/// | Field  |  Type   | Required? | Comments                                 |
+/// |--------|---------|:---------:|------------------------------------------|
+/// | url    | string  |     ✓     | must be http or https                    |
+/// | kinds  | array   |     ✓     | the kinds of things to send requests for |
+/// | secret | string  |           | generated if missing when creating       |
+/// | active | boolean |           | defaults to true                         |
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewWebhook {
+    pub url: String,
+    pub kinds: Vec<WebhookKind>,
+    #[serde(default, deserialize_with = "deser_opt_string")]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub active: Option<bool>,
+}
+
+/// The model for the log of requests sent to webhooks.
+///
+/// ## Database Format:
+///
+/// ```sql
+/// CREATE TABLE webhook_delivery (
+///   id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+///   webhook int NOT NULL,
+///   kind enum(...) NOT NULL, -- the same as webhook_kind
+///   payload longtext NOT NULL,
+///   status enum('pending', 'delivered', 'failed') NOT NULL DEFAULT 'pending',
+///   attempts int NOT NULL DEFAULT '0',
+///   response_status int DEFAULT NULL,
+///   last_error text DEFAULT NULL,
+///   created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+///   next_attempt datetime NOT NULL,
+///   delivered_at datetime DEFAULT NULL,
+///
+///   FOREIGN KEY (webhook) REFERENCES webhook (id) ON DELETE CASCADE ON UPDATE CASCADE
+/// ) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+/// ```
+///
+/// ## JSON Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "webhook": integer,
+///     "kind": string,
+///     "payload": string,
+///     "status": "Pending" | "Delivered" | "Failed",
+///     "attempts": integer,
+///     "responseStatus": integer?,
+///     "lastError": string?,
+///     "created": datetime,
+///     "nextAttempt": datetime,
+///     "deliveredAt": datetime?
+/// }
+/// ```
+#[derive(Identifiable, Queryable, Serialize)]
+#[table_name = "webhook_delivery"]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    /// The ID of the delivery
+    pub id: i32,
+    /// The ID of the webhook it is being sent to
+    pub webhook: i32,
+    /// What happened
+    pub kind: WebhookKind,
+    /// The exact body of the request
+    pub payload: String,
+    /// Whether the delivery is still waiting to be sent, was delivered, or was given up on
+    pub status: WebhookDeliveryStatus,
+    /// How many times delivery has been attempted
+    pub attempts: i32,
+    /// The HTTP status of the most recent response, if one was received
+    pub response_status: Option<i32>,
+    /// The error from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// When the delivery was queued
+    #[serde(with = "naivedatetime_posix")]
+    pub created: NaiveDateTime,
+    /// The earliest time the next attempt will be made
+    #[serde(with = "naivedatetime_posix")]
+    pub next_attempt: NaiveDateTime,
+    /// When the webhook accepted the delivery
+    #[serde(with = "optional_naivedatetime_posix")]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
 /// Deserialize an Option<String> normally, but Some("") maps to None.
 fn deser_opt_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
@@ -2026,6 +3392,15 @@ where
     Option::<String>::deserialize(deserializer).map(|s| s.filter(|s| s.len() > 0))
 }
 
+/// Deserialize an Option<String> that is present, so that a missing field
+/// can be told apart from one being cleared with null or "".
+fn deser_clearable_string<'de, D>(deserializer: D) -> Result<Option<Option<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deser_opt_string(deserializer).map(Some)
+}
+
 /// Deserialize an Option<Enrollment>.
 fn deser_enrollment<'de, D>(deserializer: D) -> Result<Option<Enrollment>, D::Error>
 where
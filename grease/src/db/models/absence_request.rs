@@ -1,6 +1,7 @@
-use db::schema::{event, AbsenceRequestState};
-use db::{AbsenceRequest, Event, Semester};
+use db::schema::{event, AbsenceRequestState, NotificationCategory, WebhookKind};
+use db::{AbsenceRequest, Event, NotificationPreference, Semester, SemesterFinalization, Webhook};
 use diesel::prelude::*;
+use email::{template::escape, Email};
 use error::*;
 
 impl AbsenceRequest {
@@ -73,29 +74,84 @@ impl AbsenceRequest {
     ) -> GreaseResult<()> {
         use db::schema::absence_request::dsl::*;
 
-        diesel::insert_into(absence_request)
-            .values((
-                event.eq(event_id),
-                member.eq(given_member),
-                reason.eq(given_reason),
-            ))
-            .execute(conn)?;
+        conn.transaction(|| {
+            diesel::insert_into(absence_request)
+                .values((
+                    event.eq(event_id),
+                    member.eq(given_member),
+                    reason.eq(given_reason),
+                ))
+                .execute(conn)?;
+
+            let new_request = Self::load(given_member, event_id, conn)?;
+            Webhook::trigger(WebhookKind::AbsenceRequestSubmitted, &new_request, conn)
+        })
+    }
+
+    /// Approve an absence directly, creating the request if the member
+    /// never made one. Neither the member nor any webhooks are told about
+    /// it, since whatever approved it, like a grade dispute, already does.
+    pub fn record_approved(
+        given_member: &str,
+        event_id: i32,
+        given_reason: &str,
+        given_comment: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        use db::schema::absence_request::dsl::*;
+
+        if Self::load(given_member, event_id, conn)?.is_some() {
+            diesel::update(absence_request.filter(event.eq(event_id).and(member.eq(given_member))))
+                .set((
+                    state.eq(AbsenceRequestState::Approved),
+                    comment.eq(given_comment),
+                ))
+                .execute(conn)?;
+        } else {
+            diesel::insert_into(absence_request)
+                .values((
+                    event.eq(event_id),
+                    member.eq(given_member),
+                    reason.eq(given_reason),
+                    state.eq(AbsenceRequestState::Approved),
+                    comment.eq(given_comment),
+                ))
+                .execute(conn)?;
+        }
 
         Ok(())
     }
 
-    pub fn approve(member: &str, event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
-        Self::set_request_state(member, event_id, AbsenceRequestState::Approved, conn)
+    pub fn approve(
+        member: &str,
+        event_id: i32,
+        comment: Option<String>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        Self::set_request_state(
+            member,
+            event_id,
+            AbsenceRequestState::Approved,
+            comment,
+            conn,
+        )
     }
 
-    pub fn deny(member: &str, event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
-        Self::set_request_state(member, event_id, AbsenceRequestState::Denied, conn)
+    pub fn deny(
+        member: &str,
+        event_id: i32,
+        comment: Option<String>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        Self::set_request_state(member, event_id, AbsenceRequestState::Denied, comment, conn)
     }
 
+    /// Decide on a request and let the member know about it.
     fn set_request_state(
         given_member: &str,
         event_id: i32,
         given_state: AbsenceRequestState,
+        given_comment: Option<String>,
         conn: &MysqlConnection,
     ) -> GreaseResult<()> {
         use db::schema::absence_request::dsl::*;
@@ -106,12 +162,63 @@ impl AbsenceRequest {
                 given_member, event_id
             )),
         )?;
+        let decided_event = Event::load(event_id, conn)?.event;
+
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&decided_event.semester, conn)?;
+            diesel::update(absence_request.filter(event.eq(event_id).and(member.eq(given_member))))
+                .set((state.eq(&given_state), comment.eq(&given_comment)))
+                .execute(conn)
+                .map_err(GreaseError::DbError)?;
+
+            let email = Self::decision_email(&decided_event, &given_state, &given_comment);
+            NotificationPreference::notify(
+                given_member,
+                NotificationCategory::AbsenceDecisions,
+                email,
+                conn,
+            )?;
+
+            let decided_request = Self::load(given_member, event_id, conn)?;
+            Webhook::trigger(WebhookKind::AbsenceRequestDecided, &decided_request, conn)
+        })
+    }
 
-        diesel::update(absence_request.filter(event.eq(event_id).and(member.eq(given_member))))
-            .set(state.eq(given_state))
-            .execute(conn)
-            .map_err(GreaseError::DbError)?;
-
-        Ok(())
+    fn decision_email(
+        decided_event: &Event,
+        decision: &AbsenceRequestState,
+        officer_comment: &Option<String>,
+    ) -> Email {
+        let decision = match decision {
+            AbsenceRequestState::Approved => "Approved",
+            AbsenceRequestState::Denied => "Denied",
+            AbsenceRequestState::Pending => "Reopened",
+        };
+        let officer_comment = officer_comment
+            .as_ref()
+            .map(|officer_comment| {
+                format!(
+                    "<p>The officer left a comment: <i>{}</i></p>",
+                    escape(officer_comment)
+                )
+            })
+            .unwrap_or_default();
+
+        Email::new(
+            String::new(),
+            format!("Absence Request {} for {}", decision, decided_event.name),
+            format!(
+                "\
+                 <p>Your absence request for <a href=\"https://gleeclub.gatech.edu/glubhub/#/events/{}\">{}</a> \
+                 on {} was {}.</p>\
+                 {}\
+                 ",
+                decided_event.id,
+                escape(&decided_event.name),
+                decided_event.call_time.format("%A, %B %-d"),
+                decision.to_lowercase(),
+                officer_comment
+            ),
+        )
     }
 }
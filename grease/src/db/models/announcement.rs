@@ -0,0 +1,437 @@
+use chrono::{Local, NaiveDateTime};
+use db::models::member::MemberForSemester;
+use db::schema::{
+    active_semester, announcement, announcement_read, member, member_role, NotificationCategory,
+    WebhookKind,
+};
+use db::{Announcement, Member, NewAnnouncement, NotificationPreference, Webhook};
+use diesel::prelude::*;
+use email::{template::escape, Email};
+use error::*;
+use serde::Serialize;
+
+/// An announcement, along with whether a member has read it yet.
+#[derive(Serialize)]
+pub struct MemberAnnouncement {
+    #[serde(flatten)]
+    pub announcement: Announcement,
+    pub read: bool,
+}
+
+impl Announcement {
+    pub fn load(announcement_id: i32, conn: &MysqlConnection) -> GreaseResult<Announcement> {
+        announcement::table
+            .filter(announcement::id.eq(announcement_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No announcement with id {}.",
+                announcement_id
+            )))
+    }
+
+    pub fn insert(
+        new_announcement: &NewAnnouncement,
+        given_member: &str,
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<i32> {
+        if let Some(expires_at) = new_announcement.expires_at {
+            let publish_at = new_announcement
+                .publish_at
+                .unwrap_or_else(|| Local::now().naive_local());
+            if expires_at <= publish_at {
+                return Err(GreaseError::BadRequest(
+                    "Announcements must expire after they are published.".to_owned(),
+                ));
+            }
+        }
+
+        conn.transaction(|| {
+            diesel::insert_into(announcement::table)
+                .values((
+                    announcement::member.eq(given_member),
+                    announcement::semester.eq(given_semester),
+                    announcement::content.eq(&new_announcement.content),
+                    announcement::publish_at.eq(&new_announcement.publish_at),
+                    announcement::expires_at.eq(&new_announcement.expires_at),
+                    announcement::section.eq(&new_announcement.section),
+                    announcement::enrollment.eq(&new_announcement.enrollment),
+                    announcement::role.eq(&new_announcement.role),
+                    announcement::send_email.eq(new_announcement.send_email),
+                ))
+                .execute(conn)?;
+
+            announcement::table
+                .select(announcement::id)
+                .order_by(announcement::id.desc())
+                .first(conn)
+                .map_err(GreaseError::DbError)
+        })
+    }
+
+    pub fn load_all(conn: &MysqlConnection) -> GreaseResult<Vec<Announcement>> {
+        announcement::table
+            .order_by(announcement::time.desc())
+            .load(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    pub fn load_all_for_semester(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<Announcement>> {
+        announcement::table
+            .filter(
+                announcement::semester
+                    .eq(given_semester)
+                    .and(announcement::archived.eq(false)),
+            )
+            .order_by(announcement::time.desc())
+            .load(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Load the announcements for the semester that are currently shown to
+    /// the given member, along with whether they've read each one.
+    pub fn load_visible_for_member(
+        given_member: &MemberForSemester,
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<MemberAnnouncement>> {
+        let now = Local::now().naive_local();
+        let roles = member_role::table
+            .filter(member_role::member.eq(&given_member.member.email))
+            .select(member_role::role)
+            .load::<String>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(
+            Self::load_all_for_member(&given_member.member.email, given_semester, conn)?
+                .into_iter()
+                .filter(|member_announcement| {
+                    let announcement = &member_announcement.announcement;
+                    announcement.is_visible_at(now) && announcement.is_for(given_member, &roles)
+                })
+                .collect(),
+        )
+    }
+
+    /// Load every announcement for the semester that isn't archived, including
+    /// ones that are scheduled, expired, or targeted at other members, along
+    /// with whether the given member has read each one.
+    pub fn load_all_for_member(
+        given_member: &str,
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<MemberAnnouncement>> {
+        let read_ids = announcement_read::table
+            .filter(announcement_read::member.eq(given_member))
+            .select(announcement_read::announcement)
+            .load::<i32>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(Self::load_all_for_semester(given_semester, conn)?
+            .into_iter()
+            .map(|announcement| MemberAnnouncement {
+                read: read_ids.contains(&announcement.id),
+                announcement,
+            })
+            .collect())
+    }
+
+    pub fn archive(announcement_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        Self::load(announcement_id, conn)?;
+
+        diesel::update(announcement::table.filter(announcement::id.eq(announcement_id)))
+            .set(announcement::archived.eq(true))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    pub fn mark_read(
+        announcement_id: i32,
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        Self::load(announcement_id, conn)?;
+
+        diesel::insert_or_ignore_into(announcement_read::table)
+            .values((
+                announcement_read::member.eq(given_member),
+                announcement_read::announcement.eq(announcement_id),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Whether the announcement has been published and hasn't expired yet.
+    pub fn is_visible_at(&self, time: NaiveDateTime) -> bool {
+        self.publish_at
+            .map(|publish_at| publish_at <= time)
+            .unwrap_or(true)
+            && self
+                .expires_at
+                .map(|expires_at| expires_at > time)
+                .unwrap_or(true)
+    }
+
+    /// Whether the announcement isn't targeted at a section, enrollment, or role.
+    pub fn is_for_everyone(&self) -> bool {
+        self.section.is_none() && self.enrollment.is_none() && self.role.is_none()
+    }
+
+    /// Whether the member is in the announcement's audience, given the roles they hold.
+    ///
+    /// Announcements without a target are for everyone.
+    pub fn is_for(&self, given_member: &MemberForSemester, roles: &Vec<String>) -> bool {
+        if self.is_for_everyone() {
+            return true;
+        }
+
+        given_member
+            .active_semester
+            .as_ref()
+            .filter(|active_semester| active_semester.semester == self.semester)
+            .map(|active_semester| {
+                self.section
+                    .as_ref()
+                    .map(|section| active_semester.section.as_ref() == Some(section))
+                    .unwrap_or(true)
+                    && self
+                        .enrollment
+                        .map(|enrollment| active_semester.enrollment == enrollment)
+                        .unwrap_or(true)
+                    && self
+                        .role
+                        .as_ref()
+                        .map(|role| roles.contains(role))
+                        .unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Load every member active in the announcement's semester that it targets.
+    pub fn load_audience(&self, conn: &MysqlConnection) -> GreaseResult<Vec<Member>> {
+        let mut query = member::table
+            .inner_join(active_semester::table)
+            .filter(active_semester::semester.eq(&self.semester))
+            .select(member::all_columns)
+            .into_boxed();
+        if let Some(section) = &self.section {
+            query = query.filter(active_semester::section.eq(section));
+        }
+        if let Some(enrollment) = self.enrollment {
+            query = query.filter(active_semester::enrollment.eq(enrollment));
+        }
+        if let Some(role) = &self.role {
+            query = query.filter(
+                member::email.eq_any(
+                    member_role::table
+                        .filter(member_role::role.eq(role))
+                        .select(member_role::member),
+                ),
+            );
+        }
+
+        query
+            .order_by((member::last_name.asc(), member::first_name.asc()))
+            .load(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Email the announcement to everyone in its audience who wants announcements,
+    /// returning how many emails were queued.
+    ///
+    /// The announcement is marked as emailed first, so it is never sent twice.
+    pub fn email_audience(&self, conn: &MysqlConnection) -> GreaseResult<usize> {
+        conn.transaction(|| {
+            let claimed = diesel::update(
+                announcement::table.filter(
+                    announcement::id
+                        .eq(self.id)
+                        .and(announcement::emailed.eq(false)),
+                ),
+            )
+            .set(announcement::emailed.eq(true))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+            if claimed == 0 {
+                return Ok(0);
+            }
+
+            let mut queued = 0;
+            for recipient in self.load_audience(conn)? {
+                if NotificationPreference::notify(
+                    &recipient.email,
+                    NotificationCategory::Announcements,
+                    self.build_email(),
+                    conn,
+                )? {
+                    queued += 1;
+                }
+            }
+
+            Ok(queued)
+        })
+    }
+
+    /// Email every announcement that has been published but not sent yet,
+    /// returning how many emails were queued.
+    pub fn email_published(conn: &MysqlConnection) -> GreaseResult<usize> {
+        let now = Local::now().naive_local();
+        let unsent = announcement::table
+            .filter(
+                announcement::send_email
+                    .eq(true)
+                    .and(announcement::emailed.eq(false))
+                    .and(announcement::archived.eq(false)),
+            )
+            .load::<Announcement>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        unsent
+            .into_iter()
+            .filter(|announcement| announcement.is_visible_at(now))
+            .map(|announcement| announcement.email_audience(conn))
+            .sum()
+    }
+
+    /// Whether the announcement should be sent to webhooks at the given time.
+    ///
+    /// Only announcements for everyone are sent, once they are published,
+    /// since webhooks can't tell who is in an announcement's audience.
+    pub fn should_post_at(&self, time: NaiveDateTime) -> bool {
+        !self.posted && !self.archived && self.is_for_everyone() && self.is_visible_at(time)
+    }
+
+    /// Send the announcement to the webhooks subscribed to new announcements,
+    /// returning whether it was sent.
+    ///
+    /// The announcement is marked as posted first, so it is never sent twice.
+    pub fn post_to_webhooks(&self, conn: &MysqlConnection) -> GreaseResult<bool> {
+        conn.transaction(|| {
+            let claimed = diesel::update(
+                announcement::table.filter(
+                    announcement::id
+                        .eq(self.id)
+                        .and(announcement::posted.eq(false)),
+                ),
+            )
+            .set(announcement::posted.eq(true))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+            if claimed == 0 {
+                return Ok(false);
+            }
+
+            let posted_announcement = Self::load(self.id, conn)?;
+            Webhook::trigger(WebhookKind::AnnouncementPosted, &posted_announcement, conn)?;
+
+            Ok(true)
+        })
+    }
+
+    /// Send every announcement that has been published but not posted yet
+    /// to webhooks, returning how many were sent.
+    pub fn post_published(conn: &MysqlConnection) -> GreaseResult<usize> {
+        let now = Local::now().naive_local();
+        let unposted = announcement::table
+            .filter(
+                announcement::posted
+                    .eq(false)
+                    .and(announcement::archived.eq(false)),
+            )
+            .load::<Announcement>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        let mut posted = 0;
+        for announcement in unposted {
+            if announcement.should_post_at(now) && announcement.post_to_webhooks(conn)? {
+                posted += 1;
+            }
+        }
+
+        Ok(posted)
+    }
+
+    fn build_email(&self) -> Email {
+        let mut email = Email::new(
+            String::new(),
+            "New Announcement from the Glee Club".to_owned(),
+            format!(
+                "\
+                 <p>{}</p>\
+                 <p>See all announcements <a href=\"https://gleeclub.gatech.edu/glubhub/\">on the site</a>.</p>\
+                 ",
+                escape(&self.content).replace('\n', "<br>")
+            ),
+        );
+        email.text = Some(self.content.clone());
+
+        email
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn announcement(publish_at: Option<NaiveDateTime>) -> Announcement {
+        Announcement {
+            id: 7,
+            member: Some("officer@gatech.edu".to_owned()),
+            semester: "Fall 2019".to_owned(),
+            time: Local::now().naive_local(),
+            content: "Concert dress is all black.".to_owned(),
+            archived: false,
+            publish_at,
+            expires_at: None,
+            section: None,
+            enrollment: None,
+            role: None,
+            send_email: false,
+            emailed: false,
+            posted: false,
+        }
+    }
+
+    #[test]
+    fn scheduled_announcements_are_not_posted_until_published() {
+        let now = Local::now().naive_local();
+        let publish_at = now + Duration::hours(2);
+        let scheduled = announcement(Some(publish_at));
+
+        assert!(!scheduled.should_post_at(now));
+        assert!(!scheduled.should_post_at(publish_at - Duration::seconds(1)));
+        assert!(scheduled.should_post_at(publish_at));
+    }
+
+    #[test]
+    fn immediate_announcements_are_posted_once() {
+        let now = Local::now().naive_local();
+        let mut immediate = announcement(None);
+
+        assert!(immediate.should_post_at(now));
+        immediate.posted = true;
+        assert!(!immediate.should_post_at(now));
+    }
+
+    #[test]
+    fn targeted_and_archived_announcements_are_never_posted() {
+        let now = Local::now().naive_local();
+        let mut targeted = announcement(None);
+        targeted.section = Some("Tenor".to_owned());
+        let mut archived = announcement(None);
+        archived.archived = true;
+
+        assert!(!targeted.should_post_at(now));
+        assert!(!archived.should_post_at(now));
+    }
+}
@@ -7,12 +7,22 @@ use db::schema::{
     absence_request, active_semester, event, gig, member as member_dsl, AbsenceRequestState,
 };
 use db::{
-    AbsenceRequest, ActiveSemester, Attendance, AttendanceForm, Event, Gig, Member, NewAttendance,
+    AbsenceRequest, ActiveSemester, Attendance, AttendanceForm, Event, Gig, Member,
+    MemberAttendanceForm, NewAttendance, SemesterFinalization,
 };
 use diesel::prelude::*;
 use error::*;
 use serde::Serialize;
 
+/// Why one row of a bulk attendance update couldn't be applied.
+#[derive(Serialize)]
+pub struct AttendanceRowError {
+    /// The index of the update in the list, or the line of the CSV file
+    pub row: usize,
+    pub member: String,
+    pub error: String,
+}
+
 #[derive(Serialize)]
 pub struct MemberAttendance {
     #[serde(flatten)]
@@ -37,21 +47,6 @@ impl Attendance {
             .map_err(GreaseError::DbError)
     }
 
-    fn ensure_exists_for_member_at_event(
-        given_member: &str,
-        given_event: i32,
-        conn: &MysqlConnection,
-    ) -> GreaseResult<()> {
-        if Self::load(given_member, given_event, conn)?.is_some() {
-            Ok(())
-        } else {
-            Err(GreaseError::BadRequest(format!(
-                "No attendance exists for member {} at event {}. (Are they inactive?)",
-                given_member, given_event
-            )))
-        }
-    }
-
     pub fn load_for_event(
         event_id: i32,
         conn: &MysqlConnection,
@@ -161,8 +156,21 @@ impl Attendance {
             .collect())
     }
 
+    /// Whether a member in the given section is expected at an event unless
+    /// told otherwise. Events for a single section only expect its members.
+    pub fn expected_by_default(given_event: &Event, member_section: Option<&str>) -> bool {
+        given_event.default_attend
+            && match &given_event.section {
+                Some(event_section) => member_section == Some(event_section.as_str()),
+                None => true,
+            }
+    }
+
     pub fn create_for_new_member(given_member: &str, conn: &MysqlConnection) -> GreaseResult<()> {
         let now = Local::now().naive_local();
+        let current_semester = Semester::load_current(conn)?;
+        let member_section = ActiveSemester::load(given_member, &current_semester.name, conn)?
+            .and_then(|active_semester| active_semester.section);
         let all_events_for_semester = Event::load_all_for_current_semester(conn)?;
         let new_attendances = all_events_for_semester
             .into_iter()
@@ -171,7 +179,10 @@ impl Attendance {
                 should_attend: if now > event_with_gig.event.call_time {
                     false
                 } else {
-                    event_with_gig.event.default_attend
+                    Self::expected_by_default(
+                        &event_with_gig.event,
+                        member_section.as_ref().map(|section| section.as_str()),
+                    )
                 },
                 member: given_member.to_owned(),
             })
@@ -194,8 +205,11 @@ impl Attendance {
                     .into_iter()
                     .map(|member_for_semester| NewAttendance {
                         event: event_id,
+                        should_attend: Self::expected_by_default(
+                            &parent_event,
+                            member_for_semester.section(),
+                        ),
                         member: member_for_semester.member.email,
-                        should_attend: parent_event.default_attend,
                     })
                     .collect::<Vec<NewAttendance>>(),
             )
@@ -204,27 +218,197 @@ impl Attendance {
         Ok(())
     }
 
-    pub fn excuse_unconfirmed(event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
-        diesel::update(attendance.filter(event.eq(event_id).and(confirmed.eq(false))))
-            .set(should_attend.eq(false))
+    /// After a member changes sections, recompute whether they are expected
+    /// at the upcoming events in the semester that are for a single section.
+    ///
+    /// Only events where the change of section changes whether they are
+    /// expected by default are updated, and attendance the member or an
+    /// officer has confirmed is left alone.
+    pub fn recompute_for_member(
+        given_member: &str,
+        given_semester: &str,
+        old_section: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let member_section = ActiveSemester::load(given_member, given_semester, conn)?
+            .and_then(|active_semester| active_semester.section);
+        let member_section = member_section.as_ref().map(|section| section.as_str());
+        let (expected, not_expected): (Vec<Event>, Vec<Event>) = event::table
+            .filter(event::semester.eq(given_semester))
+            .filter(event::call_time.gt(Local::now().naive_local()))
+            .filter(event::section.is_not_null())
+            .load::<Event>(conn)?
+            .into_iter()
+            .filter(|section_event| {
+                Self::expected_by_default(section_event, member_section)
+                    != Self::expected_by_default(section_event, old_section)
+            })
+            .partition(|section_event| Self::expected_by_default(section_event, member_section));
+
+        for (events, expectation) in vec![(expected, true), (not_expected, false)] {
+            let event_ids = events
+                .iter()
+                .map(|section_event| section_event.id)
+                .collect::<Vec<_>>();
+            diesel::update(
+                attendance.filter(
+                    member
+                        .eq(given_member)
+                        .and(event.eq_any(event_ids))
+                        .and(confirmed.eq(false)),
+                ),
+            )
+            .set(should_attend.eq(expectation))
             .execute(conn)?;
+        }
 
         Ok(())
     }
 
+    /// After an event changes sections, recompute which members are expected
+    /// at it, unless it has already happened.
+    ///
+    /// Like [recompute_for_member](Attendance::recompute_for_member), only
+    /// members whose default expectation changed and who haven't confirmed
+    /// their attendance are updated.
+    pub fn recompute_for_event(
+        event_id: i32,
+        old_section: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let parent_event = Event::load(event_id, conn)?.event;
+        if parent_event.call_time <= Local::now().naive_local() {
+            return Ok(());
+        }
+        let old_event = Event {
+            section: old_section.map(|section| section.to_owned()),
+            ..parent_event.clone()
+        };
+
+        let (expected, not_expected): (Vec<MemberForSemester>, Vec<MemberForSemester>) =
+            MemberForSemester::load_all(&parent_event.semester, conn)?
+                .into_iter()
+                .filter(|member_for_semester| {
+                    Self::expected_by_default(&parent_event, member_for_semester.section())
+                        != Self::expected_by_default(&old_event, member_for_semester.section())
+                })
+                .partition(|member_for_semester| {
+                    Self::expected_by_default(&parent_event, member_for_semester.section())
+                });
+
+        for (members, expectation) in vec![(expected, true), (not_expected, false)] {
+            let emails = members
+                .into_iter()
+                .map(|member_for_semester| member_for_semester.member.email)
+                .collect::<Vec<_>>();
+            diesel::update(
+                attendance.filter(
+                    event
+                        .eq(event_id)
+                        .and(member.eq_any(emails))
+                        .and(confirmed.eq(false)),
+                ),
+            )
+            .set(should_attend.eq(expectation))
+            .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check a row of a bulk update, returning what's wrong with it, if
+    /// anything.
+    pub fn check_row(
+        event_id: i32,
+        row: &MemberAttendanceForm,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Option<String>> {
+        match Self::load(&row.member, event_id, conn)? {
+            Some(existing) => Ok(Self::check_form(&row.form, &existing)),
+            None => Ok(Some(format!(
+                "No attendance exists for member {} at event {}. (Are they inactive?)",
+                row.member, event_id
+            ))),
+        }
+    }
+
+    /// What's wrong with an attendance form, if anything, once its missing
+    /// fields are filled in from the existing attendance.
+    fn check_form(form: &AttendanceForm, existing: &Attendance) -> Option<String> {
+        let left_early = form
+            .minutes_left_early
+            .unwrap_or(existing.minutes_left_early);
+        let excused_late = form.lateness_excused.unwrap_or(existing.lateness_excused);
+        let excused_early = form
+            .early_departure_excused
+            .unwrap_or(existing.early_departure_excused);
+        let has_reason = form
+            .excuse_reason
+            .as_ref()
+            .unwrap_or(&existing.excuse_reason)
+            .is_some();
+
+        if form.minutes_late < 0 || left_early < 0 {
+            Some("Minutes late or left early can't be negative.".to_owned())
+        } else if (excused_late || excused_early) && !has_reason {
+            Some("Excusing lateness or leaving early needs a reason.".to_owned())
+        } else {
+            None
+        }
+    }
+
+    /// Update many members' attendance at an event in one transaction.
+    pub fn update_many(
+        event_id: i32,
+        rows: &Vec<MemberAttendanceForm>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        conn.transaction(|| {
+            SemesterFinalization::ensure_event_not_finalized(event_id, conn)?;
+            for row in rows {
+                diesel::update(attendance.filter(member.eq(&row.member).and(event.eq(event_id))))
+                    .set(&row.form)
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    pub fn excuse_unconfirmed(event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        conn.transaction(|| {
+            SemesterFinalization::ensure_event_not_finalized(event_id, conn)?;
+            diesel::update(attendance.filter(event.eq(event_id).and(confirmed.eq(false))))
+                .set(should_attend.eq(false))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
     pub fn update(
         event_id: i32,
         given_member: &str,
         attendance_form: &AttendanceForm,
         conn: &MysqlConnection,
     ) -> GreaseResult<()> {
-        Self::ensure_exists_for_member_at_event(given_member, event_id, conn)?;
+        let existing =
+            Self::load(given_member, event_id, conn)?.ok_or(GreaseError::BadRequest(format!(
+                "No attendance exists for member {} at event {}. (Are they inactive?)",
+                given_member, event_id
+            )))?;
+        if let Some(problem) = Self::check_form(attendance_form, &existing) {
+            return Err(GreaseError::BadRequest(problem));
+        }
 
-        diesel::update(attendance.filter(member.eq(given_member).and(event.eq(event_id))))
-            .set(attendance_form)
-            .execute(conn)?;
+        conn.transaction(|| {
+            SemesterFinalization::ensure_event_not_finalized(event_id, conn)?;
+            diesel::update(attendance.filter(member.eq(given_member).and(event.eq(event_id))))
+                .set(attendance_form)
+                .execute(conn)?;
 
-        Ok(())
+            Ok(())
+        })
     }
 }
 
@@ -267,4 +451,39 @@ impl MemberAttendance {
             .map(|a| a.minutes_late)
             .unwrap_or(0)
     }
+
+    pub fn minutes_left_early(&self) -> i32 {
+        self.attendance
+            .as_ref()
+            .map(|a| a.minutes_left_early)
+            .unwrap_or(0)
+    }
+
+    /// How many minutes of the event were missed by arriving late or
+    /// leaving early, whether or not they were excused.
+    pub fn minutes_missed(&self) -> i32 {
+        self.minutes_late() + self.minutes_left_early()
+    }
+
+    pub fn unexcused_minutes_late(&self) -> i32 {
+        self.attendance
+            .as_ref()
+            .filter(|a| !a.lateness_excused)
+            .map(|a| a.minutes_late)
+            .unwrap_or(0)
+    }
+
+    pub fn unexcused_minutes_left_early(&self) -> i32 {
+        self.attendance
+            .as_ref()
+            .filter(|a| !a.early_departure_excused)
+            .map(|a| a.minutes_left_early)
+            .unwrap_or(0)
+    }
+
+    pub fn excuse_reason(&self) -> Option<&str> {
+        self.attendance
+            .as_ref()
+            .and_then(|a| a.excuse_reason.as_ref().map(|reason| reason.as_str()))
+    }
 }
@@ -0,0 +1,285 @@
+use csv::{ReaderBuilder, StringRecord, Trim};
+use db::models::member::MemberForSemester;
+use db::{Attendance, AttendanceForm, AttendanceImport, MemberAttendanceForm};
+use diesel::MysqlConnection;
+use error::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// A row of a sign-in sheet that was matched to a member.
+pub struct MatchedRow {
+    /// The line of the CSV file the row was on
+    pub row: usize,
+    pub attendance: Attendance,
+    pub member: MemberForSemester,
+    pub did_attend: bool,
+    pub minutes_late: i32,
+}
+
+/// A row of a sign-in sheet that couldn't be matched to a member.
+#[derive(Serialize)]
+pub struct UnmatchedRow {
+    /// The line of the CSV file the row was on
+    pub row: usize,
+    pub values: Vec<String>,
+    pub reason: String,
+}
+
+/// Where each kind of value is in the rows of a sign-in sheet.
+struct Columns {
+    email: Option<usize>,
+    name: Option<usize>,
+    first_name: Option<usize>,
+    last_name: Option<usize>,
+    attended: Option<usize>,
+    minutes_late: Option<usize>,
+}
+
+impl Columns {
+    fn from_headers(headers: &StringRecord) -> GreaseResult<Columns> {
+        let find = |matches: &dyn Fn(&str) -> bool| {
+            headers
+                .iter()
+                .position(|header| matches(&header.trim().to_lowercase()))
+        };
+        let columns = Columns {
+            email: find(&|header| header.contains("email")),
+            name: find(&|header| header == "name" || header == "full name" || header == "member"),
+            first_name: find(&|header| header.starts_with("first")),
+            last_name: find(&|header| header.starts_with("last")),
+            attended: find(&|header| header.contains("attend") || header == "present"),
+            minutes_late: find(&|header| header.contains("late")),
+        };
+
+        if columns.email.is_none()
+            && columns.name.is_none()
+            && (columns.first_name.is_none() || columns.last_name.is_none())
+        {
+            Err(GreaseError::BadRequest(
+                "The CSV needs an \"Email\" column, a \"Name\" column, \
+                 or \"First Name\" and \"Last Name\" columns."
+                    .to_owned(),
+            ))
+        } else {
+            Ok(columns)
+        }
+    }
+
+    fn name(&self, record: &StringRecord) -> Option<String> {
+        let get = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        match (get(self.name), get(self.first_name), get(self.last_name)) {
+            // sign-in sheets often list people as "Last, First"
+            (Some(name), _, _) => Some(match name.find(',') {
+                Some(comma) => format!("{} {}", name[comma + 1..].trim(), name[..comma].trim()),
+                None => name,
+            }),
+            (None, Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+            _ => None,
+        }
+    }
+}
+
+impl AttendanceImport {
+    /// Match each row of the sign-in sheet to a member with attendance at
+    /// the event, by email if there is one and by name otherwise.
+    pub fn match_rows(
+        &self,
+        event_id: i32,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<(Vec<MatchedRow>, Vec<UnmatchedRow>)> {
+        let mut reader = ReaderBuilder::new()
+            .flexible(true)
+            .trim(Trim::All)
+            .from_reader(self.csv.as_bytes());
+        let columns =
+            Columns::from_headers(reader.headers().map_err(|err| {
+                GreaseError::BadRequest(format!("Couldn't read the CSV: {}", err))
+            })?)?;
+        let candidates = Attendance::load_for_event(event_id, conn)?;
+
+        // the row, the index of the member in the candidates, and their attendance
+        let mut matches: Vec<(usize, usize, bool, i32)> = Vec::new();
+        let mut unmatched = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record.map_err(|err| {
+                GreaseError::BadRequest(format!("Couldn't read the CSV: {}", err))
+            })?;
+            // the header is on the first line
+            let row = index + 2;
+            if record.iter().all(|value| value.is_empty()) {
+                continue;
+            }
+
+            let mut unmatch = |reason: String| {
+                unmatched.push(UnmatchedRow {
+                    row,
+                    values: record.iter().map(|value| value.to_owned()).collect(),
+                    reason,
+                })
+            };
+            let (did_attend, minutes_late) = match Self::parse_values(&columns, &record) {
+                Ok(values) => values,
+                Err(reason) => {
+                    unmatch(reason);
+                    continue;
+                }
+            };
+            let position = match Self::find_member(&columns, &record, &candidates) {
+                Ok(position) => position,
+                Err(reason) => {
+                    unmatch(reason);
+                    continue;
+                }
+            };
+            if let Some((earlier_row, ..)) = matches
+                .iter()
+                .find(|(_row, earlier_position, ..)| *earlier_position == position)
+            {
+                unmatch(format!(
+                    "{} was already on row {}.",
+                    candidates[position].1.member.full_name(),
+                    earlier_row
+                ));
+                continue;
+            }
+
+            matches.push((row, position, did_attend, minutes_late));
+        }
+
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        let matched = matches
+            .into_iter()
+            .filter_map(|(row, position, did_attend, minutes_late)| {
+                candidates[position]
+                    .take()
+                    .map(|(attendance, member)| MatchedRow {
+                        row,
+                        attendance,
+                        member,
+                        did_attend,
+                        minutes_late,
+                    })
+            })
+            .collect();
+
+        Ok((matched, unmatched))
+    }
+
+    /// What importing the sign-in sheet would do.
+    pub fn preview_json(matched: &Vec<MatchedRow>, unmatched: &Vec<UnmatchedRow>) -> Value {
+        json!({
+            "matched": matched
+                .iter()
+                .map(|matched_row| json!({
+                    "row": matched_row.row,
+                    "member": matched_row.member.to_json(),
+                    "didAttend": matched_row.did_attend,
+                    "minutesLate": matched_row.minutes_late,
+                }))
+                .collect::<Vec<_>>(),
+            "unmatched": unmatched,
+        })
+    }
+
+    /// The updates for the matched rows, keeping whether members were
+    /// expected and whether they confirmed, and leaving any early departures
+    /// or excuses alone.
+    pub fn as_updates(matched: &Vec<MatchedRow>) -> Vec<(usize, MemberAttendanceForm)> {
+        matched
+            .iter()
+            .map(|matched_row| {
+                (
+                    matched_row.row,
+                    MemberAttendanceForm {
+                        member: matched_row.member.member.email.clone(),
+                        form: AttendanceForm {
+                            should_attend: matched_row.attendance.should_attend,
+                            did_attend: matched_row.did_attend,
+                            minutes_late: matched_row.minutes_late,
+                            confirmed: matched_row.attendance.confirmed,
+                            minutes_left_early: None,
+                            lateness_excused: None,
+                            early_departure_excused: None,
+                            excuse_reason: None,
+                        },
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn parse_values(columns: &Columns, record: &StringRecord) -> Result<(bool, i32), String> {
+        let attended = columns
+            .attended
+            .and_then(|index| record.get(index))
+            .unwrap_or("");
+        let did_attend = match attended.to_lowercase().as_str() {
+            "" | "y" | "yes" | "true" | "x" | "1" | "present" | "✓" => true,
+            "n" | "no" | "false" | "0" | "absent" => false,
+            other => return Err(format!("\"{}\" isn't yes or no.", other)),
+        };
+
+        let late = columns
+            .minutes_late
+            .and_then(|index| record.get(index))
+            .unwrap_or("");
+        let minutes_late = if late.is_empty() {
+            0
+        } else {
+            late.parse::<i32>()
+                .ok()
+                .filter(|minutes| *minutes >= 0)
+                .ok_or_else(|| format!("\"{}\" isn't a number of minutes.", late))?
+        };
+
+        Ok((did_attend, minutes_late))
+    }
+
+    /// Find the index of the member a row refers to.
+    fn find_member(
+        columns: &Columns,
+        record: &StringRecord,
+        candidates: &Vec<(Attendance, MemberForSemester)>,
+    ) -> Result<usize, String> {
+        let email = columns
+            .email
+            .and_then(|index| record.get(index))
+            .filter(|email| !email.is_empty());
+        if let Some(email) = email {
+            return candidates
+                .iter()
+                .position(|(_attendance, member)| member.member.email.eq_ignore_ascii_case(email))
+                .ok_or_else(|| format!("No member at this event has the email {}.", email));
+        }
+
+        let name = columns
+            .name(record)
+            .ok_or_else(|| "The row has no email or name.".to_owned())?;
+        let lowercase_name = name.to_lowercase();
+        let positions = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_index, (_attendance, member))| {
+                let full_name = format!("{} {}", member.member.first_name, member.member.last_name);
+                member.member.full_name().to_lowercase() == lowercase_name
+                    || full_name.to_lowercase() == lowercase_name
+            })
+            .map(|(index, _candidate)| index)
+            .collect::<Vec<_>>();
+
+        match positions.as_slice() {
+            [position] => Ok(*position),
+            [] => Err(format!("No member at this event is named {}.", name)),
+            _ => Err(format!(
+                "More than one member is named {}, so use their email instead.",
+                name
+            )),
+        }
+    }
+}
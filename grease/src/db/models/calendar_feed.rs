@@ -0,0 +1,178 @@
+use chrono::{Duration, Local, NaiveDateTime, TimeZone, Utc};
+use db::models::attendance::MemberAttendance;
+use db::models::member::MemberForSemester;
+use db::schema::{calendar_feed, AbsenceRequestState};
+use db::{datetime_to_timestamp, Attendance, CalendarFeed, Semester, Uniform};
+use diesel::prelude::*;
+use error::*;
+use icalendar::{Calendar, Component, Event as CalEvent, Property};
+use serde_json::{json, Value};
+use std::iter::FromIterator;
+use uuid::Uuid;
+
+impl CalendarFeed {
+    /// Where calendar apps can subscribe to feeds, followed by the token.
+    pub const BASE_URL: &'static str = "gleeclub.gatech.edu/cgi-bin/api/calendar_feeds/";
+
+    /// The URL to subscribe to this feed with.
+    pub fn url(&self) -> String {
+        format!("webcal://{}{}.ics", Self::BASE_URL, self.token)
+    }
+
+    /// What members see of their feed. The token is left out, since it's
+    /// already part of the URL.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "url": self.url(),
+            "created": datetime_to_timestamp(&self.created),
+        })
+    }
+
+    pub fn load_for_member(
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Option<CalendarFeed>> {
+        calendar_feed::table
+            .filter(calendar_feed::member.eq(given_member))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Create a feed for the member, replacing their old one if they had one
+    /// so that the old URL stops working.
+    pub fn regenerate(given_member: &str, conn: &MysqlConnection) -> GreaseResult<CalendarFeed> {
+        let new_token = format!(
+            "{}{}",
+            Uuid::new_v4().to_simple(),
+            Uuid::new_v4().to_simple()
+        );
+
+        diesel::replace_into(calendar_feed::table)
+            .values((
+                calendar_feed::member.eq(given_member),
+                calendar_feed::token.eq(&new_token),
+                calendar_feed::created.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Self::load_for_member(given_member, conn)?.ok_or(GreaseError::ServerError(format!(
+            "The calendar feed for {} couldn't be created.",
+            given_member
+        )))
+    }
+
+    pub fn revoke(given_member: &str, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::delete(calendar_feed::table.filter(calendar_feed::member.eq(given_member)))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Build the calendar for the member a token belongs to.
+    ///
+    /// The calendar has every event this semester that the member is expected
+    /// at, from call time to release time, along with their RSVP status.
+    pub fn build_calendar(given_token: &str, conn: &MysqlConnection) -> GreaseResult<String> {
+        let feed = calendar_feed::table
+            .filter(calendar_feed::token.eq(given_token))
+            .first::<CalendarFeed>(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::NotFound)?;
+        let current_semester = Semester::load_current(conn)?;
+        let member = MemberForSemester::load(&feed.member, &current_semester.name, conn)?;
+        let uniforms = Uniform::load_all(conn)?;
+
+        let calendar_events = Attendance::load_for_member_at_all_events(
+            &member.member,
+            member.active_semester.is_some(),
+            &current_semester.name,
+            conn,
+        )?
+        .iter()
+        .filter(|status| status.should_attend())
+        .map(|status| Self::build_event(status, &uniforms))
+        .collect::<Vec<_>>();
+
+        Ok(Calendar::from_iter(calendar_events).to_string())
+    }
+
+    fn build_event(status: &MemberAttendance, uniforms: &Vec<Uniform>) -> CalEvent {
+        let event = &status.event.event;
+        let end_time = event
+            .release_time
+            .unwrap_or(event.call_time + Duration::hours(1));
+
+        let mut details = vec![format!("Your status: {}", Self::describe_status(status))];
+        if let Some(gig) = &status.event.gig {
+            details.push(format!(
+                "Performance at {}",
+                gig.performance_time.format("%-I:%M %p")
+            ));
+            if let Some(uniform) = uniforms.iter().find(|uniform| uniform.id == gig.uniform) {
+                details.push(format!("Uniform: {}", uniform.name));
+            }
+        }
+        if let Some(comments) = &event.comments {
+            details.push(String::new());
+            details.push(comments.clone());
+        }
+
+        CalEvent::new()
+            .summary(&event.name)
+            .description(&details.join("\n"))
+            .starts(to_utc(&event.call_time))
+            .ends(to_utc(&end_time))
+            .add_property("UID", &format!("event-{}@gleeclub.gatech.edu", event.id))
+            .add_property(
+                "STATUS",
+                if status.confirmed() {
+                    "CONFIRMED"
+                } else {
+                    "TENTATIVE"
+                },
+            )
+            .append_property(
+                Property::new("LOCATION", &event.location.clone().unwrap_or_default()).done(),
+            )
+            .done()
+    }
+
+    fn describe_status(status: &MemberAttendance) -> String {
+        match status
+            .absence_request
+            .as_ref()
+            .map(|request| &request.state)
+        {
+            Some(AbsenceRequestState::Approved) => return "Absence approved".to_owned(),
+            Some(AbsenceRequestState::Pending) => return "Absence requested".to_owned(),
+            Some(AbsenceRequestState::Denied) | None => {}
+        }
+
+        if status.confirmed() {
+            "Attending (confirmed)".to_owned()
+        } else if let Some(issue) = &status.rsvp_issue {
+            format!("Attending (not confirmed; {})", issue)
+        } else {
+            "Attending (not confirmed yet)".to_owned()
+        }
+    }
+}
+
+/// Convert a local time to UTC, picking the earlier time when the clocks
+/// fall back and skipping ahead an hour when they spring forward.
+pub fn to_utc(time: &NaiveDateTime) -> chrono::DateTime<Utc> {
+    Local
+        .from_local_datetime(time)
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(*time + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(time))
+}
@@ -1,8 +1,10 @@
-use db::schema::{carpool, member, rides_in};
-use db::{Carpool, Member, NewCarpool, RidesIn, UpdatedCarpool};
+use db::schema::{carpool, member, rides_in, NotificationCategory};
+use db::{Carpool, Event, Member, NewCarpool, NotificationPreference, RidesIn, UpdatedCarpool};
 use diesel::prelude::*;
+use email::{template::escape, Email};
 use error::*;
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 
 impl Carpool {
     pub fn load_for_event(
@@ -52,7 +54,9 @@ impl Carpool {
     ) -> GreaseResult<()> {
         use db::schema::carpool::dsl::{carpool, event, id};
 
-        conn.transaction(|| {
+        let old_carpools = Self::load_for_event(given_event_id, conn)?;
+
+        conn.transaction::<_, GreaseError, _>(|| {
             diesel::delete(carpool.filter(event.eq(given_event_id)))
                 .execute(conn)
                 .map_err(GreaseError::DbError)?;
@@ -98,7 +102,68 @@ impl Carpool {
                 .map_err(GreaseError::DbError)?;
 
             Ok(())
-        })
+        })?;
+
+        let updated_carpools = Self::load_for_event(given_event_id, conn)?;
+        Self::notify_changes(given_event_id, &old_carpools, &updated_carpools, conn)
+    }
+
+    /// Email every member whose carpool changed, if they want to hear about it.
+    fn notify_changes(
+        given_event_id: i32,
+        old_carpools: &Vec<EventCarpool>,
+        updated_carpools: &Vec<EventCarpool>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let old_rides = EventCarpool::by_member(old_carpools);
+        let updated_rides = EventCarpool::by_member(updated_carpools);
+        let changed_members = old_rides
+            .keys()
+            .chain(updated_rides.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|email| {
+                old_rides.get(*email).map(|ride| ride.signature())
+                    != updated_rides.get(*email).map(|ride| ride.signature())
+            });
+        let event_name = Event::load(given_event_id, conn)?.event.name;
+
+        for changed_member in changed_members {
+            let content = match updated_rides.get(changed_member) {
+                Some(ride) => format!(
+                    "\
+                    <p>Your carpool for {} has changed.</p>\
+                    <p>Driver: {}</p>\
+                    <p>Passengers: {}</p>\
+                ",
+                    escape(&event_name),
+                    escape(&ride.driver.full_name()),
+                    ride.passengers
+                        .iter()
+                        .map(|passenger| escape(&passenger.full_name()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                None => format!(
+                    "<p>You are no longer in a carpool for {}.</p>",
+                    escape(&event_name)
+                ),
+            };
+            let email = Email::new(
+                changed_member.to_string(),
+                format!("Carpool Update for {}", event_name),
+                content,
+            );
+
+            NotificationPreference::notify(
+                changed_member,
+                NotificationCategory::CarpoolChanges,
+                email,
+                conn,
+            )?;
+        }
+
+        Ok(())
     }
 }
 
@@ -109,6 +174,30 @@ pub struct EventCarpool {
 }
 
 impl EventCarpool {
+    /// Index carpools by the emails of everyone in them, drivers included.
+    pub fn by_member<'c>(carpools: &'c Vec<EventCarpool>) -> HashMap<&'c str, &'c EventCarpool> {
+        carpools
+            .iter()
+            .flat_map(|event_carpool| {
+                std::iter::once(&event_carpool.driver)
+                    .chain(event_carpool.passengers.iter())
+                    .map(move |rider| (rider.email.as_str(), event_carpool))
+            })
+            .collect()
+    }
+
+    /// Who is in the carpool, for telling whether a carpool changed.
+    fn signature(&self) -> (String, Vec<String>) {
+        let mut passengers = self
+            .passengers
+            .iter()
+            .map(|passenger| passenger.email.clone())
+            .collect::<Vec<_>>();
+        passengers.sort();
+
+        (self.driver.email.clone(), passengers)
+    }
+
     /// Render this event's carpool data to JSON.
     ///
     /// ## JSON Format:
@@ -0,0 +1,260 @@
+use chrono::{Duration, Local, TimeZone};
+use db::models::member::MemberForSemester;
+use db::schema::{attendance, check_in_attempt, check_in_window};
+use db::{
+    datetime_to_timestamp, Attendance, CheckInAttempt, CheckInWindow, Event, SemesterFinalization,
+};
+use diesel::prelude::*;
+use error::*;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::cmp::max;
+use uuid::Uuid;
+
+impl CheckInWindow {
+    /// How many seconds each check-in code lasts.
+    pub const CODE_PERIOD: i64 = 30;
+    /// How long a window stays open if the event has already ended.
+    pub const DEFAULT_MINUTES: i64 = 30;
+    /// How many wrong codes a member can enter before they're locked out.
+    pub const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+    pub fn load(event_id: i32, conn: &MysqlConnection) -> GreaseResult<Option<CheckInWindow>> {
+        check_in_window::table
+            .filter(check_in_window::event.eq(event_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Open a check-in window for an event, replacing any window that was
+    /// already open so that its codes stop working.
+    ///
+    /// Without a number of minutes, the window stays open until the event
+    /// ends, or for half an hour if it already has.
+    pub fn open(
+        event_id: i32,
+        given_member: &str,
+        minutes: Option<i64>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<CheckInWindow> {
+        let found_event = Event::load(event_id, conn)?.event;
+        let now = Local::now().naive_local();
+        let closes = match minutes {
+            Some(minutes) if minutes > 0 => now + Duration::minutes(minutes),
+            Some(_minutes) => {
+                return Err(GreaseError::BadRequest(
+                    "Check-in windows must be open for at least a minute.".to_owned(),
+                ))
+            }
+            None => max(
+                found_event.end_time(),
+                now + Duration::minutes(Self::DEFAULT_MINUTES),
+            ),
+        };
+        let new_secret = format!(
+            "{}{}",
+            Uuid::new_v4().to_simple(),
+            Uuid::new_v4().to_simple()
+        );
+
+        diesel::replace_into(check_in_window::table)
+            .values((
+                check_in_window::event.eq(event_id),
+                check_in_window::secret.eq(&new_secret),
+                check_in_window::opened_by.eq(given_member),
+                check_in_window::opened.eq(now),
+                check_in_window::closes.eq(closes),
+            ))
+            .execute(conn)?;
+
+        Self::load(event_id, conn)?.ok_or(GreaseError::ServerError(format!(
+            "The check-in window for event {} couldn't be opened.",
+            event_id
+        )))
+    }
+
+    pub fn close(event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::delete(check_in_window::table.filter(check_in_window::event.eq(event_id)))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn is_open(&self) -> bool {
+        Local::now().naive_local() < self.closes
+    }
+
+    /// The six digit code for the given period since the epoch.
+    fn code_for_period(&self, period: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(self.secret.as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.input(format!("{}:{}", self.event, period).as_bytes());
+        let hash = mac.result().code();
+        let number = hash
+            .iter()
+            .take(4)
+            .fold(0u32, |number, byte| (number << 8) | *byte as u32);
+
+        format!("{:06}", number % 1_000_000)
+    }
+
+    fn current_period() -> i64 {
+        Local::now().timestamp() / Self::CODE_PERIOD
+    }
+
+    /// Whether a code is the current one. The previous code is also
+    /// accepted, in case it changed while the member was typing it.
+    pub fn accepts(&self, code: &str) -> bool {
+        let period = Self::current_period();
+        let code = code.trim();
+
+        self.code_for_period(period) == code || self.code_for_period(period - 1) == code
+    }
+
+    /// The current code, when it expires, and a payload for QR codes.
+    pub fn current_code(&self) -> Value {
+        let period = Self::current_period();
+        let code = self.code_for_period(period);
+        let expires = Local
+            .timestamp((period + 1) * Self::CODE_PERIOD, 0)
+            .naive_local();
+
+        json!({
+            "window": self,
+            "code": code,
+            "expires": datetime_to_timestamp(&expires),
+            "qrPayload": format!("gleeclub:check-in?event={}&code={}", self.event, code),
+        })
+    }
+
+    /// Check a member in to an event, marking them as having attended and
+    /// how many whole minutes after call time they checked in.
+    ///
+    /// Checking in again doesn't change anything.
+    pub fn check_in(
+        event_id: i32,
+        member: &MemberForSemester,
+        code: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Attendance> {
+        let window = Self::load(event_id, conn)?
+            .filter(|window| window.is_open())
+            .ok_or(GreaseError::BadRequest(
+                "Check-in isn't open for this event.".to_owned(),
+            ))?;
+        let email = &member.member.email;
+        if CheckInAttempt::failures(event_id, email, conn)? >= Self::MAX_FAILED_ATTEMPTS {
+            return Err(GreaseError::BadRequest(
+                "Too many wrong codes were entered. Ask an officer to check you in.".to_owned(),
+            ));
+        }
+        if !window.accepts(code) {
+            CheckInAttempt::record_failure(event_id, email, conn)?;
+            return Err(GreaseError::BadRequest(
+                "That code is wrong or has expired.".to_owned(),
+            ));
+        }
+
+        let found_event = Event::load(event_id, conn)?.event;
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&found_event.semester, conn)?;
+            let existing = Attendance::load(email, event_id, conn)?.ok_or(
+                GreaseError::BadRequest(format!(
+                    "No attendance exists for member {} at event {}. (Are they inactive?)",
+                    email, event_id
+                )),
+            )?;
+            if existing.did_attend {
+                return Ok(existing);
+            }
+
+            let seconds_late = (Local::now().naive_local() - found_event.call_time).num_seconds();
+            let minutes_late = max(0, seconds_late / 60) as i32;
+            diesel::update(
+                attendance::table.filter(
+                    attendance::member
+                        .eq(email)
+                        .and(attendance::event.eq(event_id)),
+                ),
+            )
+            .set((
+                attendance::did_attend.eq(true),
+                attendance::minutes_late.eq(minutes_late),
+            ))
+            .execute(conn)?;
+
+            Attendance::load(email, event_id, conn)?.ok_or(GreaseError::ServerError(format!(
+                "The attendance for {} at event {} couldn't be updated.",
+                email, event_id
+            )))
+        })
+    }
+
+    /// The members expected at an event who haven't checked in yet.
+    pub fn not_checked_in(
+        event_id: i32,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<MemberForSemester>> {
+        Ok(Attendance::load_for_event(event_id, conn)?
+            .into_iter()
+            .filter(|(attends, _member)| attends.should_attend && !attends.did_attend)
+            .map(|(_attends, member)| member)
+            .collect())
+    }
+}
+
+impl CheckInAttempt {
+    /// How many wrong codes a member has entered for an event's current window.
+    pub fn failures(
+        event_id: i32,
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<i32> {
+        check_in_attempt::table
+            .filter(
+                check_in_attempt::event
+                    .eq(event_id)
+                    .and(check_in_attempt::member.eq(given_member)),
+            )
+            .first::<CheckInAttempt>(conn)
+            .optional()
+            .map(|attempt| attempt.map(|attempt| attempt.failures).unwrap_or(0))
+            .map_err(GreaseError::DbError)
+    }
+
+    pub fn record_failure(
+        event_id: i32,
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let now = Local::now().naive_local();
+        conn.transaction(|| {
+            let updated = diesel::update(
+                check_in_attempt::table.filter(
+                    check_in_attempt::event
+                        .eq(event_id)
+                        .and(check_in_attempt::member.eq(given_member)),
+                ),
+            )
+            .set((
+                check_in_attempt::failures.eq(check_in_attempt::failures + 1),
+                check_in_attempt::last_failure.eq(now),
+            ))
+            .execute(conn)?;
+            if updated == 0 {
+                diesel::insert_into(check_in_attempt::table)
+                    .values((
+                        check_in_attempt::event.eq(event_id),
+                        check_in_attempt::member.eq(given_member),
+                        check_in_attempt::failures.eq(1),
+                        check_in_attempt::last_failure.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use db::schema::{email_attachment, email_outbox, EmailStatus};
+use db::{EmailAttachment, OutboxEmail};
+use diesel::prelude::*;
+use email::{transport::EmailTransport, Attachment, Email};
+use error::*;
+use serde::Serialize;
+
+/// The outcome of a single run of the outbox worker.
+#[derive(Serialize, Debug, Default)]
+pub struct DeliveryReport {
+    /// How many emails were sent successfully
+    pub sent: usize,
+    /// How many emails failed but will be retried later
+    pub retrying: usize,
+    /// How many emails failed for the last time and were given up on
+    pub failed: usize,
+}
+
+impl OutboxEmail {
+    /// How many delivery attempts are made before an email is marked as failed.
+    pub const MAX_ATTEMPTS: i32 = 8;
+    /// The most emails delivered in a single run of the worker.
+    pub const BATCH_SIZE: i64 = 50;
+    /// How long an email is held by a worker before another may pick it up.
+    pub const CLAIM_MINUTES: i64 = 10;
+
+    pub fn load(email_id: i32, conn: &MysqlConnection) -> GreaseResult<OutboxEmail> {
+        email_outbox::table
+            .filter(email_outbox::id.eq(email_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No queued email with id {}.",
+                email_id
+            )))
+    }
+
+    pub fn load_all(
+        given_status: Option<EmailStatus>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<OutboxEmail>> {
+        let query = email_outbox::table
+            .order_by(email_outbox::id.desc())
+            .into_boxed();
+        let query = if let Some(given_status) = given_status {
+            query.filter(email_outbox::status.eq(given_status))
+        } else {
+            query
+        };
+
+        query.load(conn).map_err(GreaseError::DbError)
+    }
+
+    /// Queue an email to be sent by the next run of the outbox worker.
+    ///
+    /// This only writes to the database, so it is safe to call from inside
+    /// of a transaction: if the transaction is rolled back, so is the email.
+    pub fn enqueue(email: &Email, conn: &MysqlConnection) -> GreaseResult<i32> {
+        conn.transaction(|| {
+            diesel::insert_into(email_outbox::table)
+                .values((
+                    email_outbox::to_address.eq(&email.to_address),
+                    email_outbox::subject.eq(&email.subject),
+                    email_outbox::html.eq(&email.html),
+                    email_outbox::text.eq(&email.text),
+                    email_outbox::reply_to.eq(&email.reply_to),
+                    email_outbox::next_attempt.eq(Local::now().naive_local()),
+                ))
+                .execute(conn)?;
+            let new_id: i32 = email_outbox::table
+                .select(email_outbox::id)
+                .order_by(email_outbox::id.desc())
+                .first(conn)?;
+
+            for attachment in &email.attachments {
+                diesel::insert_into(email_attachment::table)
+                    .values((
+                        email_attachment::email.eq(new_id),
+                        email_attachment::file_name.eq(&attachment.file_name),
+                        email_attachment::content_type.eq(&attachment.content_type),
+                        email_attachment::content.eq(&attachment.content),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(new_id)
+        })
+        .map_err(GreaseError::DbError)
+    }
+
+    /// Put a failed email back in the queue to be sent on the next run.
+    pub fn retry(email_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        let queued = OutboxEmail::load(email_id, conn)?;
+        if queued.status == EmailStatus::Sent {
+            return Err(GreaseError::BadRequest(format!(
+                "The email with id {} was already sent.",
+                email_id
+            )));
+        }
+
+        diesel::update(email_outbox::table.filter(email_outbox::id.eq(email_id)))
+            .set((
+                email_outbox::status.eq(EmailStatus::Pending),
+                email_outbox::attempts.eq(0),
+                email_outbox::next_attempt.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Attempt to send every queued email that is due.
+    ///
+    /// Each email is claimed before it is sent so that overlapping runs
+    /// of the worker don't send the same email twice. Failures are recorded
+    /// on the email and retried with exponential backoff until
+    /// [MAX_ATTEMPTS](OutboxEmail::MAX_ATTEMPTS) is reached.
+    pub fn deliver_pending(
+        transport: &dyn EmailTransport,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<DeliveryReport> {
+        let now = Local::now().naive_local();
+        let due = email_outbox::table
+            .filter(
+                email_outbox::status
+                    .eq(EmailStatus::Pending)
+                    .and(email_outbox::next_attempt.le(now)),
+            )
+            .order_by(email_outbox::id.asc())
+            .limit(Self::BATCH_SIZE)
+            .load::<OutboxEmail>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        let mut report = DeliveryReport::default();
+        for queued in due {
+            if !queued.claim(now, conn)? {
+                continue;
+            }
+
+            let email = queued.to_email(conn)?;
+            match transport.send(&email) {
+                Ok(()) => {
+                    queued.mark_sent(conn)?;
+                    report.sent += 1;
+                }
+                Err(error) => {
+                    if queued.mark_failed(&error, conn)? == EmailStatus::Failed {
+                        report.failed += 1;
+                    } else {
+                        report.retrying += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Push back the next attempt so no other worker picks this email up,
+    /// returning whether this worker got to it first.
+    fn claim(&self, now: NaiveDateTime, conn: &MysqlConnection) -> GreaseResult<bool> {
+        diesel::update(
+            email_outbox::table.filter(
+                email_outbox::id
+                    .eq(self.id)
+                    .and(email_outbox::next_attempt.eq(self.next_attempt)),
+            ),
+        )
+        .set(email_outbox::next_attempt.eq(now + Duration::minutes(Self::CLAIM_MINUTES)))
+        .execute(conn)
+        .map(|updated| updated == 1)
+        .map_err(GreaseError::DbError)
+    }
+
+    fn mark_sent(&self, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::update(email_outbox::table.filter(email_outbox::id.eq(self.id)))
+            .set((
+                email_outbox::status.eq(EmailStatus::Sent),
+                email_outbox::attempts.eq(self.attempts + 1),
+                email_outbox::last_error.eq(None::<String>),
+                email_outbox::sent_at.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    fn mark_failed(
+        &self,
+        error: &GreaseError,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<EmailStatus> {
+        let attempts = self.attempts + 1;
+        let new_status = if attempts >= Self::MAX_ATTEMPTS {
+            EmailStatus::Failed
+        } else {
+            EmailStatus::Pending
+        };
+
+        diesel::update(email_outbox::table.filter(email_outbox::id.eq(self.id)))
+            .set((
+                email_outbox::status.eq(&new_status),
+                email_outbox::attempts.eq(attempts),
+                email_outbox::last_error.eq(error.as_response().1.to_string()),
+                email_outbox::next_attempt.eq(Local::now().naive_local() + Self::backoff(attempts)),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(new_status)
+    }
+
+    /// Wait twice as long after each failure, starting at two minutes and
+    /// never waiting more than a day.
+    fn backoff(attempts: i32) -> Duration {
+        let minutes = 2i64.pow(attempts.max(0).min(11) as u32);
+
+        Duration::minutes(minutes).min(Duration::days(1))
+    }
+
+    fn to_email(&self, conn: &MysqlConnection) -> GreaseResult<Email> {
+        let attachments = email_attachment::table
+            .filter(email_attachment::email.eq(self.id))
+            .order_by(email_attachment::id.asc())
+            .load::<EmailAttachment>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(Email {
+            to_address: self.to_address.clone(),
+            subject: self.subject.clone(),
+            html: self.html.clone(),
+            text: self.text.clone(),
+            reply_to: self.reply_to.clone(),
+            attachments: attachments
+                .into_iter()
+                .map(|attachment| Attachment {
+                    file_name: attachment.file_name,
+                    content_type: attachment.content_type,
+                    content: attachment.content,
+                })
+                .collect(),
+        })
+    }
+}
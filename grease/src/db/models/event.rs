@@ -1,14 +1,21 @@
 use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use db::models::calendar_feed::to_utc;
 use db::models::member::MemberForSemester;
-use db::schema::{attendance, event, gig, gig_request, AbsenceRequestState, GigRequestStatus};
+use db::schema::{
+    attendance, event, event_series, gig, gig_request, AbsenceRequestState, GigRequestStatus,
+    WebhookKind,
+};
 use db::{
-    AbsenceRequest, Attendance, Event, EventUpdate, Gig, GigRequest, NewEvent, NewEventFields,
-    NewGig, Period, Semester, Uniform,
+    AbsenceRequest, Attendance, Event, EventSeries, EventUpdate, Gig, GigRequest, NewEvent,
+    NewEventFields, NewEventSeries, NewGig, Period, Semester, SemesterFinalization, Uniform,
+    Webhook,
 };
 use diesel::prelude::*;
 use error::*;
 use icalendar::{Calendar, Component, Event as CalEvent, Property};
+use recurrence::{self, RecurrenceRule};
 use serde::Serialize;
+use std::collections::HashSet;
 use std::iter::FromIterator;
 
 impl Event {
@@ -19,10 +26,18 @@ impl Event {
     pub const OMBUDS: &'static str = "Ombuds";
     pub const OTHER: &'static str = "Other";
 
+    /// How many hours before call time responses to an event close.
+    pub const RSVP_CLOSES_HOURS_BEFORE: i64 = 24;
+
     pub fn is_gig(&self) -> bool {
         self.type_ == Self::VOLUNTEER_GIG || self.type_ == Self::TUTTI_GIG
     }
 
+    /// The last moment members can respond to the event.
+    pub fn rsvp_deadline(&self) -> NaiveDateTime {
+        self.call_time - Duration::hours(Self::RSVP_CLOSES_HOURS_BEFORE)
+    }
+
     pub fn load(event_id: i32, conn: &MysqlConnection) -> GreaseResult<EventWithGig> {
         event::table
             .left_outer_join(gig::table)
@@ -95,6 +110,16 @@ impl Event {
             .collect()
     }
 
+    /// Load every public gig from every semester, latest first.
+    pub fn load_all_public_gigs(conn: &MysqlConnection) -> GreaseResult<Vec<(Event, Gig)>> {
+        event::table
+            .inner_join(gig::table)
+            .filter(gig::public.eq(true))
+            .order_by(gig::performance_time.desc())
+            .load::<(Event, Gig)>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
     pub fn went_to_event_type_during_week_of(
         &self,
         events_with_attendance: &Vec<(EventWithGig, Attendance)>,
@@ -164,21 +189,23 @@ impl Event {
             .map_err(GreaseError::DbError)
     }
 
+    /// Create an event, or a series of them if it repeats, returning the ID
+    /// of the first one and any existing events that they overlap.
     pub fn create(
-        new_event: NewEvent,
+        mut new_event: NewEvent,
         from_request: Option<GigRequest>,
         conn: &MysqlConnection,
-    ) -> GreaseResult<i32> {
+    ) -> GreaseResult<(i32, Vec<EventConflict>)> {
         use db::schema::event::dsl::*;
 
         let new_gig = if let Some(ref request) = from_request {
-            if let Some(new_gig) = new_event.gig {
+            if let Some(new_gig) = new_event.gig.take() {
                 Some(new_gig)
             } else {
                 Some(request.build_new_gig(conn)?)
             }
         } else {
-            new_event.gig
+            new_event.gig.take()
         };
 
         if new_event.fields.release_time.is_some()
@@ -189,19 +216,52 @@ impl Event {
             ));
         }
 
-        let until = if new_event.repeat == Period::No {
-            new_event.fields.call_time.date()
+        let exceptions = recurrence::parse_exceptions(&new_event.exdates)?;
+        let call_and_release_time_pairs = if let Some(rule) = &new_event.rrule {
+            if new_event.repeat != Period::No {
+                return Err(GreaseError::BadRequest(
+                    "Events can repeat by either a period or a recurrence rule, but not both."
+                        .to_owned(),
+                ));
+            }
+
+            let release_offset = new_event
+                .fields
+                .release_time
+                .map(|given_release_time| given_release_time - new_event.fields.call_time);
+            RecurrenceRule::parse(rule)?
+                .occurrences(
+                    &new_event.fields.call_time,
+                    new_event.repeat_until,
+                    &exceptions,
+                )?
+                .into_iter()
+                .map(|new_call_time| {
+                    (
+                        new_call_time,
+                        release_offset.map(|offset| new_call_time + offset),
+                    )
+                })
+                .collect::<Vec<_>>()
         } else {
-            new_event.repeat_until.ok_or(GreaseError::BadRequest(
-                "Must supply a repeat until time if repeat is supplied.".to_owned(),
-            ))?
+            let until = if new_event.repeat == Period::No {
+                new_event.fields.call_time.date()
+            } else {
+                new_event.repeat_until.ok_or(GreaseError::BadRequest(
+                    "Must supply a repeat until time if repeat is supplied.".to_owned(),
+                ))?
+            };
+
+            Event::repeat_event_times(
+                &new_event.fields.call_time,
+                &new_event.fields.release_time,
+                new_event.repeat,
+                until,
+            )
+            .into_iter()
+            .filter(|(new_call_time, _release_time)| !exceptions.contains(&new_call_time.date()))
+            .collect::<Vec<_>>()
         };
-        let call_and_release_time_pairs = Event::repeat_event_times(
-            &new_event.fields.call_time,
-            &new_event.fields.release_time,
-            new_event.repeat,
-            until,
-        );
 
         let num_events = call_and_release_time_pairs.len();
         if num_events == 0 {
@@ -211,8 +271,40 @@ impl Event {
             ));
         }
 
+        let conflicts = Event::find_conflicts(
+            &new_event.fields.semester,
+            &call_and_release_time_pairs,
+            &Vec::new(),
+            conn,
+        )?;
+        Event::check_for_conflicts(&conflicts, new_event.strict)?;
+
+        let is_repeated = new_event.repeat != Period::No || new_event.rrule.is_some();
+        let last_day = call_and_release_time_pairs
+            .iter()
+            .map(|(new_call_time, _release_time)| new_call_time.date())
+            .max()
+            .unwrap_or(new_event.fields.call_time.date());
+        let new_event_series = if is_repeated {
+            Some(NewEventSeries {
+                semester: new_event.fields.semester.clone(),
+                period: new_event.repeat,
+                repeat_until: last_day,
+                rule: new_event.rrule.clone(),
+                exceptions: EventSeries::format_exceptions(&exceptions),
+            })
+        } else {
+            None
+        };
         let event_fields = new_event.fields;
         conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&event_fields.semester, conn)?;
+            let new_series = if let Some(new_event_series) = &new_event_series {
+                Some(EventSeries::create(new_event_series, conn)?)
+            } else {
+                None
+            };
+
             diesel::insert_into(event)
                 .values(
                     &call_and_release_time_pairs
@@ -220,6 +312,7 @@ impl Event {
                         .map(|(new_call_time, new_release_time)| NewEventFields {
                             call_time: new_call_time,
                             release_time: new_release_time,
+                            series: new_series,
                             ..event_fields.clone()
                         })
                         .collect::<Vec<_>>(),
@@ -245,6 +338,10 @@ impl Event {
                     )
                     .execute(conn)?;
             }
+            for new_id in new_ids.iter().rev() {
+                let new_event = Event::load(*new_id, conn)?;
+                Webhook::trigger(WebhookKind::EventCreated, &new_event, conn)?;
+            }
 
             let first_id = *new_ids
                 .iter()
@@ -269,6 +366,7 @@ impl Event {
 
             Ok(first_id)
         })
+        .map(|first_id| (first_id, conflicts))
     }
 
     pub fn repeat_event_times(
@@ -315,11 +413,46 @@ impl Event {
         .collect::<Vec<_>>()
     }
 
+    /// Load the events that a change to the given event should apply to,
+    /// in chronological order.
+    pub fn load_in_scope(
+        given_event: &Event,
+        scope: EventScope,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<Event>> {
+        let series_id = match (scope, given_event.series) {
+            (EventScope::This, _) | (_, None) => return Ok(vec![given_event.clone()]),
+            (_, Some(series_id)) => series_id,
+        };
+
+        let query = event::table
+            .filter(event::series.eq(series_id))
+            .order_by(event::call_time.asc())
+            .into_boxed();
+        let query = if scope == EventScope::Following {
+            query.filter(event::call_time.ge(given_event.call_time))
+        } else {
+            query
+        };
+
+        query.load(conn).map_err(GreaseError::DbError)
+    }
+
+    /// Update an event, or every event in the given scope of its series.
+    ///
+    /// When multiple events are updated, each one is moved by however much
+    /// the given event's call time changed, and keeps the same release and
+    /// performance times relative to its call time as the given event. Only
+    /// events that are gigs have their gig fields updated. Updating "this and
+    /// following" events splits them off into a new series.
+    ///
+    /// Returns any other events that the updated events overlap.
     pub fn update(
         event_id: i32,
         event_update: EventUpdate,
+        scope: EventScope,
         conn: &MysqlConnection,
-    ) -> GreaseResult<()> {
+    ) -> GreaseResult<Vec<EventConflict>> {
         let found_event = Event::load(event_id, conn)?;
 
         if found_event.gig.is_some() && event_update.gig.is_none() {
@@ -328,22 +461,129 @@ impl Event {
             ));
         }
 
-        conn.transaction(|| {
-            diesel::update(event::table.filter(event::id.eq(event_id)))
-                .set(&event_update.fields)
-                .execute(conn)?;
+        let targets = Event::load_in_scope(&found_event.event, scope, conn)?;
+        let new_call_time = event_update.fields.call_time;
+        let time_shift = new_call_time - found_event.event.call_time;
+        let release_offset = event_update
+            .fields
+            .release_time
+            .map(|release_time| release_time - new_call_time);
+        let performance_offset = event_update
+            .gig
+            .as_ref()
+            .map(|gig_update| gig_update.performance_time - new_call_time);
+
+        let new_times = targets
+            .iter()
+            .map(|target| {
+                let target_call_time = target.call_time + time_shift;
+                (
+                    target_call_time,
+                    release_offset.map(|offset| target_call_time + offset),
+                )
+            })
+            .collect::<Vec<_>>();
+        let conflicts = Event::find_conflicts(
+            &event_update.fields.semester,
+            &new_times,
+            &targets.iter().map(|target| target.id).collect::<Vec<_>>(),
+            conn,
+        )?;
+        Event::check_for_conflicts(&conflicts, event_update.strict)?;
 
-            if found_event.gig.is_some() {
-                if let Some(gig_update) = event_update.gig {
-                    diesel::update(gig::table.filter(gig::event.eq(event_id)))
-                        .set(&gig_update)
+        conn.transaction::<_, GreaseError, _>(|| {
+            SemesterFinalization::ensure_not_finalized(&found_event.event.semester, conn)?;
+            if event_update.fields.semester != found_event.event.semester {
+                SemesterFinalization::ensure_not_finalized(&event_update.fields.semester, conn)?;
+            }
+            if scope == EventScope::Following {
+                Self::split_series(&found_event.event, &targets, conn)?;
+            }
+
+            for target in &targets {
+                let target_call_time = target.call_time + time_shift;
+                diesel::update(event::table.filter(event::id.eq(target.id)))
+                    .set(&NewEventFields {
+                        call_time: target_call_time,
+                        release_time: release_offset.map(|offset| target_call_time + offset),
+                        series: None,
+                        ..event_update.fields.clone()
+                    })
+                    .execute(conn)?;
+
+                // the section can be cleared, so it's set separately
+                if target.section != event_update.fields.section {
+                    diesel::update(event::table.filter(event::id.eq(target.id)))
+                        .set(event::section.eq(&event_update.fields.section))
                         .execute(conn)?;
+                    Attendance::recompute_for_event(
+                        target.id,
+                        target.section.as_ref().map(|section| section.as_str()),
+                        conn,
+                    )?;
                 }
+
+                if let (Some(gig_update), Some(offset)) = (&event_update.gig, performance_offset) {
+                    diesel::update(gig::table.filter(gig::event.eq(target.id)))
+                        .set(&NewGig {
+                            performance_time: target_call_time + offset,
+                            ..gig_update.clone()
+                        })
+                        .execute(conn)?;
+                }
+
+                let updated_event = Event::load(target.id, conn)?;
+                Webhook::trigger(WebhookKind::EventUpdated, &updated_event, conn)?;
             }
 
             Ok(())
-        })
-        .map_err(GreaseError::DbError)
+        })?;
+
+        Ok(conflicts)
+    }
+
+    /// Move the given events, which start at the given event, into a new
+    /// series of their own, unless they already make up the whole series.
+    fn split_series(
+        given_event: &Event,
+        following: &Vec<Event>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let series = match given_event.series {
+            Some(series_id) => EventSeries::load(series_id, conn)?,
+            None => return Ok(()),
+        };
+        let earlier_events = event::table
+            .filter(
+                event::series
+                    .eq(series.id)
+                    .and(event::call_time.lt(given_event.call_time)),
+            )
+            .count()
+            .get_result::<i64>(conn)?;
+        if earlier_events == 0 {
+            return Ok(());
+        }
+
+        let new_series_id = EventSeries::create(
+            &NewEventSeries {
+                semester: series.semester,
+                period: series.period,
+                repeat_until: series.repeat_until,
+                rule: series.rule,
+                exceptions: series.exceptions,
+            },
+            conn,
+        )?;
+        diesel::update(
+            event::table.filter(
+                event::id.eq_any(following.iter().map(|event| event.id).collect::<Vec<_>>()),
+            ),
+        )
+        .set(event::series.eq(new_series_id))
+        .execute(conn)?;
+
+        Ok(())
     }
 
     pub fn rsvp(
@@ -365,20 +605,23 @@ impl Event {
             member.active_semester.is_some(),
         )?;
 
-        diesel::update(
-            attendance::table.filter(
-                attendance::event
-                    .eq(event_id)
-                    .and(attendance::member.eq(&member.member.email)),
-            ),
-        )
-        .set((
-            attendance::should_attend.eq(attending),
-            attendance::confirmed.eq(true),
-        ))
-        .execute(conn)?;
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&found_event.event.semester, conn)?;
+            diesel::update(
+                attendance::table.filter(
+                    attendance::event
+                        .eq(event_id)
+                        .and(attendance::member.eq(&member.member.email)),
+                ),
+            )
+            .set((
+                attendance::should_attend.eq(attending),
+                attendance::confirmed.eq(true),
+            ))
+            .execute(conn)?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     pub fn confirm(
@@ -386,7 +629,7 @@ impl Event {
         member: &MemberForSemester,
         conn: &MysqlConnection,
     ) -> GreaseResult<()> {
-        let _found_event = Event::load(event_id, conn)?;
+        let found_event = Event::load(event_id, conn)?;
         let _attendance = Attendance::load(&member.member.email, event_id, conn)?.ok_or(
             GreaseError::ServerError(format!(
                 "No attendance exists for member {} at event with id {}.",
@@ -394,27 +637,153 @@ impl Event {
             )),
         )?;
 
-        diesel::update(
-            attendance::table.filter(
-                attendance::event
-                    .eq(event_id)
-                    .and(attendance::member.eq(&member.member.email)),
-            ),
-        )
-        .set((
-            attendance::should_attend.eq(true),
-            attendance::confirmed.eq(true),
-        ))
-        .execute(conn)?;
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&found_event.event.semester, conn)?;
+            diesel::update(
+                attendance::table.filter(
+                    attendance::event
+                        .eq(event_id)
+                        .and(attendance::member.eq(&member.member.email)),
+                ),
+            )
+            .set((
+                attendance::should_attend.eq(true),
+                attendance::confirmed.eq(true),
+            ))
+            .execute(conn)?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
-    pub fn delete(event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
-        diesel::delete(event::table.filter(event::id.eq(event_id))).execute(conn)?;
+    /// Delete an event, or every event in the given scope of its series.
+    ///
+    /// The attendance, gig details, carpools, and absence requests for each
+    /// deleted event are deleted along with it.
+    pub fn delete(event_id: i32, scope: EventScope, conn: &MysqlConnection) -> GreaseResult<()> {
+        let found_event = Event::load(event_id, conn)?;
+        let targets = Event::load_in_scope(&found_event.event, scope, conn)?;
 
-        Ok(())
-        // format!("No event with id {}.", event_id),
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(&found_event.event.semester, conn)?;
+            for target in &targets {
+                let deleted_event = Event::load(target.id, conn)?;
+                diesel::delete(event::table.filter(event::id.eq(target.id))).execute(conn)?;
+                Webhook::trigger(WebhookKind::EventDeleted, &deleted_event, conn)?;
+            }
+
+            if let Some(series_id) = found_event.event.series {
+                EventSeries::shrink_to_remaining_events(series_id, conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// When the event is assumed to end. Events without a release time are
+    /// assumed to last an hour.
+    pub fn end_time(&self) -> NaiveDateTime {
+        Self::end_of(self.call_time, self.release_time)
+    }
+
+    fn end_of(call_time: NaiveDateTime, release_time: Option<NaiveDateTime>) -> NaiveDateTime {
+        release_time
+            .filter(|release_time| *release_time > call_time)
+            .unwrap_or(call_time + Duration::hours(1))
+    }
+
+    /// Find the events in a semester that overlap any of the given call and
+    /// release times, leaving out the events with the given IDs.
+    pub fn find_conflicts(
+        given_semester: &str,
+        times: &Vec<(NaiveDateTime, Option<NaiveDateTime>)>,
+        excluded_ids: &Vec<i32>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<EventConflict>> {
+        let semester_events = event::table
+            .filter(event::semester.eq(given_semester))
+            .order_by(event::call_time.asc())
+            .load::<Event>(conn)?;
+
+        Ok(times
+            .iter()
+            .flat_map(|&(new_call_time, new_release_time)| {
+                let new_end_time = Self::end_of(new_call_time, new_release_time);
+                semester_events
+                    .iter()
+                    .filter(move |other| {
+                        !excluded_ids.contains(&other.id)
+                            && other.call_time < new_end_time
+                            && new_call_time < other.end_time()
+                    })
+                    .map(move |other| EventConflict {
+                        call_time: new_call_time,
+                        conflicts_with: other.clone(),
+                    })
+            })
+            .collect())
+    }
+
+    /// Refuse to schedule over other events if strict checking was asked for.
+    pub fn check_for_conflicts(conflicts: &Vec<EventConflict>, strict: bool) -> GreaseResult<()> {
+        match conflicts.first() {
+            Some(conflict) if strict => Err(GreaseError::BadRequest(format!(
+                "This would overlap {} other event(s), starting with {} on {}.",
+                conflicts.len(),
+                conflict.conflicts_with.name,
+                conflict
+                    .conflicts_with
+                    .call_time
+                    .format("%B %-d at %-I:%M %p")
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Find every pair of overlapping events in a semester, along with the
+    /// members who are expected at both.
+    pub fn load_schedule_conflicts(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<ScheduleConflict>> {
+        let semester_events = event::table
+            .filter(event::semester.eq(given_semester))
+            .order_by(event::call_time.asc())
+            .load::<Event>(conn)?;
+        let expected = attendance::table
+            .inner_join(event::table)
+            .filter(event::semester.eq(given_semester))
+            .filter(attendance::should_attend.eq(true))
+            .select((attendance::event, attendance::member))
+            .load::<(i32, String)>(conn)?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut conflicts = Vec::new();
+        for (index, first) in semester_events.iter().enumerate() {
+            // events are sorted by call time, so the second starts during the first
+            for second in semester_events[index + 1..]
+                .iter()
+                .take_while(|second| second.call_time < first.end_time())
+            {
+                let mut members = expected
+                    .iter()
+                    .filter(|(event_id, member)| {
+                        *event_id == first.id && expected.contains(&(second.id, member.clone()))
+                    })
+                    .map(|(_event_id, member)| member.clone())
+                    .collect::<Vec<_>>();
+                members.sort();
+
+                conflicts.push(ScheduleConflict {
+                    first: first.clone(),
+                    second: second.clone(),
+                    members,
+                });
+            }
+        }
+
+        Ok(conflicts)
     }
 
     pub fn check_for_rsvp_issue(
@@ -437,7 +806,7 @@ impl Event {
             Some("Member must be active to RSVP to events.".to_owned())
         } else if !given_attendance.map(|a| a.should_attend).unwrap_or(true) {
             None
-        } else if Local::now().naive_local() + Duration::days(1) > self.call_time {
+        } else if Local::now().naive_local() > self.rsvp_deadline() {
             Some("Responses are closed for this event.".to_owned())
         } else if let Some(bad_type) = ["Tutti Gig", "Sectional", "Rehearsal"]
             .iter()
@@ -484,6 +853,37 @@ impl EventWithGig {
             .done()
     }
 
+    /// Build an iCalendar file for the event, suitable for attaching to emails.
+    ///
+    /// Gigs start at their performance time, while all other events start at
+    /// their call time. Events without a release time are assumed to last an hour.
+    pub fn to_ics(&self) -> String {
+        let start_time = self
+            .gig
+            .as_ref()
+            .map(|gig| gig.performance_time)
+            .unwrap_or(self.event.call_time);
+        let end_time = self
+            .event
+            .release_time
+            .unwrap_or(start_time + Duration::hours(1));
+        let calendar_event = CalEvent::new()
+            .summary(&self.event.name)
+            .description(&self.event.comments.clone().unwrap_or_default())
+            .starts(to_utc(&start_time))
+            .ends(to_utc(&end_time))
+            .add_property(
+                "UID",
+                &format!("event-{}@gleeclub.gatech.edu", self.event.id),
+            )
+            .append_property(
+                Property::new("LOCATION", &self.event.location.clone().unwrap_or_default()).done(),
+            )
+            .done();
+
+        Calendar::from_iter(vec![calendar_event]).to_string()
+    }
+
     fn build_calendar(event: &Event, gig: &Gig) -> Calendar {
         let calendar_event = Self::build_calendar_event(event, gig);
 
@@ -518,6 +918,117 @@ impl EventWithGig {
     }
 }
 
+/// An existing event that overlaps an event being created or updated.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventConflict {
+    /// When the event being scheduled starts
+    #[serde(with = "::db::naivedatetime_posix")]
+    pub call_time: NaiveDateTime,
+    /// The event that it overlaps
+    pub conflicts_with: Event,
+}
+
+/// Two events in a semester that overlap, and the emails of the members
+/// who are expected at both of them.
+#[derive(Serialize)]
+pub struct ScheduleConflict {
+    pub first: Event,
+    pub second: Event,
+    pub members: Vec<String>,
+}
+
+/// Which events in a series an update or deletion applies to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventScope {
+    /// Only the given event
+    This,
+    /// The given event and every later event in its series
+    Following,
+    /// Every event in the given event's series
+    Series,
+}
+
+impl EventScope {
+    /// Parse a scope from a query parameter, defaulting to just the one event.
+    pub fn parse(scope: Option<String>) -> GreaseResult<EventScope> {
+        match scope.as_ref().map(|scope| scope.as_str()) {
+            None | Some("this") => Ok(EventScope::This),
+            Some("following") => Ok(EventScope::Following),
+            Some("series") => Ok(EventScope::Series),
+            Some(other) => Err(GreaseError::BadRequest(format!(
+                "\"{}\" is not an event scope (expected \"this\", \"following\", or \"series\").",
+                other
+            ))),
+        }
+    }
+}
+
+impl EventSeries {
+    pub fn load(series_id: i32, conn: &MysqlConnection) -> GreaseResult<EventSeries> {
+        event_series::table
+            .filter(event_series::id.eq(series_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No event series with id {}.",
+                series_id
+            )))
+    }
+
+    pub fn create(new_series: &NewEventSeries, conn: &MysqlConnection) -> GreaseResult<i32> {
+        conn.transaction(|| {
+            diesel::insert_into(event_series::table)
+                .values(new_series)
+                .execute(conn)?;
+
+            event_series::table
+                .select(event_series::id)
+                .order_by(event_series::id.desc())
+                .first(conn)
+        })
+        .map_err(GreaseError::DbError)
+    }
+
+    /// Store exceptions the way they would appear in an `EXDATE`.
+    pub fn format_exceptions(exceptions: &Vec<NaiveDate>) -> Option<String> {
+        if exceptions.is_empty() {
+            None
+        } else {
+            Some(
+                exceptions
+                    .iter()
+                    .map(|exception| exception.format("%Y%m%d").to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+        }
+    }
+
+    /// After events in a series are deleted, end the series at its last
+    /// remaining event, or delete it if none are left.
+    pub fn shrink_to_remaining_events(series_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        let last_call_time = event::table
+            .filter(event::series.eq(series_id))
+            .select(event::call_time)
+            .order_by(event::call_time.desc())
+            .first::<NaiveDateTime>(conn)
+            .optional()?;
+
+        if let Some(last_call_time) = last_call_time {
+            diesel::update(event_series::table.filter(event_series::id.eq(series_id)))
+                .set(event_series::repeat_until.eq(last_call_time.date()))
+                .execute(conn)?;
+        } else {
+            diesel::delete(event_series::table.filter(event_series::id.eq(series_id)))
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl GigRequest {
     pub fn load(given_id: i32, conn: &MysqlConnection) -> GreaseResult<GigRequest> {
         gig_request::table
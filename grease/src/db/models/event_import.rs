@@ -0,0 +1,353 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use db::models::event::{EventScope, EventWithGig};
+use db::schema::{event_type, imported_event, Period};
+use db::{
+    CategoryMapping, Event, EventImport, EventType, EventUpdate, ImportedEvent, NewEvent,
+    NewEventFields, NewGig, Semester, SemesterFinalization,
+};
+use diesel::prelude::*;
+use error::*;
+use ics::{self, CalendarEvent};
+use recurrence::{self, RecurrenceRule};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// What will happen to an event from the calendar when it's imported.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAction {
+    /// It hasn't been imported before, so it will be created
+    Create,
+    /// It was imported before, and will be updated to match the calendar
+    Update,
+    /// It was imported before, and will be left alone
+    Skip,
+    /// None of its categories are mapped to an event type, so it will be left out
+    Unmapped,
+    /// It starts before or after the semester it's imported into, so it will be left out
+    OutOfSemester,
+}
+
+/// An event from the calendar, as it would be imported.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCandidate {
+    pub uid: String,
+    pub name: String,
+    #[serde(with = "::db::naivedatetime_posix")]
+    pub call_time: NaiveDateTime,
+    #[serde(with = "::db::optional_naivedatetime_posix")]
+    pub release_time: Option<NaiveDateTime>,
+    pub location: Option<String>,
+    pub categories: Vec<String>,
+    /// The `RRULE` of recurring events
+    pub recurrence: Option<String>,
+    /// How many events will be created, more than one for recurring events
+    pub occurrences: usize,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub points: Option<i32>,
+    /// The ID of the event this was imported as before, if it was
+    pub existing_event: Option<i32>,
+    pub action: ImportAction,
+}
+
+impl EventImport {
+    /// Read the calendar and work out what importing it would do, without
+    /// changing anything.
+    ///
+    /// Each event takes the mapping of the first of its categories that has
+    /// one, or the default mapping if none do.
+    pub fn preview(
+        &self,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<(Semester, Vec<(CalendarEvent, ImportCandidate)>)> {
+        let semester = match &self.semester {
+            Some(semester_name) => Semester::load(semester_name, conn)?,
+            None => Semester::load_current(conn)?,
+        };
+        let event_types = event_type::table
+            .order_by(event_type::name.asc())
+            .load::<EventType>(conn)?;
+        self.check_mappings(&event_types)?;
+
+        let previously_imported = imported_event::table.load::<ImportedEvent>(conn)?;
+        let candidates = ics::parse_events(&self.calendar)?
+            .into_iter()
+            .map(|calendar_event| {
+                let candidate = self.build_candidate(
+                    &calendar_event,
+                    &semester,
+                    &event_types,
+                    &previously_imported,
+                )?;
+                Ok((calendar_event, candidate))
+            })
+            .collect::<GreaseResult<Vec<_>>>()?;
+
+        Ok((semester, candidates))
+    }
+
+    /// Preview the import as JSON, along with every category in the calendar
+    /// so that they can be mapped to event types.
+    pub fn preview_json(&self, conn: &MysqlConnection) -> GreaseResult<Value> {
+        let (_semester, candidates) = self.preview(conn)?;
+        let mut categories = candidates
+            .iter()
+            .flat_map(|(calendar_event, _candidate)| calendar_event.categories.iter().cloned())
+            .collect::<Vec<_>>();
+        categories.sort();
+        categories.dedup();
+
+        Ok(json!({
+            "events": candidates
+                .into_iter()
+                .map(|(_calendar_event, candidate)| candidate)
+                .collect::<Vec<_>>(),
+            "categories": categories,
+        }))
+    }
+
+    /// Import the calendar, creating new events (with their attendance) and
+    /// updating or skipping ones that were imported before.
+    ///
+    /// Recurring events are updated by moving their whole series, so changes
+    /// to how they repeat aren't picked up.
+    pub fn import(&self, conn: &MysqlConnection) -> GreaseResult<Value> {
+        let (semester, candidates) = self.preview(conn)?;
+        let (mut created, mut updated, mut skipped) = (0, 0, 0);
+        let mut conflicts = Vec::new();
+
+        conn.transaction::<_, GreaseError, _>(|| {
+            SemesterFinalization::ensure_not_finalized(&semester.name, conn)?;
+            for (calendar_event, candidate) in candidates {
+                match (candidate.action, candidate.existing_event) {
+                    (ImportAction::Create, _) => {
+                        let (new_id, new_conflicts) = Event::create(
+                            Self::build_new_event(&calendar_event, &candidate, &semester, self),
+                            None,
+                            conn,
+                        )?;
+                        Self::record_import(&candidate.uid, new_id, conn)?;
+                        conflicts.extend(new_conflicts);
+                        created += 1;
+                    }
+                    (ImportAction::Update, Some(existing_id)) => {
+                        let existing = Event::load(existing_id, conn)?;
+                        let scope = if existing.event.series.is_some() {
+                            EventScope::Series
+                        } else {
+                            EventScope::This
+                        };
+                        let event_update = Self::build_update(&existing, &candidate, self);
+                        let new_conflicts = Event::update(existing_id, event_update, scope, conn)?;
+                        Self::record_import(&candidate.uid, existing_id, conn)?;
+                        conflicts.extend(new_conflicts);
+                        updated += 1;
+                    }
+                    _ => skipped += 1,
+                }
+            }
+
+            Ok(())
+        })?;
+
+        Ok(json!({
+            "created": created,
+            "updated": updated,
+            "skipped": skipped,
+            "conflicts": conflicts,
+        }))
+    }
+
+    fn check_mappings(&self, event_types: &Vec<EventType>) -> GreaseResult<()> {
+        for mapping in self.categories.values().chain(self.default.iter()) {
+            if !event_types
+                .iter()
+                .any(|event_type| event_type.name == mapping.type_)
+            {
+                return Err(GreaseError::BadRequest(format!(
+                    "There is no event type called {}.",
+                    mapping.type_
+                )));
+            } else if mapping.type_ == Event::VOLUNTEER_GIG || mapping.type_ == Event::TUTTI_GIG {
+                return Err(GreaseError::BadRequest(
+                    "Gigs need performance details, so they can't be imported.".to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The types of events that imported events will be created as.
+    pub fn mapped_types(&self) -> Vec<&str> {
+        let mut types = self
+            .categories
+            .values()
+            .chain(self.default.iter())
+            .map(|mapping| mapping.type_.as_str())
+            .collect::<Vec<_>>();
+        types.sort();
+        types.dedup();
+
+        types
+    }
+
+    fn mapping_for(&self, calendar_event: &CalendarEvent) -> Option<&CategoryMapping> {
+        calendar_event
+            .categories
+            .iter()
+            .find_map(|category| self.categories.get(category))
+            .or(self.default.as_ref())
+    }
+
+    fn build_candidate(
+        &self,
+        calendar_event: &CalendarEvent,
+        semester: &Semester,
+        event_types: &Vec<EventType>,
+        previously_imported: &Vec<ImportedEvent>,
+    ) -> GreaseResult<ImportCandidate> {
+        let mapping = self.mapping_for(calendar_event);
+        let points = mapping.map(|mapping| {
+            mapping.points.unwrap_or_else(|| {
+                event_types
+                    .iter()
+                    .find(|event_type| event_type.name == mapping.type_)
+                    .map(|event_type| event_type.weight)
+                    .unwrap_or(0)
+            })
+        });
+        let existing_event = previously_imported
+            .iter()
+            .find(|imported| imported.uid == calendar_event.uid)
+            .map(|imported| imported.event);
+        let occurrences = match &calendar_event.rrule {
+            Some(rule) => RecurrenceRule::parse(rule)?
+                .occurrences(
+                    &calendar_event.start,
+                    Some(Self::repeat_until(semester)),
+                    &recurrence::parse_exceptions(&calendar_event.exdates)?,
+                )?
+                .len(),
+            None => 1,
+        };
+
+        let in_semester = calendar_event.start >= semester.start_date
+            && calendar_event.start <= semester.end_date;
+        let action = match (mapping, existing_event) {
+            _ if !in_semester => ImportAction::OutOfSemester,
+            (None, _) => ImportAction::Unmapped,
+            (Some(_mapping), None) => ImportAction::Create,
+            (Some(_mapping), Some(_existing)) if self.update_duplicates => ImportAction::Update,
+            (Some(_mapping), Some(_existing)) => ImportAction::Skip,
+        };
+
+        Ok(ImportCandidate {
+            uid: calendar_event.uid.clone(),
+            name: calendar_event.summary.chars().take(64).collect(),
+            call_time: calendar_event.start,
+            release_time: calendar_event.end,
+            location: calendar_event
+                .location
+                .as_ref()
+                .map(|location| location.chars().take(255).collect()),
+            categories: calendar_event.categories.clone(),
+            recurrence: calendar_event.rrule.clone(),
+            occurrences,
+            type_: mapping.map(|mapping| mapping.type_.clone()),
+            points,
+            existing_event,
+            action,
+        })
+    }
+
+    /// Recurring events without an end stop at the end of the semester.
+    fn repeat_until(semester: &Semester) -> NaiveDate {
+        semester.end_date.date()
+    }
+
+    fn build_fields(
+        candidate: &ImportCandidate,
+        semester: &str,
+        import: &EventImport,
+    ) -> NewEventFields {
+        NewEventFields {
+            name: candidate.name.clone(),
+            semester: semester.to_owned(),
+            type_: candidate.type_.clone().unwrap_or_default(),
+            call_time: candidate.call_time,
+            release_time: candidate.release_time,
+            points: candidate.points.unwrap_or(0),
+            comments: None,
+            location: candidate.location.clone(),
+            gig_count: None,
+            default_attend: import.default_attend.unwrap_or(true),
+            section: None,
+            series: None,
+        }
+    }
+
+    fn build_new_event(
+        calendar_event: &CalendarEvent,
+        candidate: &ImportCandidate,
+        semester: &Semester,
+        import: &EventImport,
+    ) -> NewEvent {
+        NewEvent {
+            fields: NewEventFields {
+                comments: calendar_event.description.clone(),
+                ..Self::build_fields(candidate, &semester.name, import)
+            },
+            gig: None,
+            repeat: Period::No,
+            repeat_until: Some(Self::repeat_until(semester)),
+            rrule: calendar_event.rrule.clone(),
+            exdates: calendar_event.exdates.clone(),
+            strict: false,
+        }
+    }
+
+    /// Update an event to match the calendar, keeping its semester, section,
+    /// and comments, and moving its performance along with it if it's a gig.
+    fn build_update(
+        existing: &EventWithGig,
+        candidate: &ImportCandidate,
+        import: &EventImport,
+    ) -> EventUpdate {
+        let time_shift = candidate.call_time - existing.event.call_time;
+
+        EventUpdate {
+            fields: NewEventFields {
+                comments: existing.event.comments.clone(),
+                section: existing.event.section.clone(),
+                ..Self::build_fields(candidate, &existing.event.semester, import)
+            },
+            gig: existing.gig.as_ref().map(|gig| NewGig {
+                performance_time: gig.performance_time + time_shift,
+                uniform: gig.uniform,
+                contact_name: gig.contact_name.clone(),
+                contact_email: gig.contact_email.clone(),
+                contact_phone: gig.contact_phone.clone(),
+                price: gig.price,
+                public: gig.public,
+                summary: gig.summary.clone(),
+                description: gig.description.clone(),
+            }),
+            strict: false,
+        }
+    }
+
+    fn record_import(uid: &str, event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::replace_into(imported_event::table)
+            .values((
+                imported_event::uid.eq(uid),
+                imported_event::event.eq(event_id),
+                imported_event::imported.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use db::models::grades::Grades;
+use db::models::member::MemberForSemester;
+use db::schema::{attendance, event};
+use db::{Attendance, Enrollment, Event, FinalGrade, Semester, SemesterFinalization};
+use diesel::prelude::*;
+use error::*;
+use export::{Cell, ExportFilter, Spreadsheet};
+use std::collections::HashMap;
+
+/// The columns that start every row about a member.
+const MEMBER_HEADERS: [&'static str; 5] =
+    ["Last Name", "First Name", "Email", "Section", "Enrollment"];
+
+fn member_cells(member: &MemberForSemester) -> Vec<Cell> {
+    let enrollment =
+        member
+            .active_semester
+            .as_ref()
+            .map(|active_semester| match active_semester.enrollment {
+                Enrollment::Class => "Class",
+                Enrollment::Club => "Club",
+            });
+
+    vec![
+        Cell::text(&member.member.last_name),
+        Cell::text(&member.member.first_name),
+        Cell::text(&member.member.email),
+        Cell::from(member.section().map(|section| section.to_owned())),
+        Cell::from(enrollment.map(|enrollment| enrollment.to_owned())),
+    ]
+}
+
+impl Spreadsheet {
+    /// Every member's attendance at a single event.
+    pub fn for_event_attendance(
+        event_id: i32,
+        filter: &ExportFilter,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Spreadsheet> {
+        let found_event = Event::load(event_id, conn)?.event;
+        let headers = MEMBER_HEADERS
+            .iter()
+            .chain(
+                [
+                    "Should Attend",
+                    "Did Attend",
+                    "Confirmed",
+                    "Minutes Late",
+                    "Minutes Left Early",
+                    "Lateness Excused",
+                    "Early Departure Excused",
+                    "Excuse Reason",
+                ]
+                .iter(),
+            )
+            .collect::<Vec<_>>();
+        let mut spreadsheet = Spreadsheet::new(
+            &format!(
+                "{} {}",
+                found_event.name,
+                found_event.call_time.format("%Y-%m-%d")
+            ),
+            headers,
+        );
+
+        spreadsheet.rows = Attendance::load_for_event(event_id, conn)?
+            .into_iter()
+            .filter(|(_attendance, member)| filter.includes(member))
+            .map(|(attendance, member)| {
+                let mut row = member_cells(&member);
+                row.extend(vec![
+                    Cell::Bool(attendance.should_attend),
+                    Cell::Bool(attendance.did_attend),
+                    Cell::Bool(attendance.confirmed),
+                    Cell::Number(attendance.minutes_late as f64),
+                    Cell::Number(attendance.minutes_left_early as f64),
+                    Cell::Bool(attendance.lateness_excused),
+                    Cell::Bool(attendance.early_departure_excused),
+                    Cell::from(attendance.excuse_reason),
+                ]);
+                row
+            })
+            .collect();
+
+        Ok(spreadsheet)
+    }
+
+    /// A grid of every member's attendance at every event in a semester,
+    /// with columns for whether they should have attended, whether they
+    /// did, and how late they were at each event.
+    pub fn for_semester_attendance(
+        semester: &Semester,
+        filter: &ExportFilter,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Spreadsheet> {
+        let events = event::table
+            .filter(event::semester.eq(&semester.name))
+            .order_by(event::call_time.asc())
+            .load::<Event>(conn)?;
+        let all_attendance = attendance::table
+            .inner_join(event::table)
+            .filter(event::semester.eq(&semester.name))
+            .select(attendance::all_columns)
+            .load::<Attendance>(conn)?;
+        let attendance_by_member = all_attendance
+            .iter()
+            .map(|attends| ((attends.member.as_str(), attends.event), attends))
+            .collect::<HashMap<_, _>>();
+
+        let mut headers = MEMBER_HEADERS
+            .iter()
+            .map(|header| header.to_string())
+            .collect::<Vec<_>>();
+        for semester_event in &events {
+            let event_name = format!(
+                "{} ({})",
+                semester_event.name,
+                semester_event.call_time.format("%m/%d")
+            );
+            headers.push(format!("{}: Should Attend", event_name));
+            headers.push(format!("{}: Did Attend", event_name));
+            headers.push(format!("{}: Minutes Late", event_name));
+        }
+        let mut spreadsheet = Spreadsheet::new(&format!("{} Attendance", semester.name), headers);
+
+        spreadsheet.rows = MemberForSemester::load_all(&semester.name, conn)?
+            .into_iter()
+            .filter(|member| filter.includes(member))
+            .map(|member| {
+                let mut row = member_cells(&member);
+                for semester_event in &events {
+                    match attendance_by_member
+                        .get(&(member.member.email.as_str(), semester_event.id))
+                    {
+                        Some(attends) => row.extend(vec![
+                            Cell::Bool(attends.should_attend),
+                            Cell::Bool(attends.did_attend),
+                            Cell::Number(attends.minutes_late as f64),
+                        ]),
+                        None => row.extend(vec![Cell::Empty, Cell::Empty, Cell::Empty]),
+                    }
+                }
+                row
+            })
+            .collect();
+
+        Ok(spreadsheet)
+    }
+
+    /// Every member's final grade for a semester, and whether they met the
+    /// semester's volunteer gig requirement.
+    pub fn for_grades(
+        semester: &Semester,
+        filter: &ExportFilter,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Spreadsheet> {
+        let headers = MEMBER_HEADERS
+            .iter()
+            .chain(
+                [
+                    "Grade",
+                    "Volunteer Gigs Attended",
+                    "Gig Requirement",
+                    "Met Gig Requirement",
+                ]
+                .iter(),
+            )
+            .collect::<Vec<_>>();
+        let mut spreadsheet = Spreadsheet::new(&format!("{} Grades", semester.name), headers);
+
+        let members = MemberForSemester::load_all(&semester.name, conn)?
+            .into_iter()
+            .filter(|member| filter.includes(member))
+            .collect::<Vec<_>>();
+        let mut roster_grades = Grades::for_roster(&members, semester, conn)?;
+
+        spreadsheet.rows = members
+            .iter()
+            .map(|member| {
+                let grades =
+                    roster_grades
+                        .remove(&member.member.email)
+                        .ok_or(GreaseError::ServerError(format!(
+                            "No grades were calculated for {}.",
+                            member.member.email
+                        )))?;
+                let mut row = member_cells(member);
+                row.extend(vec![
+                    Cell::Number((grades.grade as f64 * 100.0).round() / 100.0),
+                    Cell::Number(grades.volunteer_gigs_attended as f64),
+                    Cell::Number(semester.gig_requirement as f64),
+                    Cell::Bool(grades.volunteer_gigs_attended as i32 >= semester.gig_requirement),
+                ]);
+                Ok(row)
+            })
+            .collect::<GreaseResult<Vec<_>>>()?;
+
+        Ok(spreadsheet)
+    }
+
+    /// The final grades of a finalized semester, laid out for the registrar.
+    pub fn for_registrar(semester: &Semester, conn: &MysqlConnection) -> GreaseResult<Spreadsheet> {
+        if SemesterFinalization::load(&semester.name, conn)?.is_none() {
+            return Err(GreaseError::BadRequest(format!(
+                "The {} semester must be finalized before its grades go to the registrar.",
+                semester.name
+            )));
+        }
+
+        let mut spreadsheet = Spreadsheet::new(
+            &format!("{} Final Grades", semester.name),
+            vec![
+                "Last Name",
+                "First Name",
+                "Email",
+                "Semester",
+                "Grade",
+                "Letter Grade",
+            ],
+        );
+        spreadsheet.rows = FinalGrade::load_for_semester(&semester.name, conn)?
+            .into_iter()
+            .map(|(final_grade, member)| {
+                vec![
+                    Cell::text(&member.last_name),
+                    Cell::text(&member.first_name),
+                    Cell::text(&member.email),
+                    Cell::text(&final_grade.semester),
+                    Cell::Number((final_grade.grade as f64 * 100.0).round() / 100.0),
+                    Cell::text(&final_grade.letter),
+                ]
+            })
+            .collect();
+
+        Ok(spreadsheet)
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::Local;
+use db::models::grades::Grades;
+use db::models::member::MemberForSemester;
+use db::schema::{
+    event, final_grade, finalization_log, member, semester_finalization, Enrollment,
+    FinalizationAction,
+};
+use db::{FinalGrade, FinalizationLog, Member, Semester, SemesterFinalization};
+use diesel::prelude::*;
+use error::*;
+use serde_json::{json, Value};
+
+impl SemesterFinalization {
+    pub fn load(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Option<SemesterFinalization>> {
+        semester_finalization::table
+            .filter(semester_finalization::semester.eq(given_semester))
+            .first::<SemesterFinalization>(conn)
+            .optional()
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Fail if a semester has been finalized, since its attendance
+    /// can't change until it's reopened.
+    ///
+    /// Call this in the same transaction as the change it guards: the
+    /// check locks the semester's finalization, so the semester can't be
+    /// finalized until the change is committed.
+    pub fn ensure_not_finalized(given_semester: &str, conn: &MysqlConnection) -> GreaseResult<()> {
+        let finalization = semester_finalization::table
+            .filter(semester_finalization::semester.eq(given_semester))
+            .for_update()
+            .first::<SemesterFinalization>(conn)
+            .optional()?;
+        if finalization.is_some() {
+            Err(GreaseError::BadRequest(format!(
+                "The {} semester has been finalized, so its events, attendance, and grading \
+                 can't be changed unless an officer reopens it.",
+                given_semester
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The same as [ensure_not_finalized](SemesterFinalization::ensure_not_finalized),
+    /// but for the semester an event is in.
+    pub fn ensure_event_not_finalized(event_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        let event_semester = event::table
+            .filter(event::id.eq(event_id))
+            .select(event::semester)
+            .first::<String>(conn)
+            .optional()?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No event with id {}.",
+                event_id
+            )))?;
+
+        Self::ensure_not_finalized(&event_semester, conn)
+    }
+
+    /// Snapshot the grades of every member enrolled in the class for a
+    /// semester and lock its attendance.
+    ///
+    /// Any final grades from before the semester was last reopened are replaced.
+    pub fn finalize(
+        given_semester: &str,
+        officer: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<FinalGrade>> {
+        let semester = Semester::load(given_semester, conn)?;
+
+        conn.transaction(|| {
+            // insert the finalization before computing grades, so that
+            // attendance changes wait on it instead of racing the snapshot
+            let existing = semester_finalization::table
+                .filter(semester_finalization::semester.eq(&semester.name))
+                .for_update()
+                .first::<SemesterFinalization>(conn)
+                .optional()?;
+            if existing.is_some() {
+                return Err(GreaseError::BadRequest(format!(
+                    "The {} semester has already been finalized.",
+                    semester.name
+                )));
+            }
+            diesel::insert_into(semester_finalization::table)
+                .values((
+                    semester_finalization::semester.eq(&semester.name),
+                    semester_finalization::finalized_by.eq(officer),
+                    semester_finalization::finalized_at.eq(Local::now().naive_local()),
+                ))
+                .execute(conn)?;
+
+            let class_members = MemberForSemester::load_all(&semester.name, conn)?
+                .into_iter()
+                .filter(|member_for_semester| {
+                    member_for_semester
+                        .active_semester
+                        .as_ref()
+                        .map(|active_semester| active_semester.enrollment == Enrollment::Class)
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>();
+            let mut roster_grades = Grades::for_roster(&class_members, &semester, conn)?;
+            let final_grades =
+                class_members
+                    .iter()
+                    .map(|member_for_semester| {
+                        let email = &member_for_semester.member.email;
+                        let grades =
+                            roster_grades.remove(email).ok_or(GreaseError::ServerError(
+                                format!("No grades were calculated for {}.", email),
+                            ))?;
+                        let breakdown = serde_json::to_string(&grades.events_with_changes)
+                            .map_err(|err| {
+                                GreaseError::ServerError(format!(
+                                    "Couldn't save the grade breakdown for {}: {}",
+                                    email, err
+                                ))
+                            })?;
+
+                        Ok(FinalGrade {
+                            member: email.clone(),
+                            semester: semester.name.clone(),
+                            grade: grades.grade,
+                            letter: FinalGrade::letter_for(grades.grade).to_owned(),
+                            volunteer_gigs_attended: grades.volunteer_gigs_attended as i32,
+                            breakdown,
+                        })
+                    })
+                    .collect::<GreaseResult<Vec<_>>>()?;
+
+            diesel::delete(final_grade::table.filter(final_grade::semester.eq(&semester.name)))
+                .execute(conn)?;
+            if !final_grades.is_empty() {
+                diesel::insert_into(final_grade::table)
+                    .values(&final_grades)
+                    .execute(conn)?;
+            }
+            FinalizationLog::record(
+                &semester.name,
+                officer,
+                FinalizationAction::Finalized,
+                None,
+                conn,
+            )?;
+
+            Ok(final_grades)
+        })
+    }
+
+    /// Unlock a finalized semester's attendance, recording who did it and why.
+    ///
+    /// Its final grades are kept until it's finalized again, but can't be
+    /// exported until then.
+    pub fn reopen(
+        given_semester: &str,
+        officer: &str,
+        reason: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        if reason.trim().is_empty() {
+            return Err(GreaseError::BadRequest(
+                "A reason is needed to reopen a finalized semester.".to_owned(),
+            ));
+        } else if Self::load(given_semester, conn)?.is_none() {
+            return Err(GreaseError::BadRequest(format!(
+                "The {} semester hasn't been finalized.",
+                given_semester
+            )));
+        }
+
+        conn.transaction(|| {
+            diesel::delete(
+                semester_finalization::table
+                    .filter(semester_finalization::semester.eq(given_semester)),
+            )
+            .execute(conn)?;
+            FinalizationLog::record(
+                given_semester,
+                officer,
+                FinalizationAction::Reopened,
+                Some(reason.trim()),
+                conn,
+            )
+        })
+    }
+}
+
+impl FinalizationLog {
+    pub fn load_for_semester(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<FinalizationLog>> {
+        finalization_log::table
+            .filter(finalization_log::semester.eq(given_semester))
+            .order_by(finalization_log::time.desc())
+            .load::<FinalizationLog>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    fn record(
+        given_semester: &str,
+        officer: &str,
+        action: FinalizationAction,
+        reason: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        diesel::insert_into(finalization_log::table)
+            .values((
+                finalization_log::semester.eq(given_semester),
+                finalization_log::member.eq(officer),
+                finalization_log::action.eq(action),
+                finalization_log::reason.eq(reason),
+                finalization_log::time.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl FinalGrade {
+    /// The letter grade for a score, on the usual 10-point scale.
+    pub fn letter_for(grade: f32) -> &'static str {
+        if grade >= 90.0 {
+            "A"
+        } else if grade >= 80.0 {
+            "B"
+        } else if grade >= 70.0 {
+            "C"
+        } else if grade >= 60.0 {
+            "D"
+        } else {
+            "F"
+        }
+    }
+
+    pub fn load(
+        given_member: &str,
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Option<FinalGrade>> {
+        final_grade::table
+            .filter(
+                final_grade::member
+                    .eq(given_member)
+                    .and(final_grade::semester.eq(given_semester)),
+            )
+            .first::<FinalGrade>(conn)
+            .optional()
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Load the final grades for a semester along with their members,
+    /// ordered by last name.
+    pub fn load_for_semester(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<(FinalGrade, Member)>> {
+        final_grade::table
+            .inner_join(member::table)
+            .filter(final_grade::semester.eq(given_semester))
+            .order_by((member::last_name.asc(), member::first_name.asc()))
+            .load::<(FinalGrade, Member)>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "member": self.member,
+            "semester": self.semester,
+            "grade": self.grade,
+            "letter": self.letter,
+            "volunteerGigsAttended": self.volunteer_gigs_attended,
+            "breakdown": serde_json::from_str::<Value>(&self.breakdown).unwrap_or(Value::Null),
+        })
+    }
+}
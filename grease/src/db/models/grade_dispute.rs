@@ -0,0 +1,355 @@
+use chrono::Local;
+use db::models::grades::Grades;
+use db::models::member::MemberForSemester;
+use db::schema::{
+    event, grade_dispute, grade_dispute_entry, GradeDisputeAction, GradeDisputeState,
+    NotificationCategory,
+};
+use db::{
+    AbsenceRequest, Attendance, Event, GradeDispute, GradeDisputeEntry, GradeDisputeResolution,
+    NotificationPreference, Semester, SemesterFinalization,
+};
+use diesel::prelude::*;
+use email::{template::escape, Email};
+use error::*;
+use serde_json::{json, Value};
+
+impl GradeDispute {
+    pub fn load(dispute_id: i32, conn: &MysqlConnection) -> GreaseResult<GradeDispute> {
+        grade_dispute::table
+            .filter(grade_dispute::id.eq(dispute_id))
+            .first::<GradeDispute>(conn)
+            .optional()?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No grade dispute with id {}.",
+                dispute_id
+            )))
+    }
+
+    /// Load the disputes officers need to look at, oldest first, or every
+    /// dispute if `include_closed` is true.
+    ///
+    /// If `event_types` are given, only disputes about events of those
+    /// types are loaded.
+    pub fn load_queue(
+        include_closed: bool,
+        event_types: Option<&Vec<&str>>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<GradeDispute>> {
+        let mut query = grade_dispute::table
+            .order_by(grade_dispute::created.asc())
+            .into_boxed();
+        if !include_closed {
+            query = query.filter(grade_dispute::state.eq(GradeDisputeState::Open));
+        }
+        if let Some(event_types) = event_types {
+            query = query.filter(
+                grade_dispute::event.eq_any(
+                    event::table
+                        .filter(event::type_.eq_any(event_types))
+                        .select(event::id),
+                ),
+            );
+        }
+
+        query
+            .load::<GradeDispute>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    pub fn load_for_member(
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<GradeDispute>> {
+        grade_dispute::table
+            .filter(grade_dispute::member.eq(given_member))
+            .order_by(grade_dispute::created.desc())
+            .load::<GradeDispute>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Load the disputes about a member's attendance at an event.
+    pub fn load_for_attendance(
+        given_member: &str,
+        event_id: i32,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<GradeDispute>> {
+        grade_dispute::table
+            .filter(
+                grade_dispute::member
+                    .eq(given_member)
+                    .and(grade_dispute::event.eq(event_id)),
+            )
+            .order_by(grade_dispute::created.desc())
+            .load::<GradeDispute>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Dispute the grade change for a member's attendance at an event,
+    /// returning the ID of the new dispute.
+    ///
+    /// The grade change as it is now is saved with the dispute, so that
+    /// officers can see what was disputed even after it changes.
+    pub fn create(
+        given_member: &str,
+        event_id: i32,
+        given_message: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<i32> {
+        if given_message.trim().is_empty() {
+            return Err(GreaseError::BadRequest(
+                "Say why you think the grade change is wrong.".to_owned(),
+            ));
+        } else if Attendance::load(given_member, event_id, conn)?.is_none() {
+            return Err(GreaseError::BadRequest(format!(
+                "No attendance exists for member {} at event {}.",
+                given_member, event_id
+            )));
+        } else if Self::load_for_attendance(given_member, event_id, conn)?
+            .iter()
+            .any(|dispute| dispute.state == GradeDisputeState::Open)
+        {
+            return Err(GreaseError::BadRequest(
+                "There is already an open dispute for this event.".to_owned(),
+            ));
+        }
+
+        let disputed_event = Event::load(event_id, conn)?.event;
+        let semester = Semester::load(&disputed_event.semester, conn)?;
+        let member_for_semester = MemberForSemester::load(given_member, &semester.name, conn)?;
+        let grades = Grades::for_member(
+            &member_for_semester.member,
+            member_for_semester.active_semester.as_ref(),
+            &semester,
+            conn,
+        )?;
+        let disputed = grades.events_with_changes.change_for_event(event_id);
+
+        conn.transaction(|| {
+            diesel::insert_into(grade_dispute::table)
+                .values((
+                    grade_dispute::member.eq(given_member),
+                    grade_dispute::event.eq(event_id),
+                    grade_dispute::message.eq(given_message.trim()),
+                    grade_dispute::disputed_reason
+                        .eq(disputed.as_ref().map(|change| change.reason.clone())),
+                    grade_dispute::disputed_change
+                        .eq(disputed.as_ref().map(|change| change.change)),
+                    grade_dispute::created.eq(Local::now().naive_local()),
+                ))
+                .execute(conn)?;
+
+            grade_dispute::table
+                .select(grade_dispute::id)
+                .order_by(grade_dispute::id.desc())
+                .first(conn)
+        })
+        .map_err(GreaseError::DbError)
+    }
+
+    /// Comment on a dispute, letting the member know if someone else commented.
+    pub fn comment(
+        dispute_id: i32,
+        author: &str,
+        content: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        if content.trim().is_empty() {
+            return Err(GreaseError::BadRequest(
+                "Comments can't be empty.".to_owned(),
+            ));
+        }
+        let dispute = Self::load(dispute_id, conn)?;
+
+        conn.transaction(|| {
+            dispute.record(
+                author,
+                GradeDisputeAction::Commented,
+                Some(content.trim()),
+                conn,
+            )?;
+            if author != dispute.member {
+                dispute.notify_member(GradeDisputeAction::Commented, Some(content.trim()), conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Resolve a dispute by correcting the member's attendance, approving
+    /// an absence for the event, or both.
+    pub fn resolve(
+        dispute_id: i32,
+        officer: &str,
+        resolution: GradeDisputeResolution,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let dispute = Self::load(dispute_id, conn)?;
+        dispute.ensure_open()?;
+        if resolution.attendance.is_none() && !resolution.approve_absence {
+            return Err(GreaseError::BadRequest(
+                "Resolving a dispute needs corrected attendance or an approved absence.".to_owned(),
+            ));
+        }
+
+        conn.transaction(|| {
+            SemesterFinalization::ensure_event_not_finalized(dispute.event, conn)?;
+            if let Some(attendance_form) = &resolution.attendance {
+                Attendance::update(dispute.event, &dispute.member, attendance_form, conn)?;
+            }
+            // the dispute's notification covers the approval, so the usual
+            // absence request emails and webhooks aren't sent
+            if resolution.approve_absence
+                && !AbsenceRequest::excused_for_event(&dispute.member, dispute.event, conn)?
+            {
+                AbsenceRequest::record_approved(
+                    &dispute.member,
+                    dispute.event,
+                    &format!("Approved retroactively for grade dispute #{}", dispute.id),
+                    resolution.comment.as_ref().map(|comment| comment.as_str()),
+                    conn,
+                )?;
+            }
+
+            dispute.close(
+                officer,
+                GradeDisputeState::Resolved,
+                GradeDisputeAction::Resolved,
+                resolution.comment.as_ref().map(|comment| comment.as_str()),
+                conn,
+            )
+        })
+    }
+
+    /// Reject a dispute, leaving the member's grade as it is.
+    pub fn reject(
+        dispute_id: i32,
+        officer: &str,
+        reason: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        if reason.trim().is_empty() {
+            return Err(GreaseError::BadRequest(
+                "Rejecting a dispute needs a reason.".to_owned(),
+            ));
+        }
+        let dispute = Self::load(dispute_id, conn)?;
+        dispute.ensure_open()?;
+
+        conn.transaction(|| {
+            dispute.close(
+                officer,
+                GradeDisputeState::Rejected,
+                GradeDisputeAction::Rejected,
+                Some(reason.trim()),
+                conn,
+            )
+        })
+    }
+
+    pub fn load_history(&self, conn: &MysqlConnection) -> GreaseResult<Vec<GradeDisputeEntry>> {
+        grade_dispute_entry::table
+            .filter(grade_dispute_entry::dispute.eq(self.id))
+            .order_by(grade_dispute_entry::time.asc())
+            .load::<GradeDisputeEntry>(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// The dispute's JSON format, with its comments and how it was closed
+    /// under `history`, oldest first.
+    pub fn to_json_with_history(&self, conn: &MysqlConnection) -> GreaseResult<Value> {
+        let mut json_val = json!(self);
+        json_val["history"] = json!(self.load_history(conn)?);
+
+        Ok(json_val)
+    }
+
+    fn ensure_open(&self) -> GreaseResult<()> {
+        if self.state == GradeDisputeState::Open {
+            Ok(())
+        } else {
+            Err(GreaseError::BadRequest(format!(
+                "Grade dispute #{} has already been closed.",
+                self.id
+            )))
+        }
+    }
+
+    fn close(
+        &self,
+        officer: &str,
+        given_state: GradeDisputeState,
+        action: GradeDisputeAction,
+        content: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        diesel::update(grade_dispute::table.filter(grade_dispute::id.eq(self.id)))
+            .set(grade_dispute::state.eq(given_state))
+            .execute(conn)?;
+        self.record(officer, action, content, conn)?;
+        self.notify_member(action, content, conn)
+    }
+
+    fn record(
+        &self,
+        author: &str,
+        action: GradeDisputeAction,
+        content: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        diesel::insert_into(grade_dispute_entry::table)
+            .values((
+                grade_dispute_entry::dispute.eq(self.id),
+                grade_dispute_entry::author.eq(author),
+                grade_dispute_entry::action.eq(action),
+                grade_dispute_entry::content.eq(content),
+                grade_dispute_entry::time.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn notify_member(
+        &self,
+        action: GradeDisputeAction,
+        content: Option<&str>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let disputed_event = Event::load(self.event, conn)?.event;
+        let (subject, summary) = match action {
+            GradeDisputeAction::Commented => ("New Comment", "has a new comment"),
+            GradeDisputeAction::Resolved => {
+                ("Resolved", "was resolved, and your grade was corrected")
+            }
+            GradeDisputeAction::Rejected => ("Rejected", "was rejected"),
+        };
+        let content = content
+            .map(|content| format!("<p><i>{}</i></p>", escape(content)))
+            .unwrap_or_default();
+        let email = Email::new(
+            String::new(),
+            format!("Grade Dispute {} for {}", subject, disputed_event.name),
+            format!(
+                "\
+                 <p>Your grade dispute for <a href=\"https://gleeclub.gatech.edu/glubhub/#/events/{}\">{}</a> \
+                 on {} {}.</p>\
+                 {}\
+                 ",
+                disputed_event.id,
+                escape(&disputed_event.name),
+                disputed_event.call_time.format("%A, %B %-d"),
+                summary,
+                content
+            ),
+        );
+
+        NotificationPreference::notify(
+            &self.member,
+            NotificationCategory::GradeDisputes,
+            email,
+            conn,
+        )
+        .map(|_queued| ())
+    }
+}
@@ -1,19 +1,37 @@
 use chrono::{Datelike, Duration, Local, NaiveDateTime};
 use db::models::attendance::MemberAttendance;
-use db::{ActiveSemester, Attendance, Event, Member, Semester};
+use db::models::event::EventWithGig;
+use db::models::grading_policy::SemesterGradingPolicy;
+use db::models::member::MemberForSemester;
+use db::schema::{absence_request, attendance, event, gig, AbsenceRequestState, BonusRule};
+use db::{
+    AbsenceRequest, ActiveSemester, Attendance, Event, FinalGrade, Gig, GradingPolicy, Member,
+    Semester, SemesterFinalization, SimulatedAttendance,
+};
 use diesel::prelude::*;
 use error::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 
 #[derive(Serialize)]
 pub struct Grades {
     pub grade: f32,
     #[serde(rename = "eventsWithChanges")]
-    pub events_with_changes: Vec<EventWithGradeChange>,
+    pub events_with_changes: GradeBreakdown,
     #[serde(rename = "volunteerGigsAttended")]
     pub volunteer_gigs_attended: usize,
 }
 
+/// The events that changed a member's grade, either calculated from their
+/// attendance or as they were saved when the semester was finalized.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum GradeBreakdown {
+    Calculated(Vec<EventWithGradeChange>),
+    Finalized(Value),
+}
+
 #[derive(Serialize)]
 pub struct EventWithGradeChange {
     #[serde(flatten)]
@@ -21,7 +39,7 @@ pub struct EventWithGradeChange {
     pub change: GradeChange,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct GradeChange {
     pub reason: String,
     pub change: f32,
@@ -29,6 +47,25 @@ pub struct GradeChange {
     pub partial_score: f32,
 }
 
+impl GradeBreakdown {
+    /// How an event changed the grade, if it's in the breakdown.
+    pub fn change_for_event(self, event_id: i32) -> Option<GradeChange> {
+        match self {
+            GradeBreakdown::Calculated(events) => events
+                .into_iter()
+                .find(|event_with_change| event_with_change.event.event.event.id == event_id)
+                .map(|event_with_change| event_with_change.change),
+            GradeBreakdown::Finalized(events) => events
+                .as_array()?
+                .iter()
+                .find(|event_with_change| event_with_change["id"] == event_id)
+                .and_then(|event_with_change| {
+                    serde_json::from_value(event_with_change["change"].clone()).ok()
+                }),
+        }
+    }
+}
+
 impl Grades {
     pub fn for_member(
         member: &Member,
@@ -36,41 +73,268 @@ impl Grades {
         semester: &Semester,
         conn: &MysqlConnection,
     ) -> GreaseResult<Grades> {
+        if SemesterFinalization::load(&semester.name, conn)?.is_some() {
+            if let Some(final_grade) = FinalGrade::load(&member.email, &semester.name, conn)? {
+                return Self::from_final_grade(&final_grade);
+            }
+        }
+
         let semester_attendance = Attendance::load_for_member_at_all_events(
             &member,
             active_semester.is_some(),
             &semester.name,
             conn,
         )?;
+        let policy = GradingPolicy::load_for_semester(&semester.name, conn)?;
 
-        let initial_grades = Grades {
-            grade: 100.0,
-            volunteer_gigs_attended: 0,
-            events_with_changes: vec![],
+        Ok(Self::calculate(semester, semester_attendance, &policy))
+    }
+
+    /// Grade many members for a semester at once, by email.
+    ///
+    /// The semester's events, attendance, and absence requests are each
+    /// loaded in a single query, instead of once per member like with
+    /// [for_member](Grades::for_member). Grades aren't cached between
+    /// requests, since they change as events pass, except for the saved
+    /// grades of a finalized semester.
+    pub fn for_roster(
+        members: &Vec<MemberForSemester>,
+        semester: &Semester,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<HashMap<String, Grades>> {
+        let final_grades = if SemesterFinalization::load(&semester.name, conn)?.is_some() {
+            FinalGrade::load_for_semester(&semester.name, conn)?
+                .into_iter()
+                .map(|(final_grade, _member)| (final_grade.member.clone(), final_grade))
+                .collect::<HashMap<_, _>>()
+        } else {
+            HashMap::new()
         };
-        let weeks = Self::organize_into_weeks(&semester, semester_attendance);
-        let grades = weeks.fold(initial_grades, |weeks_grades, week| {
+        let events = event::table
+            .left_outer_join(gig::table)
+            .filter(event::semester.eq(&semester.name))
+            .order_by(event::call_time.asc())
+            .load::<(Event, Option<Gig>)>(conn)?;
+        let all_attendance = attendance::table
+            .inner_join(event::table)
+            .filter(event::semester.eq(&semester.name))
+            .select(attendance::all_columns)
+            .load::<Attendance>(conn)?;
+        let absence_requests = absence_request::table
+            .inner_join(event::table)
+            .filter(event::semester.eq(&semester.name))
+            .select(absence_request::all_columns)
+            .load::<AbsenceRequest>(conn)?;
+        let policy = GradingPolicy::load_for_semester(&semester.name, conn)?;
+
+        let attendance_by_member = all_attendance
+            .iter()
+            .map(|attends| ((attends.member.as_str(), attends.event), attends))
+            .collect::<HashMap<_, _>>();
+        let requests_by_member = absence_requests
+            .iter()
+            .map(|request| ((request.member.as_str(), request.event), request))
+            .collect::<HashMap<_, _>>();
+
+        members
+            .iter()
+            .map(|member| {
+                let email = member.member.email.as_str();
+                if let Some(final_grade) = final_grades.get(email) {
+                    return Ok((email.to_owned(), Self::from_final_grade(final_grade)?));
+                }
+
+                let is_active = member.active_semester.is_some();
+                let semester_attendance = events
+                    .iter()
+                    .filter_map(|(given_event, given_gig)| {
+                        let attends = attendance_by_member.get(&(email, given_event.id))?;
+                        Some(MemberAttendance {
+                            rsvp_issue: given_event.rsvp_issue(Some(*attends), is_active),
+                            event: EventWithGig {
+                                event: given_event.clone(),
+                                gig: given_gig.clone(),
+                            },
+                            attendance: Some((*attends).clone()),
+                            absence_request: requests_by_member
+                                .get(&(email, given_event.id))
+                                .map(|request| (*request).clone()),
+                        })
+                    })
+                    .collect();
+
+                Ok((
+                    email.to_owned(),
+                    Self::calculate(semester, semester_attendance, &policy),
+                ))
+            })
+            .collect()
+    }
+
+    /// The grades that were saved when a semester was finalized.
+    fn from_final_grade(final_grade: &FinalGrade) -> GreaseResult<Grades> {
+        let breakdown = serde_json::from_str(&final_grade.breakdown).map_err(|err| {
+            GreaseError::ServerError(format!(
+                "The saved grade breakdown for {} is invalid: {}",
+                final_grade.member, err
+            ))
+        })?;
+
+        Ok(Grades {
+            grade: final_grade.grade,
+            events_with_changes: GradeBreakdown::Finalized(breakdown),
+            volunteer_gigs_attended: final_grade.volunteer_gigs_attended as usize,
+        })
+    }
+
+    /// Grade a member's attendance at every event in a semester by the
+    /// semester's grading policy.
+    pub fn calculate(
+        semester: &Semester,
+        semester_attendance: Vec<MemberAttendance>,
+        policy: &SemesterGradingPolicy,
+    ) -> Grades {
+        let now = Local::now().naive_utc();
+
+        Self::calculate_as_if(semester, semester_attendance, policy, &|event: &Event| {
+            event.call_time <= now
+        })
+    }
+
+    /// Calculate grades as if only the events that `has_happened` says
+    /// have happened so far.
+    fn calculate_as_if(
+        semester: &Semester,
+        semester_attendance: Vec<MemberAttendance>,
+        policy: &SemesterGradingPolicy,
+        has_happened: &dyn Fn(&Event) -> bool,
+    ) -> Grades {
+        let mut grade = policy.policy.start_grade;
+        let mut volunteer_gigs_attended = 0;
+        let mut events_with_changes = Vec::new();
+
+        for week in Self::organize_into_weeks(&semester, semester_attendance) {
             let context = WeeklyAttendanceContext::for_week(&week);
 
-            week.into_iter()
-                .fold(weeks_grades, |mut grades, member_attendance| {
-                    let change =
-                        Self::calculate_grade_change(&member_attendance, &context, grades.grade);
-                    let attended_volunteer_gig =
-                        Self::attended_volunteer_gig(&member_attendance, &context);
-
-                    grades.grade = change.partial_score;
-                    grades.volunteer_gigs_attended += if attended_volunteer_gig { 1 } else { 0 };
-                    grades.events_with_changes.push(EventWithGradeChange {
-                        event: member_attendance,
-                        change,
-                    });
-
-                    grades
-                })
-        });
+            for member_attendance in week {
+                let change = Self::calculate_grade_change(
+                    &member_attendance,
+                    &context,
+                    grade,
+                    has_happened(&member_attendance.event.event),
+                    policy,
+                );
+                if Self::attended_volunteer_gig(&member_attendance, &context, policy) {
+                    volunteer_gigs_attended += 1;
+                }
+
+                grade = change.partial_score;
+                events_with_changes.push(EventWithGradeChange {
+                    event: member_attendance,
+                    change,
+                });
+            }
+        }
+
+        Grades {
+            grade,
+            events_with_changes: GradeBreakdown::Calculated(events_with_changes),
+            volunteer_gigs_attended,
+        }
+    }
 
-        Ok(grades)
+    /// Calculate what a member's grades would be for the current semester
+    /// if their attendance changed, without saving any of the changes.
+    ///
+    /// Changed events are graded even if they haven't happened yet.
+    pub fn simulate(
+        member: &MemberForSemester,
+        changes: &Vec<SimulatedAttendance>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Grades> {
+        let semester = Semester::load_current(conn)?;
+        let mut semester_attendance = Attendance::load_for_member_at_all_events(
+            &member.member,
+            member.active_semester.is_some(),
+            &semester.name,
+            conn,
+        )?;
+
+        for change in changes {
+            let member_attendance = semester_attendance
+                .iter_mut()
+                .find(|member_attendance| member_attendance.event.event.id == change.event)
+                .ok_or(GreaseError::BadRequest(format!(
+                    "There is no event with id {} this semester.",
+                    change.event
+                )))?;
+            Self::apply_simulated_change(&member.member.email, member_attendance, change)?;
+        }
+
+        let policy = GradingPolicy::load_for_semester(&semester.name, conn)?;
+        let now = Local::now().naive_utc();
+
+        Ok(Self::calculate_as_if(
+            &semester,
+            semester_attendance,
+            &policy,
+            &|event: &Event| {
+                event.call_time <= now || changes.iter().any(|change| change.event == event.id)
+            },
+        ))
+    }
+
+    fn apply_simulated_change(
+        member: &str,
+        member_attendance: &mut MemberAttendance,
+        change: &SimulatedAttendance,
+    ) -> GreaseResult<()> {
+        if change.minutes_late.unwrap_or(0) < 0 || change.minutes_left_early.unwrap_or(0) < 0 {
+            return Err(GreaseError::BadRequest(
+                "Minutes late and minutes left early can't be negative.".to_owned(),
+            ));
+        }
+
+        if let Some(attends) = member_attendance.attendance.as_mut() {
+            if let Some(did_attend) = change.did_attend {
+                attends.did_attend = did_attend;
+            }
+            if let Some(minutes_late) = change.minutes_late {
+                attends.minutes_late = minutes_late;
+            }
+            if let Some(minutes_left_early) = change.minutes_left_early {
+                attends.minutes_left_early = minutes_left_early;
+            }
+            if !attends.did_attend {
+                attends.minutes_late = 0;
+                attends.minutes_left_early = 0;
+            }
+        } else if change.did_attend.is_some()
+            || change.minutes_late.is_some()
+            || change.minutes_left_early.is_some()
+        {
+            return Err(GreaseError::BadRequest(format!(
+                "You have no attendance for event {} to change.",
+                change.event
+            )));
+        }
+
+        match change.absence_approved {
+            Some(true) => {
+                member_attendance.absence_request = Some(AbsenceRequest {
+                    member: member.to_owned(),
+                    event: member_attendance.event.event.id,
+                    time: Local::now().naive_local(),
+                    reason: "Simulated absence request".to_owned(),
+                    state: AbsenceRequestState::Approved,
+                    comment: None,
+                });
+            }
+            Some(false) => member_attendance.absence_request = None,
+            None => {}
+        }
+
+        Ok(())
     }
 
     fn organize_into_weeks(
@@ -117,24 +381,31 @@ impl Grades {
         member_attendance: &MemberAttendance,
         context: &WeeklyAttendanceContext,
         grade: f32,
+        has_happened: bool,
+        policy: &SemesterGradingPolicy,
     ) -> GradeChange {
         let event = &member_attendance.event.event;
-        let is_bonus_event = Self::is_bonus_event(&member_attendance, &context);
+        let is_bonus_event = Self::is_bonus_event(&member_attendance, &context, policy);
 
-        let (change, reason) = if event.call_time > Local::now().naive_utc() {
+        let (change, reason) = if !has_happened {
             Self::event_hasnt_happened_yet()
         } else if member_attendance.did_attend() {
-            if context.missed_rehearsal && event.is_gig() {
-                Self::missed_rehearsal(&event)
-            } else if member_attendance.minutes_late() > 0 && event.type_ != Event::OMBUDS {
-                Self::late_for_event(&member_attendance, grade, is_bonus_event)
+            if context.missed_rehearsal
+                && event.is_gig()
+                && policy.policy.rehearsal_required_for_gigs
+            {
+                Self::missed_rehearsal(&event, policy)
+            } else if member_attendance.minutes_missed() > 0
+                && policy.penalizes_lateness(&event.type_)
+            {
+                Self::late_for_event(&member_attendance, grade, is_bonus_event, policy)
             } else if is_bonus_event {
-                Self::attended_bonus_event(&event, grade)
+                Self::attended_bonus_event(&event, grade, policy)
             } else {
                 Self::attended_normal_event()
             }
         } else if member_attendance.should_attend() {
-            Self::should_have_attended(&member_attendance, &context)
+            Self::should_have_attended(&member_attendance, &context, policy)
         } else {
             Self::didnt_need_to_attend()
         };
@@ -142,29 +413,37 @@ impl Grades {
         GradeChange {
             reason,
             change,
-            partial_score: (grade + change).max(0.0).min(100.0),
+            partial_score: policy.cap(grade + change),
         }
     }
 
     fn is_bonus_event(
         member_attendance: &MemberAttendance,
         context: &WeeklyAttendanceContext,
+        policy: &SemesterGradingPolicy,
     ) -> bool {
         let event = &member_attendance.event.event;
+        let bonus_by_type = match policy.bonus_rule(&event.type_) {
+            BonusRule::Always => true,
+            BonusRule::WhenOptional => !member_attendance.should_attend(),
+            BonusRule::Never => false,
+        };
 
-        ([Event::VOLUNTEER_GIG, Event::OMBUDS].contains(&event.type_.as_str()))
-            || (event.type_ == Event::OTHER && !member_attendance.should_attend())
-            || (event.type_ == Event::SECTIONAL && context.missed_sectional.is_none())
+        bonus_by_type
+            || (event.type_ == Event::SECTIONAL
+                && policy.policy.sectional_substitution
+                && context.missed_sectional.is_none())
     }
 
     fn attended_volunteer_gig(
         member_attendance: &MemberAttendance,
         context: &WeeklyAttendanceContext,
+        policy: &SemesterGradingPolicy,
     ) -> bool {
         let event = &member_attendance.event.event;
 
         member_attendance.did_attend()
-            && !context.missed_rehearsal
+            && !(context.missed_rehearsal && policy.policy.rehearsal_required_for_gigs)
             && event.type_ == Event::VOLUNTEER_GIG
             && event.gig_count
     }
@@ -188,28 +467,47 @@ impl Grades {
         member_attendance: &MemberAttendance,
         grade: f32,
         bonus_event: bool,
+        policy: &SemesterGradingPolicy,
     ) -> (f32, String) {
         let event = &member_attendance.event.event;
+        let points = policy.points_for(event);
+        let max_grade = policy.policy.max_grade;
+        let unexcused_late = member_attendance.unexcused_minutes_late();
+        let unexcused_early = member_attendance.unexcused_minutes_left_early();
         let points_lost_for_lateness =
-            Self::points_lost_for_lateness(event, member_attendance.minutes_late());
+            Self::points_lost_for_lateness(event, unexcused_late + unexcused_early, policy);
+        let excuse = Self::excuse_explanation(member_attendance);
+        let missed = match (unexcused_late > 0, unexcused_early > 0) {
+            (true, true) => "lateness and leaving early",
+            (false, true) => "leaving early",
+            _ => "lateness",
+        };
 
-        if bonus_event {
-            if grade + event.points as f32 - points_lost_for_lateness > 100.0 {
+        if unexcused_late + unexcused_early == 0 {
+            // everything missed was excused, so it's as if they were on time
+            let (change, reason) = if bonus_event {
+                Self::attended_bonus_event(event, grade, policy)
+            } else {
+                Self::attended_normal_event()
+            };
+            (change, format!("{}{}", reason, excuse))
+        } else if bonus_event {
+            if grade + points as f32 - points_lost_for_lateness > max_grade {
                 (
-                    100.0 - grade,
+                    max_grade - grade,
                     format!(
                         "Event would grant {}-point bonus, \
-                         but {:.2} points deducted for lateness (capped at 100%)",
-                        event.points, points_lost_for_lateness
+                         but {:.2} points deducted for {} (capped at {}%){}",
+                        points, points_lost_for_lateness, missed, max_grade, excuse
                     ),
                 )
             } else {
                 (
-                    event.points as f32 - points_lost_for_lateness,
+                    points as f32 - points_lost_for_lateness,
                     format!(
                         "Event would grant {}-point bonus, \
-                         but {:.2} points deducted for lateness",
-                        event.points, points_lost_for_lateness
+                         but {:.2} points deducted for {}{}",
+                        points, points_lost_for_lateness, missed, excuse
                     ),
                 )
             }
@@ -217,8 +515,8 @@ impl Grades {
             (
                 -points_lost_for_lateness,
                 format!(
-                    "{:.2} points deducted for lateness to required event",
-                    points_lost_for_lateness
+                    "{:.2} points deducted for {} to required event{}",
+                    points_lost_for_lateness, missed, excuse
                 ),
             )
         } else {
@@ -229,53 +527,85 @@ impl Grades {
         }
     }
 
-    fn points_lost_for_lateness(event: &Event, minutes_late: i32) -> f32 {
-        // Lose points equal to the percentage of the event missed, if they should have attended
+    /// Explains which missed minutes weren't counted because they were excused.
+    fn excuse_explanation(member_attendance: &MemberAttendance) -> String {
+        let excused_minutes = member_attendance.minutes_missed()
+            - member_attendance.unexcused_minutes_late()
+            - member_attendance.unexcused_minutes_left_early();
+
+        if excused_minutes > 0 {
+            format!(
+                " ({} excused minutes not counted: {})",
+                excused_minutes,
+                member_attendance
+                    .excuse_reason()
+                    .unwrap_or("no reason given")
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    fn points_lost_for_lateness(
+        event: &Event,
+        minutes_missed: i32,
+        policy: &SemesterGradingPolicy,
+    ) -> f32 {
+        // Lose points equal to the percentage of the event missed, whether from
+        // arriving late or leaving early, if they should have attended
+        let default_duration = policy.policy.default_event_minutes as f32;
         let event_duration = if let Some(release_time) = event.release_time {
             if release_time <= event.call_time {
-                60.0
+                default_duration
             } else {
                 (release_time - event.call_time).num_minutes() as f32
             }
         } else {
-            60.0
+            default_duration
         };
 
-        (minutes_late as f32 / event_duration) * (event.points as f32)
+        ((minutes_missed as f32).min(event_duration) / event_duration)
+            * (policy.points_for(event) as f32)
     }
 
-    fn missed_rehearsal(event: &Event) -> (f32, String) {
+    fn missed_rehearsal(event: &Event, policy: &SemesterGradingPolicy) -> (f32, String) {
         // If you haven't been to rehearsal this week, you can't get points or gig credit
         if event.type_ == Event::VOLUNTEER_GIG {
             (
                 0.0,
                 format!(
                     "{}-point bonus denied because this week's rehearsal was missed",
-                    event.points
+                    policy.points_for(event)
                 ),
             )
         } else {
             (
-                -(event.points as f32),
+                -(policy.points_for(event) as f32),
                 "Full deduction for unexcused absence from this week's rehearsal".to_owned(),
             )
         }
     }
 
-    fn attended_bonus_event(event: &Event, grade: f32) -> (f32, String) {
+    fn attended_bonus_event(
+        event: &Event,
+        grade: f32,
+        policy: &SemesterGradingPolicy,
+    ) -> (f32, String) {
         // Get back points for volunteer gigs and and extra sectionals and ombuds events
-        if grade + event.points as f32 > 100.0 {
-            let point_change = 100.0 - grade;
+        let points = policy.points_for(event);
+        let max_grade = policy.policy.max_grade;
+        if grade + points as f32 > max_grade {
+            let point_change = max_grade - grade;
             (
                 point_change,
                 format!(
-                    "Event grants {:}-point bonus, but grade is capped at 100%",
-                    event.points
+                    "Event grants {:}-point bonus, but grade is capped at {}%",
+                    points, max_grade
                 ),
             )
         } else {
             (
-                event.points as f32,
+                points as f32,
                 "Full bonus awarded for attending volunteer or extra event".to_owned(),
             )
         }
@@ -284,21 +614,27 @@ impl Grades {
     fn should_have_attended(
         member_attendance: &MemberAttendance,
         context: &WeeklyAttendanceContext,
+        policy: &SemesterGradingPolicy,
     ) -> (f32, String) {
         let event = &member_attendance.event.event;
+        let substitutes_sectionals =
+            event.type_ == Event::SECTIONAL && policy.policy.sectional_substitution;
 
         // Lose the full point value if did not attend
-        if event.type_ == Event::OMBUDS {
+        if !policy.penalizes_absence(&event.type_) {
             (
                 0.0,
-                "You do not lose points for missing an ombuds event".to_owned(),
+                format!(
+                    "You do not lose points for missing {} events",
+                    event.type_.to_lowercase()
+                ),
             )
-        } else if event.type_ == Event::SECTIONAL && context.attended_sectionals {
+        } else if substitutes_sectionals && context.attended_sectionals {
             (
                 0.0,
                 "No deduction because you attended a different sectional this week".to_owned(),
             )
-        } else if event.type_ == Event::SECTIONAL
+        } else if substitutes_sectionals
             && context
                 .missed_sectional
                 .map(|call_time| call_time < event.call_time)
@@ -309,7 +645,7 @@ impl Grades {
                 "No deduction because you already lost points for one sectional this week"
                     .to_owned(),
             )
-        } else if event.type_ == Event::SECTIONAL
+        } else if substitutes_sectionals
             && context
                 .last_sectional
                 .as_ref()
@@ -330,7 +666,7 @@ impl Grades {
             )
         } else {
             (
-                -(event.points as f32),
+                -(policy.points_for(event) as f32),
                 "Full deduction for unexcused absence from event".to_owned(),
             )
         }
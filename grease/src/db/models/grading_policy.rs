@@ -0,0 +1,222 @@
+use db::schema::{event_type, grading_policy, grading_rule, BonusRule};
+use db::{
+    Event, EventType, GradingPolicy, GradingPolicyForm, GradingRule, Semester, SemesterFinalization,
+};
+use diesel::prelude::*;
+use error::*;
+use serde_json::{json, Value};
+
+/// A semester's grading policy, with everything needed to grade by it.
+pub struct SemesterGradingPolicy {
+    pub policy: GradingPolicy,
+    pub rules: Vec<GradingRule>,
+    pub event_types: Vec<EventType>,
+    /// Whether the semester hasn't set its own policy
+    pub is_default: bool,
+}
+
+impl GradingPolicy {
+    /// The policy for semesters that haven't set their own, which grades
+    /// the way the club always has.
+    pub fn default_for(semester: &str) -> (GradingPolicy, Vec<GradingRule>) {
+        let policy = GradingPolicy {
+            semester: semester.to_owned(),
+            start_grade: 100.0,
+            max_grade: 100.0,
+            default_event_minutes: 60,
+            use_type_weights: false,
+            sectional_substitution: true,
+            rehearsal_required_for_gigs: true,
+        };
+        let rule = |event_type: &str, bonus, penalize_lateness, penalize_absence| GradingRule {
+            semester: semester.to_owned(),
+            event_type: event_type.to_owned(),
+            bonus,
+            penalize_lateness,
+            penalize_absence,
+        };
+        let rules = vec![
+            rule(Event::VOLUNTEER_GIG, BonusRule::Always, true, true),
+            rule(Event::OMBUDS, BonusRule::Always, false, false),
+            rule(Event::OTHER, BonusRule::WhenOptional, true, true),
+        ];
+
+        (policy, rules)
+    }
+
+    pub fn load_for_semester(
+        given_semester: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<SemesterGradingPolicy> {
+        let stored = grading_policy::table
+            .filter(grading_policy::semester.eq(given_semester))
+            .first::<GradingPolicy>(conn)
+            .optional()?;
+        let is_default = stored.is_none();
+        let (policy, rules) = match stored {
+            Some(policy) => {
+                let rules = grading_rule::table
+                    .filter(grading_rule::semester.eq(given_semester))
+                    .order_by(grading_rule::event_type.asc())
+                    .load::<GradingRule>(conn)?;
+                (policy, rules)
+            }
+            None => Self::default_for(given_semester),
+        };
+        let event_types = event_type::table
+            .order_by(event_type::name.asc())
+            .load::<EventType>(conn)?;
+
+        Ok(SemesterGradingPolicy {
+            policy,
+            rules,
+            event_types,
+            is_default,
+        })
+    }
+
+    /// Set a semester's policy, replacing all of its rules.
+    pub fn set(
+        given_semester: &str,
+        form: GradingPolicyForm,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        Semester::load(given_semester, conn)?;
+        Self::validate(&form, conn)?;
+
+        let policy = GradingPolicy {
+            semester: given_semester.to_owned(),
+            start_grade: form.start_grade,
+            max_grade: form.max_grade,
+            default_event_minutes: form.default_event_minutes,
+            use_type_weights: form.use_type_weights,
+            sectional_substitution: form.sectional_substitution,
+            rehearsal_required_for_gigs: form.rehearsal_required_for_gigs,
+        };
+        let rules = form
+            .rules
+            .into_iter()
+            .map(|rule| GradingRule {
+                semester: given_semester.to_owned(),
+                event_type: rule.event_type,
+                bonus: rule.bonus,
+                penalize_lateness: rule.penalize_lateness,
+                penalize_absence: rule.penalize_absence,
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(given_semester, conn)?;
+            diesel::replace_into(grading_policy::table)
+                .values(&policy)
+                .execute(conn)?;
+            diesel::delete(grading_rule::table.filter(grading_rule::semester.eq(given_semester)))
+                .execute(conn)?;
+            if !rules.is_empty() {
+                diesel::insert_into(grading_rule::table)
+                    .values(&rules)
+                    .execute(conn)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Remove a semester's policy, so that it's graded by the default one.
+    pub fn reset(given_semester: &str, conn: &MysqlConnection) -> GreaseResult<()> {
+        conn.transaction(|| {
+            SemesterFinalization::ensure_not_finalized(given_semester, conn)?;
+            diesel::delete(
+                grading_policy::table.filter(grading_policy::semester.eq(given_semester)),
+            )
+            .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn validate(form: &GradingPolicyForm, conn: &MysqlConnection) -> GreaseResult<()> {
+        if form.start_grade < 0.0 || form.max_grade < form.start_grade {
+            return Err(GreaseError::BadRequest(
+                "The starting grade must be between 0 and the maximum grade.".to_owned(),
+            ));
+        } else if form.default_event_minutes <= 0 {
+            return Err(GreaseError::BadRequest(
+                "Events must be assumed to last at least a minute.".to_owned(),
+            ));
+        }
+
+        let event_types = event_type::table.load::<EventType>(conn)?;
+        for (index, rule) in form.rules.iter().enumerate() {
+            if !event_types
+                .iter()
+                .any(|event_type| event_type.name == rule.event_type)
+            {
+                return Err(GreaseError::BadRequest(format!(
+                    "There is no event type called {}.",
+                    rule.event_type
+                )));
+            } else if form.rules[..index]
+                .iter()
+                .any(|earlier| earlier.event_type == rule.event_type)
+            {
+                return Err(GreaseError::BadRequest(format!(
+                    "There is more than one rule for {} events.",
+                    rule.event_type
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl SemesterGradingPolicy {
+    fn rule_for(&self, event_type: &str) -> Option<&GradingRule> {
+        self.rules.iter().find(|rule| rule.event_type == event_type)
+    }
+
+    pub fn bonus_rule(&self, event_type: &str) -> BonusRule {
+        self.rule_for(event_type)
+            .map(|rule| rule.bonus)
+            .unwrap_or(BonusRule::Never)
+    }
+
+    pub fn penalizes_lateness(&self, event_type: &str) -> bool {
+        self.rule_for(event_type)
+            .map(|rule| rule.penalize_lateness)
+            .unwrap_or(true)
+    }
+
+    pub fn penalizes_absence(&self, event_type: &str) -> bool {
+        self.rule_for(event_type)
+            .map(|rule| rule.penalize_absence)
+            .unwrap_or(true)
+    }
+
+    /// How many points an event is worth under this policy.
+    pub fn points_for(&self, event: &Event) -> i32 {
+        if self.policy.use_type_weights {
+            self.event_types
+                .iter()
+                .find(|event_type| event_type.name == event.type_)
+                .map(|event_type| event_type.weight)
+                .unwrap_or(event.points)
+        } else {
+            event.points
+        }
+    }
+
+    /// Keep a grade between 0 and the maximum grade.
+    pub fn cap(&self, grade: f32) -> f32 {
+        grade.max(0.0).min(self.policy.max_grade)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "policy": self.policy,
+            "rules": self.rules,
+            "isDefault": self.is_default,
+        })
+    }
+}
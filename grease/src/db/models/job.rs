@@ -0,0 +1,148 @@
+use chrono::{Duration, NaiveDateTime};
+use db::schema::{job, job_run, JobRunStatus};
+use db::{JobRun, JobState};
+use diesel::prelude::*;
+use error::*;
+use uuid::Uuid;
+
+impl JobState {
+    /// How long a run may hold a job's lock before it is presumed to have died.
+    pub const LOCK_MINUTES: i64 = 60;
+
+    /// Load the state of a job, creating it if the job has never been run.
+    pub fn load(job_name: &str, conn: &MysqlConnection) -> GreaseResult<JobState> {
+        diesel::insert_or_ignore_into(job::table)
+            .values(job::name.eq(job_name))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        job::table
+            .filter(job::name.eq(job_name))
+            .first(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Lock the job so that no other run can start until it is unlocked,
+    /// returning the token needed to unlock it, or `None` if it is already
+    /// locked by another run.
+    pub fn lock(
+        job_name: &str,
+        now: NaiveDateTime,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Option<String>> {
+        let token = Uuid::new_v4().to_string();
+        let locked = diesel::update(
+            job::table.filter(
+                job::name
+                    .eq(job_name)
+                    .and(job::locked_until.is_null().or(job::locked_until.lt(now))),
+            ),
+        )
+        .set((
+            job::locked_until.eq(now + Duration::minutes(Self::LOCK_MINUTES)),
+            job::lock_token.eq(&token),
+        ))
+        .execute(conn)
+        .map_err(GreaseError::DbError)?;
+
+        Ok(if locked == 1 { Some(token) } else { None })
+    }
+
+    /// Release the lock on a job, recording when the run started if it succeeded.
+    pub fn unlock(
+        job_name: &str,
+        token: &str,
+        succeeded_run_start: Option<NaiveDateTime>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let locked_job = job::table.filter(job::name.eq(job_name).and(job::lock_token.eq(token)));
+
+        if let Some(started) = succeeded_run_start {
+            diesel::update(locked_job)
+                .set((
+                    job::last_success.eq(started),
+                    job::locked_until.eq(None::<NaiveDateTime>),
+                    job::lock_token.eq(None::<String>),
+                ))
+                .execute(conn)
+        } else {
+            diesel::update(locked_job)
+                .set((
+                    job::locked_until.eq(None::<NaiveDateTime>),
+                    job::lock_token.eq(None::<String>),
+                ))
+                .execute(conn)
+        }
+        .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+}
+
+impl JobRun {
+    /// Record that a run of a job has started, returning the new run's ID.
+    pub fn start(
+        job_name: &str,
+        started: NaiveDateTime,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<i32> {
+        conn.transaction(|| {
+            diesel::insert_into(job_run::table)
+                .values((job_run::job.eq(job_name), job_run::started.eq(started)))
+                .execute(conn)?;
+
+            job_run::table
+                .select(job_run::id)
+                .order_by(job_run::id.desc())
+                .first(conn)
+        })
+        .map_err(GreaseError::DbError)
+    }
+
+    /// Record how a run of a job went.
+    pub fn finish(
+        run_id: i32,
+        finished: NaiveDateTime,
+        result: &GreaseResult<String>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let (status, output) = match result {
+            Ok(output) => (JobRunStatus::Succeeded, output.clone()),
+            Err(error) => (JobRunStatus::Failed, error.as_response().1.to_string()),
+        };
+
+        diesel::update(job_run::table.filter(job_run::id.eq(run_id)))
+            .set((
+                job_run::finished.eq(finished),
+                job_run::status.eq(status),
+                job_run::output.eq(output),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Load the most recent runs of a job, newest first.
+    pub fn load_recent(
+        job_name: &str,
+        limit: i64,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<JobRun>> {
+        job_run::table
+            .filter(job_run::job.eq(job_name))
+            .order_by(job_run::id.desc())
+            .limit(limit)
+            .load(conn)
+            .map_err(GreaseError::DbError)
+    }
+
+    /// Forget about runs that started before the given time.
+    pub fn prune(before: NaiveDateTime, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::delete(job_run::table.filter(job_run::started.lt(before)))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+}
@@ -4,12 +4,13 @@ use db::models::grades::Grades;
 use db::schema::member::dsl::*;
 use db::{
     ActiveSemester, ActiveSemesterUpdate, Attendance, Enrollment, Member, NewMember,
-    RegisterForSemesterForm, Semester, Session,
+    RegisterForSemesterForm, Semester, SemesterFinalization, Session,
 };
 use diesel::prelude::*;
 use error::*;
 use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashMap;
 
 #[derive(Queryable, Debug, PartialEq)]
 pub struct MemberForSemester {
@@ -294,6 +295,52 @@ impl Member {
             .load(conn)
             .map_err(GreaseError::DbError)
     }
+
+    /// Every member's [permissions](Member::permissions), by their email.
+    pub fn permissions_for_all(
+        conn: &MysqlConnection,
+    ) -> GreaseResult<HashMap<String, Vec<MemberPermission>>> {
+        use db::schema::{member_role, role_permission};
+
+        let rows = member_role::table
+            .inner_join(role_permission::table.on(member_role::role.eq(role_permission::role)))
+            .select((
+                member_role::member,
+                role_permission::permission,
+                role_permission::event_type,
+            ))
+            .distinct()
+            .load::<(String, String, Option<String>)>(conn)?;
+
+        let mut all_permissions = HashMap::new();
+        for (given_member, name, event_type) in rows {
+            all_permissions
+                .entry(given_member)
+                .or_insert_with(Vec::new)
+                .push(MemberPermission { name, event_type });
+        }
+
+        Ok(all_permissions)
+    }
+
+    /// Every member's [positions](Member::positions), by their email.
+    pub fn positions_for_all(conn: &MysqlConnection) -> GreaseResult<HashMap<String, Vec<String>>> {
+        use db::schema::member_role;
+
+        let rows = member_role::table
+            .select((member_role::member, member_role::role))
+            .load::<(String, String)>(conn)?;
+
+        let mut all_positions = HashMap::new();
+        for (given_member, role) in rows {
+            all_positions
+                .entry(given_member)
+                .or_insert_with(Vec::new)
+                .push(role);
+        }
+
+        Ok(all_positions)
+    }
 }
 
 impl MemberForSemester {
@@ -420,6 +467,26 @@ impl MemberForSemester {
         semester: &Semester,
         conn: &MysqlConnection,
     ) -> GreaseResult<Value> {
+        let grades =
+            Grades::for_member(&self.member, self.active_semester.as_ref(), semester, conn)?;
+
+        Ok(self.to_json_with_calculated_grades(
+            grades,
+            self.member.positions(conn)?,
+            self.member.permissions(conn)?,
+        ))
+    }
+
+    /// The same as [to_json_with_grades](MemberForSemester::to_json_with_grades),
+    /// but with grades, positions, and permissions that were already loaded,
+    /// like by [for_roster](crate::db::models::grades::Grades::for_roster) and
+    /// [positions_for_all](Member::positions_for_all).
+    pub fn to_json_with_calculated_grades(
+        &self,
+        grades: Grades,
+        positions: Vec<String>,
+        permissions: Vec<MemberPermission>,
+    ) -> Value {
         #[derive(Serialize)]
         struct JsonFormat<'m> {
             #[serde(flatten)]
@@ -428,7 +495,7 @@ impl MemberForSemester {
             permissions: Vec<MemberPermission>,
             positions: Vec<String>,
         }
-        Ok(json!(JsonFormat {
+        json!(JsonFormat {
             member: MemberForSemesterJsonFormat {
                 member: &self.member,
                 enrollment: self.active_semester.as_ref().map(|a_s| &a_s.enrollment),
@@ -437,15 +504,10 @@ impl MemberForSemester {
                     .as_ref()
                     .and_then(|a_s| a_s.section.as_ref()),
             },
-            grades: Grades::for_member(
-                &self.member,
-                self.active_semester.as_ref(),
-                semester,
-                conn
-            )?,
-            positions: self.member.positions(conn)?,
-            permissions: self.member.permissions(conn)?,
-        }))
+            grades,
+            positions,
+            permissions,
+        })
     }
 }
 
@@ -518,7 +580,8 @@ impl ActiveSemester {
         conn: &MysqlConnection,
     ) -> GreaseResult<()> {
         use db::schema::active_semester::dsl::{
-            active_semester, member as member_field, semester as semester_field,
+            active_semester, member as member_field, section as section_field,
+            semester as semester_field,
         };
 
         let active_semester_filter = active_semester.filter(
@@ -528,11 +591,24 @@ impl ActiveSemester {
         );
 
         conn.transaction(|| {
-            if ActiveSemester::load(given_member, given_semester, conn)?.is_some() {
+            if let Some(existing) = ActiveSemester::load(given_member, given_semester, conn)? {
                 if updated_semester.enrollment.is_some() {
                     diesel::update(active_semester_filter)
                         .set(&updated_semester)
                         .execute(conn)?;
+                    // the section can be cleared, so it's set separately
+                    if updated_semester.section != existing.section {
+                        SemesterFinalization::ensure_not_finalized(given_semester, conn)?;
+                        diesel::update(active_semester_filter)
+                            .set(section_field.eq(&updated_semester.section))
+                            .execute(conn)?;
+                        Attendance::recompute_for_member(
+                            given_member,
+                            given_semester,
+                            existing.section.as_ref().map(|section| section.as_str()),
+                            conn,
+                        )?;
+                    }
                 } else {
                     diesel::delete(active_semester_filter).execute(conn)?;
                 }
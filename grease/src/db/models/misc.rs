@@ -1,7 +1,7 @@
 use chrono::{Duration, NaiveDateTime, Utc};
 use db::{
-    Announcement, GigSong, GoogleDoc, MediaType, Member, MemberRole, NewGigSong, NewTodo,
-    NewUniform, PasswordReset, Role, RolePermission, Session, Song, Todo, Uniform, Variable,
+    GigSong, GoogleDoc, MediaType, Member, MemberRole, NewGigSong, NewTodo, NewUniform,
+    PasswordReset, Role, RolePermission, Session, Song, Todo, Uniform, Variable,
 };
 use diesel::prelude::*;
 use error::*;
@@ -67,72 +67,6 @@ impl GoogleDoc {
     }
 }
 
-impl Announcement {
-    pub fn load(announcement_id: i32, conn: &MysqlConnection) -> GreaseResult<Announcement> {
-        use db::schema::announcement::dsl::*;
-
-        announcement
-            .filter(id.eq(announcement_id))
-            .first(conn)
-            .map_err(GreaseError::DbError)
-    }
-
-    pub fn insert(
-        new_content: &str,
-        given_member: &str,
-        given_semester: &str,
-        conn: &MysqlConnection,
-    ) -> GreaseResult<i32> {
-        use db::schema::announcement::dsl::*;
-
-        conn.transaction(|| {
-            diesel::insert_into(announcement)
-                .values((
-                    member.eq(given_member),
-                    semester.eq(given_semester),
-                    content.eq(new_content),
-                ))
-                .execute(conn)?;
-
-            announcement.select(id).order_by(id.desc()).first(conn)
-        })
-        .map_err(GreaseError::DbError)
-    }
-
-    pub fn load_all(conn: &MysqlConnection) -> GreaseResult<Vec<Announcement>> {
-        use db::schema::announcement::dsl::*;
-
-        announcement
-            .order_by(time.desc())
-            .load(conn)
-            .map_err(GreaseError::DbError)
-    }
-
-    pub fn load_all_for_semester(
-        given_semester: &str,
-        conn: &MysqlConnection,
-    ) -> GreaseResult<Vec<Announcement>> {
-        use db::schema::announcement::dsl::*;
-
-        announcement
-            .filter(semester.eq(given_semester).and(archived.eq(false)))
-            .order_by(time.desc())
-            .load(conn)
-            .map_err(GreaseError::DbError)
-    }
-
-    pub fn archive(announcement_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
-        use db::schema::announcement::dsl::*;
-
-        diesel::update(announcement.filter(id.eq(announcement_id)))
-            .set(archived.eq(true))
-            .execute(conn)?;
-
-        Ok(())
-        // format!("No announcement with id {}.", announcement_id),
-    }
-}
-
 impl Uniform {
     pub fn load(uniform_id: i32, conn: &MysqlConnection) -> GreaseResult<Uniform> {
         use db::schema::uniform::dsl::*;
@@ -323,7 +257,7 @@ impl Session {
         conn: &MysqlConnection,
     ) -> GreaseResult<()> {
         use db::schema::session::dsl::*;
-        use util::Email;
+        use email::Email;
 
         let _given_member = Member::load(&email, &conn)?;
 
@@ -346,10 +280,10 @@ impl Session {
                 new_token
             );
 
-            Email {
-                to_address: email,
-                subject: "Reset Your Password".to_owned(),
-                content: format!(
+            Email::new(
+                email,
+                "Reset Your Password".to_owned(),
+                format!(
                     "\
                     <p>\
                         You have requested a password reset on your Glee Club account.\
@@ -358,8 +292,8 @@ impl Session {
                 ",
                     reset_url
                 ),
-            }
-            .send()?;
+            )
+            .queue(conn)?;
 
             Ok(())
         })
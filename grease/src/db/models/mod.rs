@@ -1,11 +1,24 @@
 pub mod absence_request;
+pub mod announcement;
 pub mod attendance;
+pub mod attendance_import;
+pub mod calendar_feed;
 pub mod carpool;
+pub mod check_in;
+pub mod email_outbox;
 pub mod event;
+pub mod event_import;
+pub mod export;
+pub mod final_grade;
+pub mod grade_dispute;
 pub mod grades;
+pub mod grading_policy;
+pub mod job;
 pub mod member;
 pub mod minutes;
 pub mod misc;
+pub mod notification;
 pub mod semester;
 pub mod song;
 pub mod transaction;
+pub mod webhook;
@@ -0,0 +1,169 @@
+use db::schema::{
+    active_semester, member, notification_preference, NotificationCategory, NotificationChannel,
+};
+use db::{Member, NotificationPreference, NotificationPreferenceUpdate, Semester};
+use diesel::prelude::*;
+use email::Email;
+use error::*;
+
+impl NotificationCategory {
+    pub const ALL: [NotificationCategory; 7] = [
+        NotificationCategory::EventReminders,
+        NotificationCategory::Announcements,
+        NotificationCategory::AbsenceDecisions,
+        NotificationCategory::DuesReminders,
+        NotificationCategory::CarpoolChanges,
+        NotificationCategory::OfficerDigests,
+        NotificationCategory::GradeDisputes,
+    ];
+
+    /// How many hours in advance to remind members if they haven't said otherwise.
+    pub fn default_lead_time(&self) -> i32 {
+        match self {
+            NotificationCategory::EventReminders => 48,
+            _other => 0,
+        }
+    }
+}
+
+impl NotificationPreference {
+    /// The preference used for members who haven't set one for a category.
+    pub fn default_for(given_member: &str, given_category: NotificationCategory) -> Self {
+        NotificationPreference {
+            member: given_member.to_owned(),
+            category: given_category,
+            channel: NotificationChannel::Email,
+            lead_time: given_category.default_lead_time(),
+        }
+    }
+
+    pub fn load(
+        given_member: &str,
+        given_category: NotificationCategory,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<NotificationPreference> {
+        notification_preference::table
+            .filter(
+                notification_preference::member
+                    .eq(given_member)
+                    .and(notification_preference::category.eq(given_category)),
+            )
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)
+            .map(|preference| {
+                preference.unwrap_or_else(|| Self::default_for(given_member, given_category))
+            })
+    }
+
+    /// Load a member's preferences for every category, filling in defaults.
+    pub fn load_all_for_member(
+        given_member: &str,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<NotificationPreference>> {
+        let mut saved = notification_preference::table
+            .filter(notification_preference::member.eq(given_member))
+            .load::<NotificationPreference>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(NotificationCategory::ALL
+            .iter()
+            .map(|given_category| {
+                saved
+                    .drain_filter(|preference| &preference.category == given_category)
+                    .next()
+                    .unwrap_or_else(|| Self::default_for(given_member, *given_category))
+            })
+            .collect())
+    }
+
+    pub fn update_for_member(
+        given_member: &str,
+        updates: Vec<NotificationPreferenceUpdate>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        if let Some(bad_update) = updates
+            .iter()
+            .find(|update| update.lead_time.map(|hours| hours < 0).unwrap_or(false))
+        {
+            return Err(GreaseError::BadRequest(format!(
+                "The lead time for {:?} notifications can't be negative.",
+                bad_update.category
+            )));
+        }
+
+        let preferences = updates
+            .into_iter()
+            .map(|update| NotificationPreference {
+                member: given_member.to_owned(),
+                lead_time: update
+                    .lead_time
+                    .unwrap_or_else(|| update.category.default_lead_time()),
+                category: update.category,
+                channel: update.channel,
+            })
+            .collect::<Vec<_>>();
+
+        diesel::replace_into(notification_preference::table)
+            .values(&preferences)
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Load every member active this semester who wants to be emailed about
+    /// the given category, along with their preference for it.
+    pub fn load_recipients(
+        given_category: NotificationCategory,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<(Member, NotificationPreference)>> {
+        let current_semester = Semester::load_current(conn)?;
+        let active_members = member::table
+            .filter(
+                member::email.eq_any(
+                    active_semester::table
+                        .filter(active_semester::semester.eq(&current_semester.name))
+                        .select(active_semester::member),
+                ),
+            )
+            .order_by((member::last_name.asc(), member::first_name.asc()))
+            .load::<Member>(conn)
+            .map_err(GreaseError::DbError)?;
+        let mut saved = notification_preference::table
+            .filter(notification_preference::category.eq(given_category))
+            .load::<NotificationPreference>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(active_members
+            .into_iter()
+            .map(|active_member| {
+                let preference = saved
+                    .drain_filter(|preference| preference.member == active_member.email)
+                    .next()
+                    .unwrap_or_else(|| Self::default_for(&active_member.email, given_category));
+                (active_member, preference)
+            })
+            .filter(|(_member, preference)| preference.channel == NotificationChannel::Email)
+            .collect())
+    }
+
+    /// Queue an email to a member if they want to be notified about the given
+    /// category, returning whether or not it was queued.
+    pub fn notify(
+        given_member: &str,
+        given_category: NotificationCategory,
+        mut email: Email,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<bool> {
+        let preference = Self::load(given_member, given_category, conn)?;
+
+        if preference.channel == NotificationChannel::Email {
+            email.to_address = given_member.to_owned();
+            email.queue(conn)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
@@ -1,5 +1,10 @@
-use db::{Fee, NewTransaction, Semester, Transaction, TransactionBatch, TransactionType};
+use db::schema::{NotificationCategory, WebhookKind};
+use db::{
+    Fee, NewTransaction, NotificationPreference, Semester, Transaction, TransactionBatch,
+    TransactionType, Webhook,
+};
 use diesel::prelude::*;
+use email::{template::escape, Email};
 use error::*;
 
 impl Transaction {
@@ -91,7 +96,19 @@ impl Transaction {
             .values(&new_transactions)
             .execute(conn)?;
 
-        Ok(())
+        Self::announce_new_transactions(&new_transactions, conn)
+    }
+
+    /// Let any subscribed webhooks know that members were charged.
+    fn announce_new_transactions(
+        new_transactions: &Vec<NewTransaction>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        if new_transactions.is_empty() {
+            Ok(())
+        } else {
+            Webhook::trigger(WebhookKind::TransactionCreated, new_transactions, conn)
+        }
     }
 
     pub fn resolve(given_id: i32, is_resolved: bool, conn: &MysqlConnection) -> GreaseResult<()> {
@@ -188,8 +205,9 @@ impl Fee {
         diesel::insert_into(transaction::table)
             .values(&new_transactions)
             .execute(conn)?;
+        Transaction::announce_new_transactions(&new_transactions, conn)?;
 
-        Ok(())
+        Self::notify_members_charged(&new_transactions, &current_semester, conn)
     }
 
     pub fn charge_late_dues_for_semester(conn: &MysqlConnection) -> GreaseResult<()> {
@@ -240,6 +258,40 @@ impl Fee {
         diesel::insert_into(transaction::table)
             .values(&new_transactions)
             .execute(conn)?;
+        Transaction::announce_new_transactions(&new_transactions, conn)?;
+
+        Self::notify_members_charged(&new_transactions, &current_semester, conn)
+    }
+
+    /// Let each charged member know about their new dues, if they want to hear about it.
+    fn notify_members_charged(
+        charges: &Vec<NewTransaction>,
+        semester: &Semester,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        for charge in charges {
+            let email = Email::new(
+                charge.member.clone(),
+                format!("{} for {}", charge.description, semester.name),
+                format!(
+                    "\
+                    <p>\
+                        You have been charged <b>${}</b> for {} this semester ({}). \
+                        Please pay the treasurer as soon as you can.\
+                    </p>\
+                ",
+                    charge.amount,
+                    escape(&charge.description),
+                    escape(&semester.name)
+                ),
+            );
+            NotificationPreference::notify(
+                &charge.member,
+                NotificationCategory::DuesReminders,
+                email,
+                conn,
+            )?;
+        }
 
         Ok(())
     }
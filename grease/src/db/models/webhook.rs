@@ -0,0 +1,501 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use db::models::email_outbox::DeliveryReport;
+use db::schema::{webhook, webhook_delivery, webhook_kind, WebhookDeliveryStatus, WebhookKind};
+use db::{datetime_to_timestamp, NewWebhook, Webhook, WebhookDelivery};
+use diesel::prelude::*;
+use error::*;
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+use webhook::{self as hooks, WebhookClient};
+
+/// A webhook along with the kinds of things it is subscribed to.
+#[derive(Serialize)]
+pub struct WebhookWithKinds {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub kinds: Vec<WebhookKind>,
+}
+
+impl Webhook {
+    pub fn load(webhook_id: i32, conn: &MysqlConnection) -> GreaseResult<Webhook> {
+        webhook::table
+            .filter(webhook::id.eq(webhook_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No webhook with id {}.",
+                webhook_id
+            )))
+    }
+
+    pub fn load_all(conn: &MysqlConnection) -> GreaseResult<Vec<WebhookWithKinds>> {
+        let webhooks = webhook::table
+            .order_by(webhook::id.asc())
+            .load::<Webhook>(conn)
+            .map_err(GreaseError::DbError)?;
+        let kinds = webhook_kind::table
+            .load::<(i32, WebhookKind)>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(webhooks
+            .into_iter()
+            .map(|given_webhook| WebhookWithKinds {
+                kinds: kinds
+                    .iter()
+                    .filter(|(webhook_id, _kind)| *webhook_id == given_webhook.id)
+                    .map(|(_webhook_id, kind)| *kind)
+                    .collect(),
+                webhook: given_webhook,
+            })
+            .collect())
+    }
+
+    /// Create a new webhook, returning its ID and secret.
+    pub fn create(new_webhook: NewWebhook, conn: &MysqlConnection) -> GreaseResult<(i32, String)> {
+        Self::validate_url(&new_webhook.url)?;
+        let secret = new_webhook.secret.clone().unwrap_or_else(|| {
+            format!(
+                "{}{}",
+                Uuid::new_v4().to_simple(),
+                Uuid::new_v4().to_simple()
+            )
+        });
+
+        conn.transaction(|| {
+            diesel::insert_into(webhook::table)
+                .values((
+                    webhook::url.eq(&new_webhook.url),
+                    webhook::secret.eq(&secret),
+                    webhook::active.eq(new_webhook.active.unwrap_or(true)),
+                ))
+                .execute(conn)?;
+            let new_id = webhook::table
+                .select(webhook::id)
+                .order_by(webhook::id.desc())
+                .first(conn)?;
+            Self::set_kinds(new_id, &new_webhook.kinds, conn)?;
+
+            Ok((new_id, secret.clone()))
+        })
+    }
+
+    /// Update a webhook. Its secret is only changed if a new one is given.
+    pub fn update(
+        webhook_id: i32,
+        updated_webhook: NewWebhook,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        Self::load(webhook_id, conn)?;
+        Self::validate_url(&updated_webhook.url)?;
+
+        conn.transaction(|| {
+            diesel::update(webhook::table.filter(webhook::id.eq(webhook_id)))
+                .set((
+                    webhook::url.eq(&updated_webhook.url),
+                    webhook::active.eq(updated_webhook.active.unwrap_or(true)),
+                ))
+                .execute(conn)?;
+            if let Some(new_secret) = &updated_webhook.secret {
+                diesel::update(webhook::table.filter(webhook::id.eq(webhook_id)))
+                    .set(webhook::secret.eq(new_secret))
+                    .execute(conn)?;
+            }
+
+            Self::set_kinds(webhook_id, &updated_webhook.kinds, conn)
+        })
+    }
+
+    pub fn delete(webhook_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        Self::load(webhook_id, conn)?;
+
+        diesel::delete(webhook::table.filter(webhook::id.eq(webhook_id)))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    fn set_kinds(
+        webhook_id: i32,
+        kinds: &Vec<WebhookKind>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        diesel::delete(webhook_kind::table.filter(webhook_kind::webhook.eq(webhook_id)))
+            .execute(conn)?;
+        if kinds.is_empty() {
+            return Ok(());
+        }
+
+        diesel::insert_or_ignore_into(webhook_kind::table)
+            .values(
+                &kinds
+                    .iter()
+                    .map(|kind| {
+                        (
+                            webhook_kind::webhook.eq(webhook_id),
+                            webhook_kind::kind.eq(*kind),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn validate_url(url: &str) -> GreaseResult<()> {
+        if url.starts_with("https://") || url.starts_with("http://") {
+            Ok(())
+        } else {
+            Err(GreaseError::BadRequest(format!(
+                "Webhook URLs must start with http:// or https://, but got {}.",
+                url
+            )))
+        }
+    }
+
+    /// Queue a delivery to every active webhook subscribed to this kind of thing.
+    ///
+    /// Like queueing emails, this only writes to the database, so it is safe
+    /// to call from inside of a transaction.
+    pub fn trigger<T: Serialize>(
+        kind: WebhookKind,
+        data: &T,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<()> {
+        let subscribers = webhook::table
+            .inner_join(webhook_kind::table)
+            .filter(webhook::active.eq(true).and(webhook_kind::kind.eq(kind)))
+            .select(webhook::id)
+            .load::<i32>(conn)
+            .map_err(GreaseError::DbError)?;
+        if subscribers.is_empty() {
+            return Ok(());
+        }
+
+        let now = Local::now().naive_local();
+        let payload = json!({
+            "kind": kind,
+            "time": datetime_to_timestamp(&now),
+            "data": data,
+        })
+        .to_string();
+
+        diesel::insert_into(webhook_delivery::table)
+            .values(
+                &subscribers
+                    .into_iter()
+                    .map(|subscriber| {
+                        (
+                            webhook_delivery::webhook.eq(subscriber),
+                            webhook_delivery::kind.eq(kind),
+                            webhook_delivery::payload.eq(payload.clone()),
+                            webhook_delivery::next_attempt.eq(now),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+}
+
+impl WebhookDelivery {
+    /// How many delivery attempts are made before a delivery is marked as failed.
+    pub const MAX_ATTEMPTS: i32 = 8;
+    /// The most deliveries sent in a single run of the worker.
+    pub const BATCH_SIZE: i64 = 50;
+    /// How long a delivery is held by a worker before another may pick it up.
+    pub const CLAIM_MINUTES: i64 = 10;
+
+    pub fn load(delivery_id: i32, conn: &MysqlConnection) -> GreaseResult<WebhookDelivery> {
+        webhook_delivery::table
+            .filter(webhook_delivery::id.eq(delivery_id))
+            .first(conn)
+            .optional()
+            .map_err(GreaseError::DbError)?
+            .ok_or(GreaseError::BadRequest(format!(
+                "No webhook delivery with id {}.",
+                delivery_id
+            )))
+    }
+
+    pub fn load_for_webhook(
+        webhook_id: i32,
+        given_status: Option<WebhookDeliveryStatus>,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<Vec<WebhookDelivery>> {
+        Webhook::load(webhook_id, conn)?;
+
+        let query = webhook_delivery::table
+            .filter(webhook_delivery::webhook.eq(webhook_id))
+            .order_by(webhook_delivery::id.desc())
+            .into_boxed();
+        let query = if let Some(given_status) = given_status {
+            query.filter(webhook_delivery::status.eq(given_status))
+        } else {
+            query
+        };
+
+        query.load(conn).map_err(GreaseError::DbError)
+    }
+
+    /// Put a failed delivery back in the queue to be sent on the next run.
+    pub fn retry(delivery_id: i32, conn: &MysqlConnection) -> GreaseResult<()> {
+        let delivery = Self::load(delivery_id, conn)?;
+        if delivery.status == WebhookDeliveryStatus::Delivered {
+            return Err(GreaseError::BadRequest(format!(
+                "The webhook delivery with id {} was already delivered.",
+                delivery_id
+            )));
+        }
+
+        diesel::update(webhook_delivery::table.filter(webhook_delivery::id.eq(delivery_id)))
+            .set((
+                webhook_delivery::status.eq(WebhookDeliveryStatus::Pending),
+                webhook_delivery::attempts.eq(0),
+                webhook_delivery::next_attempt.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    /// Attempt to send every queued delivery that is due, to webhooks that are still active.
+    ///
+    /// This works just like [delivering emails](crate::db::OutboxEmail::deliver_pending).
+    pub fn deliver_pending(
+        client: &dyn WebhookClient,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<DeliveryReport> {
+        let now = Local::now().naive_local();
+        let due = webhook_delivery::table
+            .inner_join(webhook::table)
+            .filter(
+                webhook_delivery::status
+                    .eq(WebhookDeliveryStatus::Pending)
+                    .and(webhook_delivery::next_attempt.le(now))
+                    .and(webhook::active.eq(true)),
+            )
+            .order_by(webhook_delivery::id.asc())
+            .limit(Self::BATCH_SIZE)
+            .load::<(WebhookDelivery, Webhook)>(conn)
+            .map_err(GreaseError::DbError)?;
+
+        let mut report = DeliveryReport::default();
+        for (delivery, subscriber) in due {
+            if !delivery.claim(now, conn)? {
+                continue;
+            }
+
+            match delivery.send(&subscriber, client) {
+                Ok(status) => {
+                    delivery.mark_delivered(status, conn)?;
+                    report.sent += 1;
+                }
+                Err(error) => {
+                    if delivery.mark_failed(&error, conn)? == WebhookDeliveryStatus::Failed {
+                        report.failed += 1;
+                    } else {
+                        report.retrying += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Make one attempt at sending the delivery to its webhook, returning
+    /// the status of the response if it was a success.
+    fn send(&self, subscriber: &Webhook, client: &dyn WebhookClient) -> GreaseResult<u16> {
+        let headers = [
+            (
+                hooks::KIND_HEADER,
+                json!(self.kind).as_str().unwrap_or_default().to_owned(),
+            ),
+            (hooks::DELIVERY_HEADER, self.id.to_string()),
+            (
+                hooks::SIGNATURE_HEADER,
+                hooks::sign(&subscriber.secret, &self.payload),
+            ),
+        ];
+
+        client
+            .post(&subscriber.url, &headers, &self.payload)
+            .and_then(|status| {
+                if status >= 200 && status < 300 {
+                    Ok(status)
+                } else {
+                    Err(GreaseError::ServerError(format!(
+                        "The webhook responded with status {}.",
+                        status
+                    )))
+                }
+            })
+    }
+
+    fn claim(&self, now: NaiveDateTime, conn: &MysqlConnection) -> GreaseResult<bool> {
+        diesel::update(
+            webhook_delivery::table.filter(
+                webhook_delivery::id
+                    .eq(self.id)
+                    .and(webhook_delivery::next_attempt.eq(self.next_attempt)),
+            ),
+        )
+        .set(webhook_delivery::next_attempt.eq(now + Duration::minutes(Self::CLAIM_MINUTES)))
+        .execute(conn)
+        .map(|updated| updated == 1)
+        .map_err(GreaseError::DbError)
+    }
+
+    fn mark_delivered(&self, response_status: u16, conn: &MysqlConnection) -> GreaseResult<()> {
+        diesel::update(webhook_delivery::table.filter(webhook_delivery::id.eq(self.id)))
+            .set((
+                webhook_delivery::status.eq(WebhookDeliveryStatus::Delivered),
+                webhook_delivery::attempts.eq(self.attempts + 1),
+                webhook_delivery::response_status.eq(response_status as i32),
+                webhook_delivery::last_error.eq(None::<String>),
+                webhook_delivery::delivered_at.eq(Local::now().naive_local()),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(())
+    }
+
+    fn mark_failed(
+        &self,
+        error: &GreaseError,
+        conn: &MysqlConnection,
+    ) -> GreaseResult<WebhookDeliveryStatus> {
+        let attempts = self.attempts + 1;
+        let new_status = Self::status_after_failure(attempts);
+
+        diesel::update(webhook_delivery::table.filter(webhook_delivery::id.eq(self.id)))
+            .set((
+                webhook_delivery::status.eq(&new_status),
+                webhook_delivery::attempts.eq(attempts),
+                webhook_delivery::last_error.eq(error.as_response().1.to_string()),
+                webhook_delivery::next_attempt
+                    .eq(Local::now().naive_local() + Self::backoff(attempts)),
+            ))
+            .execute(conn)
+            .map_err(GreaseError::DbError)?;
+
+        Ok(new_status)
+    }
+
+    /// Keep retrying a delivery until it has failed
+    /// [MAX_ATTEMPTS](WebhookDelivery::MAX_ATTEMPTS) times.
+    fn status_after_failure(attempts: i32) -> WebhookDeliveryStatus {
+        if attempts >= Self::MAX_ATTEMPTS {
+            WebhookDeliveryStatus::Failed
+        } else {
+            WebhookDeliveryStatus::Pending
+        }
+    }
+
+    /// Wait twice as long after each failure, starting at two minutes and
+    /// never waiting more than a day.
+    fn backoff(attempts: i32) -> Duration {
+        let minutes = 2i64.pow(attempts.max(0).min(11) as u32);
+
+        Duration::minutes(minutes).min(Duration::days(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webhook::MemoryClient;
+
+    fn queued_delivery() -> (WebhookDelivery, Webhook) {
+        let now = Local::now().naive_local();
+        let delivery = WebhookDelivery {
+            id: 12,
+            webhook: 3,
+            kind: WebhookKind::EventCreated,
+            payload: r#"{"kind":"EventCreated","data":{"id":40}}"#.to_owned(),
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            created: now,
+            next_attempt: now,
+            delivered_at: None,
+        };
+        let subscriber = Webhook {
+            id: 3,
+            url: "https://example.com/hooks/grease".to_owned(),
+            secret: "shhh".to_owned(),
+            active: true,
+            created: now,
+        };
+
+        (delivery, subscriber)
+    }
+
+    #[test]
+    fn delivery_is_signed_and_sent_to_the_webhook() {
+        let (delivery, subscriber) = queued_delivery();
+        let client = MemoryClient::new(204);
+
+        assert_eq!(delivery.send(&subscriber, &client).ok(), Some(204));
+
+        let received = client.received_requests();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].url, subscriber.url);
+        assert_eq!(received[0].body, delivery.payload);
+        let header = |name: &str| {
+            received[0]
+                .headers
+                .iter()
+                .find(|(header_name, _value)| header_name == name)
+                .map(|(_name, value)| value.clone())
+        };
+        assert_eq!(header(hooks::KIND_HEADER), Some("EventCreated".to_owned()));
+        assert_eq!(header(hooks::DELIVERY_HEADER), Some("12".to_owned()));
+        assert_eq!(
+            header(hooks::SIGNATURE_HEADER),
+            Some(hooks::sign("shhh", &delivery.payload))
+        );
+    }
+
+    #[test]
+    fn unsuccessful_responses_are_retried() {
+        let (delivery, subscriber) = queued_delivery();
+        let client = MemoryClient::new(503);
+
+        assert!(delivery.send(&subscriber, &client).is_err());
+        assert_eq!(client.received_requests().len(), 1);
+        assert_eq!(
+            WebhookDelivery::status_after_failure(delivery.attempts + 1),
+            WebhookDeliveryStatus::Pending
+        );
+    }
+
+    #[test]
+    fn deliveries_fail_after_max_attempts() {
+        assert_eq!(
+            WebhookDelivery::status_after_failure(WebhookDelivery::MAX_ATTEMPTS - 1),
+            WebhookDeliveryStatus::Pending
+        );
+        assert_eq!(
+            WebhookDelivery::status_after_failure(WebhookDelivery::MAX_ATTEMPTS),
+            WebhookDeliveryStatus::Failed
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_day() {
+        assert_eq!(WebhookDelivery::backoff(1), Duration::minutes(2));
+        assert_eq!(WebhookDelivery::backoff(2), Duration::minutes(4));
+        assert_eq!(WebhookDelivery::backoff(20), Duration::days(1));
+    }
+}
@@ -18,10 +18,11 @@ table! {
         time -> Timestamp,
         reason -> Varchar,
         state -> AbsenceRequestStateMapping,
+        comment -> Nullable<Varchar>,
     }
 }
 
-#[derive(Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
 pub enum Enrollment {
     Class,
     Club,
@@ -40,6 +41,9 @@ table! {
 }
 
 table! {
+    use diesel::sql_types::*;
+    use super::EnrollmentMapping;
+
     announcement (id) {
         id -> Integer,
         member -> Nullable<Varchar>,
@@ -47,6 +51,22 @@ table! {
         time -> Timestamp,
         content -> Longtext,
         archived -> Bool,
+        publish_at -> Nullable<Datetime>,
+        expires_at -> Nullable<Datetime>,
+        section -> Nullable<Varchar>,
+        enrollment -> Nullable<EnrollmentMapping>,
+        role -> Nullable<Varchar>,
+        send_email -> Bool,
+        emailed -> Bool,
+        posted -> Bool,
+    }
+}
+
+table! {
+    announcement_read (member, announcement) {
+        member -> Varchar,
+        announcement -> Integer,
+        time -> Timestamp,
     }
 }
 
@@ -58,6 +78,18 @@ table! {
         did_attend -> Bool,
         confirmed -> Bool,
         minutes_late -> Integer,
+        minutes_left_early -> Integer,
+        lateness_excused -> Bool,
+        early_departure_excused -> Bool,
+        excuse_reason -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    calendar_feed (member) {
+        member -> Varchar,
+        token -> Varchar,
+        created -> Timestamp,
     }
 }
 
@@ -69,6 +101,62 @@ table! {
     }
 }
 
+table! {
+    check_in_attempt (event, member) {
+        event -> Integer,
+        member -> Varchar,
+        failures -> Integer,
+        last_failure -> Datetime,
+    }
+}
+
+table! {
+    check_in_window (event) {
+        event -> Integer,
+        secret -> Varchar,
+        opened_by -> Varchar,
+        opened -> Timestamp,
+        closes -> Datetime,
+    }
+}
+
+table! {
+    email_attachment (id) {
+        id -> Integer,
+        email -> Integer,
+        file_name -> Varchar,
+        content_type -> Varchar,
+        content -> Blob,
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::EmailStatusMapping;
+
+    email_outbox (id) {
+        id -> Integer,
+        to_address -> Varchar,
+        subject -> Varchar,
+        html -> Longtext,
+        text -> Nullable<Longtext>,
+        reply_to -> Nullable<Varchar>,
+        status -> EmailStatusMapping,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
+        created -> Timestamp,
+        next_attempt -> Datetime,
+        sent_at -> Nullable<Datetime>,
+    }
+}
+
 table! {
     event (id) {
         id -> Integer,
@@ -84,6 +172,38 @@ table! {
         gig_count -> Bool,
         default_attend -> Bool,
         section -> Nullable<Varchar>,
+        series -> Nullable<Integer>,
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+pub enum Period {
+    No,
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+    Yearly,
+}
+
+impl Default for Period {
+    fn default() -> Self {
+        Period::No
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::PeriodMapping;
+
+    event_series (id) {
+        id -> Integer,
+        semester -> Varchar,
+        period -> PeriodMapping,
+        repeat_until -> Date,
+        created -> Timestamp,
+        rule -> Nullable<Varchar>,
+        exceptions -> Nullable<Text>,
     }
 }
 
@@ -102,6 +222,38 @@ table! {
     }
 }
 
+table! {
+    final_grade (member, semester) {
+        member -> Varchar,
+        semester -> Varchar,
+        grade -> Float,
+        letter -> Varchar,
+        volunteer_gigs_attended -> Integer,
+        breakdown -> Longtext,
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FinalizationAction {
+    Finalized,
+    Reopened,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::FinalizationActionMapping;
+
+    finalization_log (id) {
+        id -> Integer,
+        semester -> Varchar,
+        member -> Nullable<Varchar>,
+        action -> FinalizationActionMapping,
+        reason -> Nullable<Text>,
+        time -> Datetime,
+    }
+}
+
 table! {
     gig (event) {
         event -> Integer,
@@ -159,12 +311,129 @@ table! {
     }
 }
 
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GradeDisputeState {
+    Open,
+    Resolved,
+    Rejected,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::GradeDisputeStateMapping;
+
+    grade_dispute (id) {
+        id -> Integer,
+        member -> Varchar,
+        event -> Integer,
+        message -> Text,
+        disputed_reason -> Nullable<Varchar>,
+        disputed_change -> Nullable<Float>,
+        state -> GradeDisputeStateMapping,
+        created -> Datetime,
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GradeDisputeAction {
+    Commented,
+    Resolved,
+    Rejected,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::GradeDisputeActionMapping;
+
+    grade_dispute_entry (id) {
+        id -> Integer,
+        dispute -> Integer,
+        author -> Nullable<Varchar>,
+        action -> GradeDisputeActionMapping,
+        content -> Nullable<Text>,
+        time -> Datetime,
+    }
+}
+
+table! {
+    grading_policy (semester) {
+        semester -> Varchar,
+        start_grade -> Float,
+        max_grade -> Float,
+        default_event_minutes -> Integer,
+        use_type_weights -> Bool,
+        sectional_substitution -> Bool,
+        rehearsal_required_for_gigs -> Bool,
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BonusRule {
+    Never,
+    Always,
+    WhenOptional,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::BonusRuleMapping;
+
+    grading_rule (semester, event_type) {
+        semester -> Varchar,
+        event_type -> Varchar,
+        bonus -> BonusRuleMapping,
+        penalize_lateness -> Bool,
+        penalize_absence -> Bool,
+    }
+}
+
 #[derive(Deserialize, Serialize, DbEnum, Debug, PartialEq)]
 pub enum StorageType {
     Local,
     Remote,
 }
 
+table! {
+    imported_event (uid) {
+        uid -> Varchar,
+        event -> Integer,
+        imported -> Timestamp,
+    }
+}
+
+table! {
+    job (name) {
+        name -> Varchar,
+        last_success -> Nullable<Datetime>,
+        locked_until -> Nullable<Datetime>,
+        lock_token -> Nullable<Varchar>,
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+pub enum JobRunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::JobRunStatusMapping;
+
+    job_run (id) {
+        id -> Integer,
+        job -> Varchar,
+        started -> Datetime,
+        finished -> Nullable<Datetime>,
+        status -> JobRunStatusMapping,
+        output -> Nullable<Text>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use super::StorageTypeMapping;
@@ -216,6 +485,35 @@ table! {
     }
 }
 
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq, Eq, Hash)]
+pub enum NotificationCategory {
+    EventReminders,
+    Announcements,
+    AbsenceDecisions,
+    DuesReminders,
+    CarpoolChanges,
+    OfficerDigests,
+    GradeDisputes,
+}
+
+#[derive(Clone, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+pub enum NotificationChannel {
+    Email,
+    None,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::{NotificationCategoryMapping, NotificationChannelMapping};
+
+    notification_preference (member, category) {
+        member -> Varchar,
+        category -> NotificationCategoryMapping,
+        channel -> NotificationChannelMapping,
+        lead_time -> Integer,
+    }
+}
+
 #[derive(Deserialize, Serialize, DbEnum, Debug)]
 pub enum PermissionType {
     Static,
@@ -274,6 +572,14 @@ table! {
     }
 }
 
+table! {
+    semester_finalization (semester) {
+        semester -> Varchar,
+        finalized_by -> Nullable<Varchar>,
+        finalized_at -> Datetime,
+    }
+}
+
 table! {
     session (member) {
         member -> Varchar,
@@ -387,32 +693,118 @@ table! {
     }
 }
 
+table! {
+    webhook (id) {
+        id -> Integer,
+        url -> Varchar,
+        secret -> Varchar,
+        active -> Bool,
+        created -> Timestamp,
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, DbEnum, Debug, PartialEq, Eq, Hash)]
+pub enum WebhookKind {
+    EventCreated,
+    EventUpdated,
+    EventDeleted,
+    GigRequestSubmitted,
+    AbsenceRequestSubmitted,
+    AbsenceRequestDecided,
+    AnnouncementPosted,
+    TransactionCreated,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::WebhookKindMapping;
+
+    webhook_kind (webhook, kind) {
+        webhook -> Integer,
+        kind -> WebhookKindMapping,
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, DbEnum, Debug, PartialEq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+table! {
+    use diesel::sql_types::*;
+    use super::{WebhookDeliveryStatusMapping, WebhookKindMapping};
+
+    webhook_delivery (id) {
+        id -> Integer,
+        webhook -> Integer,
+        kind -> WebhookKindMapping,
+        payload -> Longtext,
+        status -> WebhookDeliveryStatusMapping,
+        attempts -> Integer,
+        response_status -> Nullable<Integer>,
+        last_error -> Nullable<Text>,
+        created -> Timestamp,
+        next_attempt -> Datetime,
+        delivered_at -> Nullable<Datetime>,
+    }
+}
+
 joinable!(absence_request -> event (event));
 joinable!(absence_request -> member (member));
 joinable!(active_semester -> member (member));
 joinable!(active_semester -> section_type (section));
 joinable!(active_semester -> semester (semester));
 joinable!(announcement -> member (member));
+joinable!(announcement -> role (role));
+joinable!(announcement -> section_type (section));
 joinable!(announcement -> semester (semester));
+joinable!(announcement_read -> announcement (announcement));
+joinable!(announcement_read -> member (member));
 joinable!(attendance -> event (event));
 joinable!(attendance -> member (member));
+joinable!(calendar_feed -> member (member));
 joinable!(carpool -> event (event));
 joinable!(carpool -> member (driver));
+joinable!(check_in_attempt -> check_in_window (event));
+joinable!(check_in_attempt -> member (member));
+joinable!(check_in_window -> event (event));
+joinable!(check_in_window -> member (opened_by));
+joinable!(email_attachment -> email_outbox (email));
+joinable!(event -> event_series (series));
 joinable!(event -> event_type (type_));
 joinable!(event -> section_type (section));
 joinable!(event -> semester (semester));
+joinable!(event_series -> semester (semester));
+joinable!(final_grade -> member (member));
+joinable!(final_grade -> semester (semester));
+joinable!(finalization_log -> member (member));
+joinable!(finalization_log -> semester (semester));
 joinable!(gig -> event (event));
 joinable!(gig -> uniform (uniform));
 joinable!(gig_request -> event (event));
 joinable!(gig_song -> event (event));
 joinable!(gig_song -> song (song));
+joinable!(grade_dispute -> event (event));
+joinable!(grade_dispute -> member (member));
+joinable!(grade_dispute_entry -> grade_dispute (dispute));
+joinable!(grade_dispute_entry -> member (author));
+joinable!(grading_policy -> semester (semester));
+joinable!(grading_rule -> event_type (event_type));
+joinable!(grading_rule -> grading_policy (semester));
+joinable!(imported_event -> event (event));
+joinable!(job_run -> job (job));
 joinable!(member_role -> member (member));
 joinable!(member_role -> role (role));
+joinable!(notification_preference -> member (member));
 joinable!(rides_in -> carpool (carpool));
 joinable!(rides_in -> member (member));
 joinable!(role_permission -> event_type (event_type));
 joinable!(role_permission -> permission (permission));
 joinable!(role_permission -> role (role));
+joinable!(semester_finalization -> member (finalized_by));
+joinable!(semester_finalization -> semester (semester));
 joinable!(session -> member (member));
 joinable!(song_link -> media_type (type_));
 joinable!(song_link -> song (song));
@@ -420,30 +812,50 @@ joinable!(todo -> member (member));
 joinable!(transaction -> member (member));
 joinable!(transaction -> semester (semester));
 joinable!(transaction -> transaction_type (type_));
+joinable!(webhook_delivery -> webhook (webhook));
+joinable!(webhook_kind -> webhook (webhook));
 
 allow_tables_to_appear_in_same_query!(
     absence_request,
     active_semester,
     announcement,
+    announcement_read,
     attendance,
+    calendar_feed,
     carpool,
+    check_in_attempt,
+    check_in_window,
+    email_attachment,
+    email_outbox,
     event,
+    event_series,
     event_type,
     fee,
+    final_grade,
+    finalization_log,
     gig,
     gig_request,
     gig_song,
     google_docs,
+    grade_dispute,
+    grade_dispute_entry,
+    grading_policy,
+    grading_rule,
+    imported_event,
+    job,
+    job_run,
     media_type,
     member,
     member_role,
     minutes,
+    notification_preference,
     permission,
     rides_in,
     role,
     role_permission,
     section_type,
     semester,
+    semester_finalization,
     session,
     song,
     song_link,
@@ -452,4 +864,7 @@ allow_tables_to_appear_in_same_query!(
     transaction_type,
     uniform,
     variable,
+    webhook,
+    webhook_delivery,
+    webhook_kind,
 );
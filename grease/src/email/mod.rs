@@ -0,0 +1,206 @@
+//! Outgoing email for the API.
+//!
+//! Emails are built up as an [Email](crate::email::Email) and then queued
+//! in the [outbox](crate::db::OutboxEmail) so that request handlers never wait
+//! on a mail server. The `deliver-emails` [job](crate::jobs) later hands them
+//! off to whichever [EmailTransport](crate::email::transport::EmailTransport)
+//! is selected by the `EMAIL_TRANSPORT` environment variable (see
+//! [from_env](crate::email::transport::from_env) for the available options).
+
+pub mod template;
+pub mod transport;
+
+use chrono::Local;
+use db::OutboxEmail;
+use diesel::MysqlConnection;
+use error::GreaseResult;
+use uuid::Uuid;
+
+/// A single outgoing email.
+///
+/// The `html` body is always sent. If a `text` body is provided, the two are
+/// sent as a `multipart/alternative` message so that mail clients without HTML
+/// support can fall back to the plain text version.
+#[derive(Clone, Debug)]
+pub struct Email {
+    /// Who the email is being sent to
+    pub to_address: String,
+    /// The subject line of the email
+    pub subject: String,
+    /// The HTML body of the email
+    pub html: String,
+    /// An optional plain-text alternative to the HTML body
+    pub text: Option<String>,
+    /// Where replies should be sent, if not to the sender
+    pub reply_to: Option<String>,
+    /// Any files to attach to the email
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file attached to an [Email](crate::email::Email).
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    /// The name of the file as the recipient will see it
+    pub file_name: String,
+    /// The MIME type of the file (e.g. "text/calendar")
+    pub content_type: String,
+    /// The raw contents of the file
+    pub content: Vec<u8>,
+}
+
+impl Email {
+    pub const DEFAULT_NAME: &'static str = "Glee Club Officers";
+    pub const DEFAULT_ADDRESS: &'static str = "gleeclub_officers@lists.gatech.edu";
+
+    pub fn new(to_address: String, subject: String, html: String) -> Email {
+        Email {
+            to_address,
+            subject,
+            html,
+            text: None,
+            reply_to: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// The address that all mail is sent from.
+    ///
+    /// Can be overridden with the `EMAIL_FROM_ADDRESS` environment variable.
+    pub fn from_address() -> String {
+        std::env::var("EMAIL_FROM_ADDRESS").unwrap_or_else(|_| Self::DEFAULT_ADDRESS.to_owned())
+    }
+
+    /// Queue the email to be sent by the outbox worker, returning its outbox ID.
+    pub fn queue(&self, conn: &MysqlConnection) -> GreaseResult<i32> {
+        OutboxEmail::enqueue(self, conn)
+    }
+
+    /// Send the email immediately with the transport configured for the environment.
+    ///
+    /// Prefer [queue](Email::queue) inside of request handlers.
+    pub fn send(&self) -> GreaseResult<()> {
+        transport::from_env()?.send(self)
+    }
+
+    /// Render the email as a full MIME message, headers included.
+    pub fn to_mime(&self) -> String {
+        let mut message = String::new();
+        let mut header = |name: &str, value: &str| {
+            message.push_str(&format!("{}: {}\r\n", name, strip_line_breaks(value)));
+        };
+
+        header(
+            "From",
+            &format!(
+                "{} <{}>",
+                encode_header(Self::DEFAULT_NAME),
+                Self::from_address()
+            ),
+        );
+        header("To", &self.to_address);
+        if let Some(reply_to) = &self.reply_to {
+            header("Reply-To", reply_to);
+        }
+        header("Subject", &encode_header(&self.subject));
+        header("Date", &Local::now().to_rfc2822());
+        header(
+            "Message-ID",
+            &format!("<{}@gleeclub.gatech.edu>", Uuid::new_v4()),
+        );
+        header("MIME-Version", "1.0");
+
+        let body = self.body_part();
+        if self.attachments.is_empty() {
+            message.push_str(&body);
+        } else {
+            let boundary = format!("mixed-{}", Uuid::new_v4());
+            message.push_str(&format!(
+                "Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n",
+                boundary
+            ));
+            message.push_str(&format!("--{}\r\n{}\r\n", boundary, body));
+            for attachment in &self.attachments {
+                message.push_str(&format!(
+                    "--{}\r\n\
+                     Content-Type: {}; name={}\r\n\
+                     Content-Disposition: attachment; filename={}\r\n\
+                     Content-Transfer-Encoding: base64\r\n\r\n\
+                     {}\r\n",
+                    boundary,
+                    strip_line_breaks(&attachment.content_type),
+                    quote_parameter(&attachment.file_name),
+                    quote_parameter(&attachment.file_name),
+                    encode_body(&attachment.content)
+                ));
+            }
+            message.push_str(&format!("--{}--\r\n", boundary));
+        }
+
+        message
+    }
+
+    /// The text and HTML parts of the email, with their own content headers.
+    fn body_part(&self) -> String {
+        let html_part = format!(
+            "Content-Type: text/html; charset=utf-8\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             {}\r\n",
+            encode_body(self.html.as_bytes())
+        );
+
+        if let Some(text) = &self.text {
+            let boundary = format!("alternative-{}", Uuid::new_v4());
+            format!(
+                "Content-Type: multipart/alternative; boundary=\"{boundary}\"\r\n\r\n\
+                 --{boundary}\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Transfer-Encoding: base64\r\n\r\n\
+                 {text}\r\n\
+                 --{boundary}\r\n\
+                 {html}\
+                 --{boundary}--\r\n",
+                boundary = boundary,
+                text = encode_body(text.as_bytes()),
+                html = html_part,
+            )
+        } else {
+            html_part
+        }
+    }
+}
+
+/// Encode a header value as a MIME encoded-word if it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_owned()
+    } else {
+        format!("=?utf-8?B?{}?=", base64::encode(value))
+    }
+}
+
+/// Replace any line breaks in a header value with spaces, so that
+/// user-provided values can't inject headers of their own.
+fn strip_line_breaks(value: &str) -> String {
+    value.replace(|c: char| c == '\r' || c == '\n', " ")
+}
+
+/// Quote a header parameter like an attachment's file name.
+fn quote_parameter(value: &str) -> String {
+    let escaped = strip_line_breaks(value)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+
+    format!("\"{}\"", encode_header(&escaped))
+}
+
+/// Base64-encode a body part, wrapped at 76 characters per line.
+fn encode_body(content: &[u8]) -> String {
+    let encoded = base64::encode(content);
+
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join("\r\n")
+}
@@ -0,0 +1,261 @@
+//! Simple templates for the bodies of outgoing emails.
+//!
+//! Templates are plain text or HTML files with the following syntax:
+//!   * `{{name}}`: Replaced with the value of the `name` variable. In HTML
+//!       templates, the value is escaped first.
+//!   * `{{#if name}} ... {{/if}}`: Only rendered if the `name` variable is set
+//!       and not empty. These can be nested.
+//!   * `{{#unless name}} ... {{/unless}}`: The opposite of `{{#if}}`, only
+//!       rendered if the `name` variable is empty.
+//!
+//! The default templates are compiled into the API from the `templates`
+//! directory, but any of them can be overridden without a rebuild by putting a
+//! file with the same name in the directory named by the
+//! `EMAIL_TEMPLATE_DIRECTORY` environment variable.
+
+use error::{GreaseError, GreaseResult};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The variables available to a template while it is being rendered.
+pub type TemplateContext = HashMap<&'static str, String>;
+
+pub struct Template {
+    /// The name of the file the template was loaded from
+    pub name: String,
+    /// The raw, unrendered template
+    pub source: String,
+}
+
+impl Template {
+    pub const EVENT_REMINDER_HTML: &'static str = "event_reminder.html";
+    pub const EVENT_REMINDER_TEXT: &'static str = "event_reminder.txt";
+
+    /// Load a template by its file name, preferring an override on disk.
+    pub fn load(name: &str) -> GreaseResult<Template> {
+        if let Ok(directory) = std::env::var("EMAIL_TEMPLATE_DIRECTORY") {
+            let path = PathBuf::from(directory).join(name);
+            if path.is_file() {
+                let source = std::fs::read_to_string(&path).map_err(|err| {
+                    GreaseError::ServerError(format!(
+                        "couldn't read email template {}: {}",
+                        name, err
+                    ))
+                })?;
+
+                return Ok(Template {
+                    name: name.to_owned(),
+                    source,
+                });
+            }
+        }
+
+        Self::built_in(name)
+            .map(|source| Template {
+                name: name.to_owned(),
+                source: source.to_owned(),
+            })
+            .ok_or(GreaseError::ServerError(format!(
+                "no email template named {}",
+                name
+            )))
+    }
+
+    fn built_in(name: &str) -> Option<&'static str> {
+        match name {
+            Self::EVENT_REMINDER_HTML => Some(include_str!("../../templates/event_reminder.html")),
+            Self::EVENT_REMINDER_TEXT => Some(include_str!("../../templates/event_reminder.txt")),
+            _ => None,
+        }
+    }
+
+    /// Render the template with the given variables.
+    ///
+    /// Referencing a variable that isn't in the context is an error, so that
+    /// typos in templates don't silently send out emails with holes in them.
+    pub fn render(&self, context: &TemplateContext) -> GreaseResult<String> {
+        let escape_html = self.name.ends_with(".html");
+
+        self.render_section(&self.source, context, escape_html)
+    }
+
+    fn render_section(
+        &self,
+        section: &str,
+        context: &TemplateContext,
+        escape_html: bool,
+    ) -> GreaseResult<String> {
+        let mut rendered = String::new();
+        let mut rest = section;
+
+        while let Some(tag_start) = rest.find("{{") {
+            rendered.push_str(&rest[..tag_start]);
+            let after_open = &rest[tag_start + 2..];
+            let tag_end = after_open
+                .find("}}")
+                .ok_or_else(|| self.syntax_error("a tag was never closed"))?;
+            let tag = after_open[..tag_end].trim();
+            rest = &after_open[tag_end + 2..];
+
+            if let Some((negated, variable)) = Self::block_opener(tag) {
+                let (body, after_block) = self.split_block(rest, Self::block_closer(negated))?;
+                let is_set = self.lookup(variable, context)?.len() > 0;
+                if is_set != negated {
+                    rendered.push_str(&self.render_section(body, context, escape_html)?);
+                }
+                rest = after_block;
+            } else if Self::is_block_closer(tag) {
+                return Err(self.syntax_error(&format!(
+                    "found {{{{{}}}}} without a matching opening tag",
+                    tag
+                )));
+            } else {
+                let value = self.lookup(tag, context)?;
+                if escape_html {
+                    rendered.push_str(&escape(value));
+                } else {
+                    rendered.push_str(value);
+                }
+            }
+        }
+
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    /// If the tag opens a block, whether it is an `{{#unless}}` and the variable it checks.
+    fn block_opener(tag: &str) -> Option<(bool, &str)> {
+        if tag.starts_with("#if ") {
+            Some((false, tag["#if ".len()..].trim()))
+        } else if tag.starts_with("#unless ") {
+            Some((true, tag["#unless ".len()..].trim()))
+        } else {
+            None
+        }
+    }
+
+    fn is_block_closer(tag: &str) -> bool {
+        tag == "/if" || tag == "/unless"
+    }
+
+    /// The tag that closes an `{{#unless}}` block if `negated`, or an `{{#if}}` block otherwise.
+    fn block_closer(negated: bool) -> &'static str {
+        if negated {
+            "/unless"
+        } else {
+            "/if"
+        }
+    }
+
+    /// Split the text following a block's opening tag into the body of the
+    /// block and everything after its matching closing tag.
+    ///
+    /// Every block inside must be closed by the same kind of tag it was
+    /// opened with, starting with `closer` for the outermost one.
+    fn split_block<'s>(&self, text: &'s str, closer: &str) -> GreaseResult<(&'s str, &'s str)> {
+        let mut expected_closers = vec![closer];
+        let mut position = 0;
+
+        while let Some(tag_start) = text[position..].find("{{") {
+            let tag_start = position + tag_start;
+            let tag_end = text[tag_start..]
+                .find("}}")
+                .map(|end| tag_start + end)
+                .ok_or_else(|| self.syntax_error("a tag was never closed"))?;
+            let tag = text[tag_start + 2..tag_end].trim();
+
+            if let Some((negated, _variable)) = Self::block_opener(tag) {
+                expected_closers.push(Self::block_closer(negated));
+            } else if Self::is_block_closer(tag) {
+                let expected = expected_closers.pop().unwrap_or_default();
+                if tag != expected {
+                    return Err(self.syntax_error(&format!(
+                        "found {{{{{}}}}} where {{{{{}}}}} was expected",
+                        tag, expected
+                    )));
+                } else if expected_closers.is_empty() {
+                    return Ok((&text[..tag_start], &text[tag_end + 2..]));
+                }
+            }
+
+            position = tag_end + 2;
+        }
+
+        Err(self.syntax_error("a block was never closed"))
+    }
+
+    fn lookup<'c>(&self, variable: &str, context: &'c TemplateContext) -> GreaseResult<&'c str> {
+        context
+            .get(variable)
+            .map(|value| value.as_str())
+            .ok_or_else(|| {
+                GreaseError::ServerError(format!(
+                    "email template {} uses the variable \"{}\", which wasn't provided",
+                    self.name, variable
+                ))
+            })
+    }
+
+    fn syntax_error(&self, problem: &str) -> GreaseError {
+        GreaseError::ServerError(format!("invalid email template {}: {}", self.name, problem))
+    }
+}
+
+/// Escape text for safe inclusion in HTML.
+pub fn escape(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                other => escaped.push(other),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(source: &str) -> Template {
+        Template {
+            name: "test.txt".to_owned(),
+            source: source.to_owned(),
+        }
+    }
+
+    fn context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.insert("carpool", "yes".to_owned());
+        context.insert("rsvpIssue", String::new());
+        context
+    }
+
+    #[test]
+    fn nested_blocks_render() {
+        let rendered = template("{{#if carpool}}a{{#unless rsvpIssue}}b{{/unless}}c{{/if}}d")
+            .render(&context())
+            .unwrap();
+
+        assert_eq!(rendered, "abcd");
+    }
+
+    #[test]
+    fn blocks_must_be_closed_by_the_same_kind_of_tag() {
+        assert!(template("{{#if carpool}}a{{/unless}}")
+            .render(&context())
+            .is_err());
+        assert!(template("{{#unless rsvpIssue}}a{{/if}}")
+            .render(&context())
+            .is_err());
+        assert!(
+            template("{{#if carpool}}{{#unless rsvpIssue}}a{{/if}}{{/unless}}")
+                .render(&context())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! The backends that actually deliver email.
+
+use super::Email;
+use chrono::Local;
+use error::{GreaseError, GreaseResult};
+use lettre::smtp::authentication::Credentials;
+use lettre::{EmailAddress, Envelope, SendableEmail, SmtpClient, Transport};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+#[cfg(test)]
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// A way of delivering outgoing email.
+pub trait EmailTransport {
+    fn send(&self, email: &Email) -> GreaseResult<()>;
+}
+
+/// Load the email transport selected for this environment.
+///
+/// The transport is chosen by the `EMAIL_TRANSPORT` environment variable:
+///   * `smtp`: Send through an SMTP server at `SMTP_HOST`, optionally logging in
+///       with `SMTP_USERNAME` and `SMTP_PASSWORD`.
+///   * `sendmail`: Pipe messages to the `sendmail` binary (the default). Its path
+///       can be overridden with `SENDMAIL_PATH`.
+///   * `file`: Write each message as an `.eml` file into `EMAIL_DROP_DIRECTORY`
+///       instead of sending it, for use during development.
+pub fn from_env() -> GreaseResult<Box<dyn EmailTransport>> {
+    let transport_name = std::env::var("EMAIL_TRANSPORT").unwrap_or_else(|_| "sendmail".to_owned());
+
+    match transport_name.as_str() {
+        "smtp" => Ok(Box::new(SmtpTransport::from_env()?)),
+        "sendmail" => Ok(Box::new(SendmailTransport::from_env())),
+        "file" => Ok(Box::new(FileTransport::from_env()?)),
+        other => Err(GreaseError::ServerError(format!(
+            "Unknown email transport \"{}\" (expected \"smtp\", \"sendmail\", or \"file\").",
+            other
+        ))),
+    }
+}
+
+/// Delivers email through an SMTP server.
+pub struct SmtpTransport {
+    pub host: String,
+    pub credentials: Option<(String, String)>,
+}
+
+impl SmtpTransport {
+    pub fn from_env() -> GreaseResult<SmtpTransport> {
+        let host = std::env::var("SMTP_HOST").map_err(|_err| {
+            GreaseError::ServerError("SMTP_HOST must be set to send email over SMTP.".to_owned())
+        })?;
+        let credentials = match (
+            std::env::var("SMTP_USERNAME"),
+            std::env::var("SMTP_PASSWORD"),
+        ) {
+            (Ok(username), Ok(password)) => Some((username, password)),
+            _ => None,
+        };
+
+        Ok(SmtpTransport { host, credentials })
+    }
+}
+
+impl EmailTransport for SmtpTransport {
+    fn send(&self, email: &Email) -> GreaseResult<()> {
+        let parse_address = |address: String| {
+            EmailAddress::new(address.clone()).map_err(|err| {
+                GreaseError::BadRequest(format!("invalid email address {}: {}", address, err))
+            })
+        };
+        let envelope = Envelope::new(
+            Some(parse_address(Email::from_address())?),
+            vec![parse_address(email.to_address.clone())?],
+        )
+        .map_err(|err| GreaseError::ServerError(format!("Couldn't address an email: {}", err)))?;
+        let message = SendableEmail::new(
+            envelope,
+            Uuid::new_v4().to_string(),
+            email.to_mime().into_bytes(),
+        );
+
+        let mut client = SmtpClient::new_simple(&self.host).map_err(|err| {
+            GreaseError::ServerError(format!(
+                "Couldn't connect to the SMTP server at {}: {}",
+                self.host, err
+            ))
+        })?;
+        if let Some((username, password)) = &self.credentials {
+            client = client.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        client
+            .transport()
+            .send(message)
+            .map(|_response| ())
+            .map_err(|err| {
+                GreaseError::ServerError(format!("Couldn't send email over SMTP: {}", err))
+            })
+    }
+}
+
+/// Delivers email by piping it to the local `sendmail` binary.
+pub struct SendmailTransport {
+    pub command: String,
+}
+
+impl SendmailTransport {
+    pub fn from_env() -> SendmailTransport {
+        SendmailTransport {
+            command: std::env::var("SENDMAIL_PATH")
+                .unwrap_or_else(|_| "/usr/sbin/sendmail".to_owned()),
+        }
+    }
+}
+
+impl EmailTransport for SendmailTransport {
+    fn send(&self, email: &Email) -> GreaseResult<()> {
+        let mut sendmail = Command::new(&self.command)
+            .args(&["-t", "-i"])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                GreaseError::ServerError(format!("Couldn't run sendmail to send an email: {}", err))
+            })?;
+        sendmail
+            .stdin
+            .as_mut()
+            .ok_or(GreaseError::ServerError(
+                "No stdin was available for sendmail.".to_owned(),
+            ))?
+            .write_all(email.to_mime().as_bytes())
+            .map_err(|err| {
+                GreaseError::ServerError(format!("Couldn't send an email with sendmail: {}", err))
+            })?;
+        let output = sendmail.wait_with_output().map_err(|err| {
+            GreaseError::ServerError(format!(
+                "The output of the sendmail command couldn't be retrieved: {}",
+                err
+            ))
+        })?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(GreaseError::ServerError(format!(
+                "sendmail failed to send an email with error code {}: {}",
+                output.status.code().unwrap_or(1),
+                String::from_utf8_lossy(&output.stderr)
+            )))
+        }
+    }
+}
+
+/// Writes each email to its own `.eml` file in a directory instead of sending it.
+pub struct FileTransport {
+    pub directory: PathBuf,
+}
+
+impl FileTransport {
+    pub fn from_env() -> GreaseResult<FileTransport> {
+        let directory = std::env::var("EMAIL_DROP_DIRECTORY").map_err(|_err| {
+            GreaseError::ServerError(
+                "EMAIL_DROP_DIRECTORY must be set to use the file email transport.".to_owned(),
+            )
+        })?;
+
+        Ok(FileTransport {
+            directory: PathBuf::from(directory),
+        })
+    }
+}
+
+impl EmailTransport for FileTransport {
+    fn send(&self, email: &Email) -> GreaseResult<()> {
+        std::fs::create_dir_all(&self.directory).map_err(|err| {
+            GreaseError::ServerError(format!("Couldn't create the email drop directory: {}", err))
+        })?;
+        let file_name = format!(
+            "{}-{}.eml",
+            Local::now().format("%Y%m%d%H%M%S"),
+            Uuid::new_v4()
+        );
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(self.directory.join(file_name))
+            .map_err(|err| {
+                GreaseError::ServerError(format!("error opening email file: {}", err))
+            })?;
+
+        file.write_all(email.to_mime().as_bytes())
+            .map_err(|err| GreaseError::ServerError(format!("error writing email file: {}", err)))
+    }
+}
+
+/// Keeps every email it is given in memory instead of sending it.
+///
+/// Useful for exercising code that sends email without a mail server.
+#[cfg(test)]
+pub struct MemoryTransport {
+    pub sent: Mutex<Vec<Email>>,
+}
+
+#[cfg(test)]
+impl MemoryTransport {
+    pub fn new() -> MemoryTransport {
+        MemoryTransport {
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// All emails sent through this transport so far, oldest first.
+    pub fn sent_emails(&self) -> Vec<Email> {
+        self.sent
+            .lock()
+            .map(|sent| sent.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl EmailTransport for MemoryTransport {
+    fn send(&self, email: &Email) -> GreaseResult<()> {
+        self.sent
+            .lock()
+            .map_err(|_err| GreaseError::ServerError("The email outbox was poisoned.".to_owned()))?
+            .push(email.clone());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use email::Attachment;
+
+    #[test]
+    fn memory_transport_keeps_sent_emails() {
+        let transport = MemoryTransport::new();
+        let mut email = Email::new(
+            "member@gatech.edu".to_owned(),
+            "Rehearsal\r\nBcc: everyone@gatech.edu".to_owned(),
+            "<p>See you there.</p>".to_owned(),
+        );
+        email.attachments.push(Attachment {
+            file_name: "event \"1\".ics".to_owned(),
+            content_type: "text/calendar".to_owned(),
+            content: b"BEGIN:VCALENDAR".to_vec(),
+        });
+
+        transport.send(&email).unwrap();
+
+        let sent = transport.sent_emails();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to_address, "member@gatech.edu");
+
+        let message = sent[0].to_mime();
+        assert!(!message.contains("\r\nBcc:"));
+        assert!(message.contains("Subject: Rehearsal  Bcc: everyone@gatech.edu\r\n"));
+        assert!(message.contains("filename=\"event \\\"1\\\".ics\""));
+    }
+}
@@ -0,0 +1,321 @@
+//! Spreadsheets for officers to download, as CSV or XLSX files.
+//!
+//! XLSX files are written by hand with just a single worksheet and inline
+//! strings, which every spreadsheet program can open.
+
+use db::models::member::MemberForSemester;
+use db::schema::Enrollment;
+use email::template::escape;
+use error::*;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// The file formats spreadsheets can be exported as.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    /// Parse the format from a query parameter, defaulting to CSV.
+    pub fn parse(format: Option<String>) -> GreaseResult<ExportFormat> {
+        match format.as_ref().map(|format| format.to_lowercase()) {
+            None => Ok(ExportFormat::Csv),
+            Some(ref format) if format == "csv" => Ok(ExportFormat::Csv),
+            Some(ref format) if format == "xlsx" => Ok(ExportFormat::Xlsx),
+            Some(other) => Err(GreaseError::BadRequest(format!(
+                "Exports can only be \"csv\" or \"xlsx\", not \"{}\".",
+                other
+            ))),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Which members to include in an export.
+#[derive(Default)]
+pub struct ExportFilter {
+    pub section: Option<String>,
+    pub enrollment: Option<Enrollment>,
+}
+
+impl ExportFilter {
+    /// Parse the filter from query parameters, where enrollment is
+    /// either "class" or "club".
+    pub fn parse(
+        section: Option<String>,
+        enrollment: Option<String>,
+    ) -> GreaseResult<ExportFilter> {
+        let enrollment = match enrollment.as_ref().map(|enrollment| enrollment.as_str()) {
+            None | Some("") => None,
+            Some("class") => Some(Enrollment::Class),
+            Some("club") => Some(Enrollment::Club),
+            Some(other) => {
+                return Err(GreaseError::BadRequest(format!(
+                    "Enrollment can only be \"class\" or \"club\", not \"{}\".",
+                    other
+                )))
+            }
+        };
+
+        Ok(ExportFilter {
+            section: section.filter(|section| !section.is_empty()),
+            enrollment,
+        })
+    }
+
+    pub fn includes(&self, member: &MemberForSemester) -> bool {
+        let enrollment = member
+            .active_semester
+            .as_ref()
+            .map(|active_semester| active_semester.enrollment);
+
+        self.section
+            .as_ref()
+            .map(|section| member.section() == Some(section.as_str()))
+            .unwrap_or(true)
+            && self
+                .enrollment
+                .map(|wanted| enrollment == Some(wanted))
+                .unwrap_or(true)
+    }
+}
+
+/// A single cell of a spreadsheet.
+pub enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Cell {
+    pub fn text<T: ToString>(text: T) -> Cell {
+        Cell::Text(text.to_string())
+    }
+
+    fn to_csv(&self) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            // spreadsheet programs run text starting with these as formulas
+            Cell::Text(text) if text.starts_with(|c| "=+-@".contains(c)) => format!("'{}", text),
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+            Cell::Bool(true) => "TRUE".to_owned(),
+            Cell::Bool(false) => "FALSE".to_owned(),
+        }
+    }
+
+    fn to_xml(&self, reference: &str) -> String {
+        match self {
+            Cell::Empty => String::new(),
+            Cell::Text(text) => format!(
+                r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                reference,
+                escape(text)
+            ),
+            Cell::Number(number) => format!(r#"<c r="{}"><v>{}</v></c>"#, reference, number),
+            Cell::Bool(value) => format!(
+                r#"<c r="{}" t="b"><v>{}</v></c>"#,
+                reference,
+                if *value { 1 } else { 0 }
+            ),
+        }
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(text: Option<String>) -> Cell {
+        text.map(Cell::Text).unwrap_or(Cell::Empty)
+    }
+}
+
+/// A spreadsheet with a row of headers.
+pub struct Spreadsheet {
+    /// The name of the worksheet, and the start of the file name
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Spreadsheet {
+    pub fn new<T: ToString>(name: &str, headers: Vec<T>) -> Spreadsheet {
+        Spreadsheet {
+            name: name.to_owned(),
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// The name the spreadsheet should be downloaded as.
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        let name = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+
+        format!("{}.{}", name, format.extension())
+    }
+
+    pub fn to_file(&self, format: ExportFormat) -> GreaseResult<Vec<u8>> {
+        match format {
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Xlsx => self.to_xlsx(),
+        }
+    }
+
+    pub fn to_csv(&self) -> GreaseResult<Vec<u8>> {
+        let write_error =
+            |err: csv::Error| GreaseError::ServerError(format!("Couldn't write the CSV: {}", err));
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.headers).map_err(write_error)?;
+        for row in &self.rows {
+            writer
+                .write_record(row.iter().map(Cell::to_csv))
+                .map_err(write_error)?;
+        }
+
+        writer.into_inner().map_err(|err| {
+            GreaseError::ServerError(format!("Couldn't write the CSV: {}", err.error()))
+        })
+    }
+
+    pub fn to_xlsx(&self) -> GreaseResult<Vec<u8>> {
+        let files = vec![
+            ("[Content_Types].xml", CONTENT_TYPES.to_owned()),
+            ("_rels/.rels", ROOT_RELATIONSHIPS.to_owned()),
+            ("xl/workbook.xml", self.workbook_xml()),
+            (
+                "xl/_rels/workbook.xml.rels",
+                WORKBOOK_RELATIONSHIPS.to_owned(),
+            ),
+            ("xl/worksheets/sheet1.xml", self.worksheet_xml()),
+        ];
+
+        let write_error =
+            |err: String| GreaseError::ServerError(format!("Couldn't write the XLSX: {}", err));
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in files {
+            zip.start_file(path, FileOptions::default())
+                .map_err(|err| write_error(err.to_string()))?;
+            zip.write_all(contents.as_bytes())
+                .map_err(|err| write_error(err.to_string()))?;
+        }
+
+        zip.finish()
+            .map(|cursor| cursor.into_inner())
+            .map_err(|err| write_error(err.to_string()))
+    }
+
+    fn workbook_xml(&self) -> String {
+        // worksheet names can't have some characters or be longer than 31
+        let sheet_name = self
+            .name
+            .chars()
+            .filter(|c| !"[]:*?/\\".contains(*c))
+            .take(31)
+            .collect::<String>();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            escape(&sheet_name)
+        )
+    }
+
+    fn worksheet_xml(&self) -> String {
+        let header_row = self
+            .headers
+            .iter()
+            .map(|header| Cell::Text(header.clone()))
+            .collect::<Vec<_>>();
+        let rows = std::iter::once(&header_row)
+            .chain(self.rows.iter())
+            .enumerate()
+            .map(|(row_index, row)| {
+                let cells = row
+                    .iter()
+                    .enumerate()
+                    .map(|(column_index, cell)| {
+                        cell.to_xml(&format!("{}{}", column_name(column_index), row_index + 1))
+                    })
+                    .collect::<String>();
+                format!(r#"<row r="{}">{}</row>"#, row_index + 1, cells)
+            })
+            .collect::<String>();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#,
+            rows
+        )
+    }
+}
+
+/// The letters of a column, like "A" for the first and "AA" for the 27th.
+fn column_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut remaining = index + 1;
+    while remaining > 0 {
+        let letter = (remaining - 1) % 26;
+        name.push((b'A' + letter as u8) as char);
+        remaining = (remaining - 1) / 26;
+    }
+
+    name.into_iter().rev().collect()
+}
+
+const CONTENT_TYPES: &'static str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELATIONSHIPS: &'static str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELATIONSHIPS: &'static str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_that_looks_like_a_formula_is_quoted() {
+        assert_eq!(Cell::text("=SUM(A1:A9)").to_csv(), "'=SUM(A1:A9)");
+        assert_eq!(Cell::text("+1 404 555 0100").to_csv(), "'+1 404 555 0100");
+        assert_eq!(Cell::text("-2").to_csv(), "'-2");
+        assert_eq!(Cell::text("@me").to_csv(), "'@me");
+    }
+
+    #[test]
+    fn other_cells_are_written_as_is() {
+        assert_eq!(Cell::text("Tenor 1").to_csv(), "Tenor 1");
+        assert_eq!(Cell::text("a=b").to_csv(), "a=b");
+        assert_eq!(Cell::Number(-2.0).to_csv(), "-2");
+        assert_eq!(Cell::Bool(true).to_csv(), "TRUE");
+        assert_eq!(Cell::Empty.to_csv(), "");
+    }
+}
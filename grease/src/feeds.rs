@@ -0,0 +1,208 @@
+//! Public feeds of the club's performances, for calendar apps, feed
+//! readers, and search engines.
+//!
+//! Every feed includes every public gig from every semester, latest first.
+
+use chrono::{DateTime, Duration, Local, Utc};
+use db::models::calendar_feed::to_utc;
+use db::{Event, Gig};
+use email::template::escape;
+use icalendar::{Calendar, Component, Event as CalEvent, Property};
+use serde_json::{json, Value};
+use std::iter::FromIterator;
+
+pub const CLUB_NAME: &'static str = "Georgia Tech Glee Club";
+pub const SITE_URL: &'static str = "https://gleeclub.gatech.edu/";
+
+/// A public gig, ready to be put in a feed.
+pub struct PublicGig<'a> {
+    pub event: &'a Event,
+    pub gig: &'a Gig,
+}
+
+impl<'a> PublicGig<'a> {
+    pub fn all(gigs: &'a Vec<(Event, Gig)>) -> Vec<PublicGig<'a>> {
+        gigs.iter()
+            .map(|(event, gig)| PublicGig { event, gig })
+            .collect()
+    }
+
+    fn id(&self) -> String {
+        format!("event-{}@gleeclub.gatech.edu", self.event.id)
+    }
+
+    fn start(&self) -> DateTime<Utc> {
+        to_utc(&self.gig.performance_time)
+    }
+
+    /// Gigs without a release time are assumed to last an hour.
+    fn end(&self) -> DateTime<Utc> {
+        let end = self
+            .event
+            .release_time
+            .filter(|release_time| *release_time > self.gig.performance_time)
+            .unwrap_or(self.gig.performance_time + Duration::hours(1));
+
+        to_utc(&end)
+    }
+
+    fn location(&self) -> String {
+        self.event.location.clone().unwrap_or_default()
+    }
+
+    fn summary(&self) -> String {
+        self.gig.summary.clone().unwrap_or_default()
+    }
+
+    fn description(&self) -> String {
+        self.gig
+            .description
+            .clone()
+            .or_else(|| self.gig.summary.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// An iCalendar file with every public gig.
+pub fn to_ics(gigs: &Vec<PublicGig>) -> String {
+    let calendar_events = gigs.iter().map(|gig| {
+        CalEvent::new()
+            .summary(&gig.event.name)
+            .description(&gig.description())
+            .starts(gig.start())
+            .ends(gig.end())
+            .add_property("UID", &gig.id())
+            .append_property(Property::new("LOCATION", &gig.location()).done())
+            .done()
+    });
+
+    Calendar::from_iter(calendar_events).to_string()
+}
+
+/// An RSS 2.0 feed with an item for every public gig.
+pub fn to_rss(gigs: &Vec<PublicGig>) -> String {
+    let items = gigs
+        .iter()
+        .map(|gig| {
+            format!(
+                "<item><title>{}</title><link>{}</link><guid isPermaLink=\"false\">{}</guid>\
+                 <pubDate>{}</pubDate><description>{}</description></item>",
+                escape(&gig.event.name),
+                SITE_URL,
+                escape(&gig.id()),
+                gig.start().to_rfc2822(),
+                escape(&describe_for_feed(gig))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <rss version=\"2.0\"><channel><title>{} Performances</title><link>{}</link>\
+         <description>Upcoming and past performances of the {}</description>\
+         <lastBuildDate>{}</lastBuildDate>{}</channel></rss>",
+        CLUB_NAME,
+        SITE_URL,
+        CLUB_NAME,
+        Utc::now().to_rfc2822(),
+        items
+    )
+}
+
+/// An Atom feed with an entry for every public gig.
+pub fn to_atom(gigs: &Vec<PublicGig>) -> String {
+    let entries = gigs
+        .iter()
+        .map(|gig| {
+            format!(
+                "<entry><id>urn:uid:{}</id><title>{}</title><updated>{}</updated>\
+                 <link href=\"{}\"/><summary>{}</summary></entry>",
+                escape(&gig.id()),
+                escape(&gig.event.name),
+                gig.start().to_rfc3339(),
+                SITE_URL,
+                escape(&describe_for_feed(gig))
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\"><id>{}public_events.atom</id>\
+         <title>{} Performances</title><link href=\"{}\"/><updated>{}</updated>\
+         <author><name>{}</name></author>{}</feed>",
+        SITE_URL,
+        CLUB_NAME,
+        SITE_URL,
+        Utc::now().to_rfc3339(),
+        CLUB_NAME,
+        entries
+    )
+}
+
+/// schema.org `MusicEvent`s for every public gig, as JSON-LD.
+pub fn to_json_ld(gigs: &Vec<PublicGig>) -> Value {
+    let events = gigs
+        .iter()
+        .map(|gig| {
+            let mut event = json!({
+                "@context": "https://schema.org",
+                "@type": "MusicEvent",
+                "@id": format!("{}#{}", SITE_URL, gig.id()),
+                "name": &gig.event.name,
+                "description": gig.description(),
+                "startDate": gig.start().with_timezone(&Local).to_rfc3339(),
+                "endDate": gig.end().with_timezone(&Local).to_rfc3339(),
+                "eventStatus": "https://schema.org/EventScheduled",
+                "eventAttendanceMode": "https://schema.org/OfflineEventAttendanceMode",
+                "location": {
+                    "@type": "Place",
+                    "name": gig.location(),
+                    "address": gig.location(),
+                },
+                "performer": {
+                    "@type": "MusicGroup",
+                    "name": CLUB_NAME,
+                    "url": SITE_URL,
+                },
+                "organizer": {
+                    "@type": "Organization",
+                    "name": CLUB_NAME,
+                    "url": SITE_URL,
+                },
+            });
+            match gig.gig.price {
+                Some(price) if price > 0 => {
+                    event["offers"] = json!({
+                        "@type": "Offer",
+                        "price": price,
+                        "priceCurrency": "USD",
+                        "url": SITE_URL,
+                    });
+                }
+                _ => event["isAccessibleForFree"] = json!(true),
+            }
+
+            event
+        })
+        .collect::<Vec<_>>();
+
+    json!(events)
+}
+
+fn describe_for_feed(gig: &PublicGig) -> String {
+    let when = gig
+        .start()
+        .with_timezone(&Local)
+        .format("%A, %B %-d, %Y at %-I:%M %p");
+    let summary = gig.summary();
+
+    match (gig.location().as_str(), summary.as_str()) {
+        ("", "") => format!("{}.", when),
+        ("", summary) => format!("{}. {}", when, summary),
+        (location, "") => format!("{} at {}.", when, location),
+        (location, summary) => format!("{} at {}. {}", when, location, summary),
+    }
+}
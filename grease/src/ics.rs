@@ -0,0 +1,413 @@
+//! A small reader for iCalendar files, with just enough of
+//! [RFC 5545](https://tools.ietf.org/html/rfc5545) to import events.
+//!
+//! Times are read in the club's local (Eastern) time. UTC times (ending in
+//! `Z`) are converted to it, and times with a `TZID` are only accepted if it
+//! names Eastern time, since there's no time zone database to convert others with.
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use error::{GreaseError, GreaseResult};
+
+/// The `TZID`s that mean the club's local time, as written by common calendar apps.
+pub const LOCAL_TIME_ZONES: [&'static str; 5] = [
+    "America/New_York",
+    "US/Eastern",
+    "EST5EDT",
+    "Eastern Standard Time",
+    "(UTC-05:00) Eastern Time (US & Canada)",
+];
+
+/// An event read from an iCalendar file.
+#[derive(Clone, Debug)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub categories: Vec<String>,
+    pub rrule: Option<String>,
+    pub exdates: Vec<String>,
+}
+
+/// A single content line, like `DTSTART;TZID=America/New_York:20200310T183000`.
+struct ContentLine<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> ContentLine<'a> {
+    fn parse(line: &'a str) -> Option<ContentLine<'a>> {
+        // the value starts at the first colon that isn't in a quoted parameter
+        let mut in_quotes = false;
+        let colon = line.char_indices().find_map(|(index, c)| match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                None
+            }
+            ':' if !in_quotes => Some(index),
+            _ => None,
+        })?;
+        let (name_and_params, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = name_and_params.split(';');
+        let name = parts.next()?.trim().to_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let mut key_and_value = param.splitn(2, '=');
+                let key = key_and_value.next()?.trim().to_uppercase();
+                let value = key_and_value.next()?.trim_matches('"');
+                Some((key, value))
+            })
+            .collect();
+
+        Some(ContentLine {
+            name,
+            params,
+            value,
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param_key, _value)| param_key == key)
+            .map(|(_key, value)| *value)
+    }
+}
+
+/// Read every event out of an iCalendar file.
+///
+/// Cancelled events and changes to single occurrences of recurring events
+/// (those with a `RECURRENCE-ID`) are left out.
+pub fn parse_events(calendar: &str) -> GreaseResult<Vec<CalendarEvent>> {
+    let unfolded = calendar
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    if !unfolded
+        .trim_start()
+        .to_uppercase()
+        .starts_with("BEGIN:VCALENDAR")
+    {
+        return Err(GreaseError::BadRequest(
+            "The file given isn't an iCalendar file.".to_owned(),
+        ));
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Vec<ContentLine>> = None;
+    let mut nested_depth = 0;
+
+    for (line_number, line) in unfolded.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let content_line = ContentLine::parse(line).ok_or_else(|| {
+            GreaseError::BadRequest(format!(
+                "Line {} of the calendar couldn't be read: {}",
+                line_number + 1,
+                line
+            ))
+        })?;
+        let component = content_line.value.trim().to_uppercase();
+
+        match (content_line.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if component == "VEVENT" => current = Some(Vec::new()),
+            ("BEGIN", Some(_lines)) => nested_depth += 1,
+            ("END", Some(_lines)) if nested_depth > 0 => nested_depth -= 1,
+            ("END", Some(_lines)) if component == "VEVENT" => {
+                if let Some(lines) = current.take() {
+                    if let Some(event) = build_event(&lines)? {
+                        events.push(event);
+                    }
+                }
+            }
+            (_name, Some(lines)) if nested_depth == 0 => lines.push(content_line),
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+fn build_event(lines: &Vec<ContentLine>) -> GreaseResult<Option<CalendarEvent>> {
+    let find = |name: &str| lines.iter().find(|line| line.name == name);
+
+    let is_cancelled = find("STATUS")
+        .map(|status| status.value.trim().eq_ignore_ascii_case("CANCELLED"))
+        .unwrap_or(false);
+    if is_cancelled || find("RECURRENCE-ID").is_some() {
+        return Ok(None);
+    }
+
+    let summary = find("SUMMARY")
+        .map(|line| unescape(line.value))
+        .unwrap_or_else(|| "Untitled Event".to_owned());
+    let start_line = find("DTSTART").ok_or_else(|| {
+        GreaseError::BadRequest(format!("The event \"{}\" has no start time.", summary))
+    })?;
+    let (start, all_day) = parse_time(start_line)?;
+    let end = match (find("DTEND"), find("DURATION")) {
+        (Some(end_line), _) => Some(parse_time(end_line)?.0),
+        (None, Some(duration_line)) => Some(start + parse_duration(duration_line.value)?),
+        // events on a date without an end take up the whole day
+        (None, None) if all_day => Some(start + Duration::days(1)),
+        (None, None) => None,
+    }
+    .filter(|end| *end > start);
+    let uid = find("UID")
+        .map(|line| line.value.trim().to_owned())
+        .unwrap_or_else(|| format!("{}-{}", start.format("%Y%m%dT%H%M%S"), summary));
+
+    Ok(Some(CalendarEvent {
+        uid,
+        summary,
+        start,
+        end,
+        location: find("LOCATION")
+            .map(|line| unescape(line.value))
+            .filter(|location| !location.is_empty()),
+        description: find("DESCRIPTION")
+            .map(|line| unescape(line.value))
+            .filter(|description| !description.is_empty()),
+        categories: lines
+            .iter()
+            .filter(|line| line.name == "CATEGORIES")
+            .flat_map(|line| split_list(line.value))
+            .filter(|category| !category.is_empty())
+            .collect(),
+        rrule: find("RRULE").map(|line| line.value.trim().to_owned()),
+        exdates: lines
+            .iter()
+            .filter(|line| line.name == "EXDATE")
+            .flat_map(|line| line.value.split(','))
+            .map(|exdate| exdate.trim().to_owned())
+            .collect(),
+    }))
+}
+
+/// Parse a `DATE` or `DATE-TIME` property, returning whether it was a date.
+fn parse_time(line: &ContentLine) -> GreaseResult<(NaiveDateTime, bool)> {
+    let value = line.value.trim();
+    let invalid =
+        || GreaseError::BadRequest(format!("{} isn't a valid time for {}.", value, line.name));
+
+    if line.param("VALUE") == Some("DATE") || value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|date| (date.and_time(NaiveTime::from_hms(0, 0, 0)), true))
+            .map_err(|_err| invalid())
+    } else if value.ends_with('Z') {
+        NaiveDateTime::parse_from_str(&value[..value.len() - 1], "%Y%m%dT%H%M%S")
+            .map(|time| {
+                (
+                    Utc.from_utc_datetime(&time)
+                        .with_timezone(&Local)
+                        .naive_local(),
+                    false,
+                )
+            })
+            .map_err(|_err| invalid())
+    } else {
+        if let Some(time_zone) = line.param("TZID") {
+            // globally unique IDs start with a slash
+            let time_zone = time_zone.trim_start_matches('/');
+            if !LOCAL_TIME_ZONES
+                .iter()
+                .any(|local| local.eq_ignore_ascii_case(time_zone))
+            {
+                return Err(GreaseError::BadRequest(format!(
+                    "{} is in the time zone {}, but only Eastern or UTC times can be imported. \
+                     Export the calendar in Eastern time and try again.",
+                    line.name, time_zone
+                )));
+            }
+        }
+
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(|time| (time, false))
+            .map_err(|_err| invalid())
+    }
+}
+
+/// Parse a `DURATION` like `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> GreaseResult<Duration> {
+    let invalid = || GreaseError::BadRequest(format!("{} isn't a valid duration.", value));
+    let value = value.trim();
+    let (negative, value) = match value.chars().next() {
+        Some('-') => (true, &value[1..]),
+        Some('+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+    if !value.starts_with('P') {
+        return Err(invalid());
+    }
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in value[1..].chars() {
+        match c {
+            'T' => {}
+            '0'..='9' => number.push(c),
+            unit => {
+                let amount = number.parse::<i64>().map_err(|_err| invalid())?;
+                number.clear();
+                duration = duration
+                    + match unit {
+                        'W' => Duration::weeks(amount),
+                        'D' => Duration::days(amount),
+                        'H' => Duration::hours(amount),
+                        'M' => Duration::minutes(amount),
+                        'S' => Duration::seconds(amount),
+                        _ => return Err(invalid()),
+                    };
+            }
+        }
+    }
+
+    Ok(if negative { -duration } else { duration })
+}
+
+/// Split a comma-separated list of text values, leaving escaped commas alone.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match (escaped, c) {
+            (false, '\\') => {
+                escaped = true;
+                continue;
+            }
+            (false, ',') => items.push(String::new()),
+            (true, c) => {
+                if let Some(item) = items.last_mut() {
+                    item.push('\\');
+                    item.push(c);
+                }
+            }
+            (false, c) => {
+                if let Some(item) = items.last_mut() {
+                    item.push(c);
+                }
+            }
+        }
+        escaped = false;
+    }
+
+    items.iter().map(|item| unescape(item.trim())).collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => {}
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(event_lines: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n{}END:VEVENT\r\nEND:VCALENDAR\r\n",
+            event_lines
+        )
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2020, 3, 10).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn unfolds_long_lines() {
+        let events = parse_events(&calendar(
+            "UID:rehearsal-1\r\n\
+             SUMMARY:Full Chorus \r\n Rehearsal\r\n\
+             DESCRIPTION:Bring your\r\n\t music\r\n\
+             DTSTART:20200310T183000\r\n",
+        ))
+        .unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Full Chorus Rehearsal");
+        assert_eq!(events[0].description, Some("Bring your music".to_owned()));
+    }
+
+    #[test]
+    fn reads_quoted_parameters() {
+        let line = ContentLine::parse("LOCATION;ALTREP=\"http://example.com:80/map\":Ferst Center")
+            .unwrap();
+
+        assert_eq!(line.name, "LOCATION");
+        assert_eq!(line.param("ALTREP"), Some("http://example.com:80/map"));
+        assert_eq!(line.value, "Ferst Center");
+    }
+
+    #[test]
+    fn reads_eastern_and_floating_times() {
+        let events = parse_events(&calendar(
+            "UID:gig-1\r\n\
+             DTSTART;TZID=\"America/New_York\":20200310T183000\r\n\
+             DTEND;TZID=/US/Eastern:20200310T200000\r\n",
+        ))
+        .unwrap();
+
+        assert_eq!(events[0].start, time(18, 30));
+        assert_eq!(events[0].end, Some(time(20, 0)));
+    }
+
+    #[test]
+    fn rejects_other_time_zones() {
+        let error = parse_events(&calendar(
+            "UID:gig-1\r\nDTSTART;TZID=America/Los_Angeles:20200310T183000\r\n",
+        ))
+        .unwrap_err();
+
+        match error {
+            GreaseError::BadRequest(message) => assert!(message.contains("America/Los_Angeles")),
+            _ => panic!("expected a bad request"),
+        }
+    }
+
+    #[test]
+    fn all_day_events_last_the_day() {
+        let events =
+            parse_events(&calendar("UID:retreat\r\nDTSTART;VALUE=DATE:20200310\r\n")).unwrap();
+
+        assert_eq!(events[0].start, time(0, 0));
+        assert_eq!(events[0].end, Some(time(0, 0) + Duration::days(1)));
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("P1W").unwrap(), Duration::weeks(1));
+        assert_eq!(parse_duration("P1DT2H").unwrap(), Duration::hours(26));
+        assert_eq!(parse_duration("-PT15M").unwrap(), Duration::minutes(-15));
+        assert!(parse_duration("1H").is_err());
+        assert!(parse_duration("PT1X").is_err());
+        assert!(parse_duration("PTH").is_err());
+    }
+
+    #[test]
+    fn splits_lists_on_unescaped_commas() {
+        assert_eq!(
+            split_list("Gig, Volunteer\\, Outreach,Tutti"),
+            vec!["Gig", "Volunteer, Outreach", "Tutti"]
+        );
+        assert_eq!(split_list(""), vec![""]);
+    }
+}
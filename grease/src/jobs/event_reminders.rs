@@ -0,0 +1,315 @@
+//! Reminders about upcoming events.
+
+use crate::db::models::attendance::MemberAttendance;
+use crate::db::models::carpool::EventCarpool;
+use crate::db::schema::{AbsenceRequestState, NotificationCategory};
+use crate::db::{
+    models::event::EventWithGig, Attendance, Carpool, Event, Member, NotificationPreference,
+    Uniform, Variable,
+};
+use crate::email::template::{Template, TemplateContext};
+use crate::email::{Attachment, Email};
+use crate::error::{GreaseError, GreaseResult};
+use chrono::{Duration, NaiveDateTime};
+use diesel::{Connection, MysqlConnection};
+use std::collections::HashMap;
+
+/// The variable holding the club-wide reminder lead times, in hours.
+pub const LEAD_TIMES_VARIABLE: &'static str = "event_reminder_offsets";
+
+/// How long before responses to an event close that members who still
+/// need to confirm get an extra reminder, in hours.
+pub const CONFIRMATION_LEAD_TIME: i32 = 24;
+
+/// A reminder about a single event for a single member.
+struct Reminder<'a> {
+    member: &'a Member,
+    event: &'a EventWithGig,
+    lead_time: i32,
+    status: MemberAttendance,
+}
+
+impl<'a> Reminder<'a> {
+    /// Whether the member is expected at the event but hasn't confirmed
+    /// that they're coming, while they still can.
+    fn needs_confirmation(&self) -> bool {
+        self.status.should_attend() && !self.status.confirmed() && self.status.rsvp_issue.is_none()
+    }
+}
+
+/// Remind each member about events once they are within one of their lead
+/// times, as long as they haven't opted out of reminders.
+///
+/// Every member is reminded at their own chosen lead time, as well as at
+/// each of the club-wide lead times in the comma-separated
+/// `event_reminder_offsets` variable. Each reminder includes that
+/// member's own attendance, carpool, and absence request for the event.
+///
+/// Members who still need to confirm their attendance get an extra
+/// reminder a day before responses close, so that they still have time
+/// to answer. When that lines up with one of their other lead times (as
+/// it does with the default of 48 hours), the two are merged into a
+/// single reminder that asks them to confirm.
+pub fn send_emails(
+    all_events: &Vec<EventWithGig>,
+    since: &NaiveDateTime,
+    until: &NaiveDateTime,
+    conn: &MysqlConnection,
+) -> GreaseResult<usize> {
+    let recipients =
+        NotificationPreference::load_recipients(NotificationCategory::EventReminders, conn)?;
+    let club_lead_times = club_lead_times(conn)?;
+    let confirmation_lead_times = vec![confirmation_lead_time()];
+
+    let mut reminders = Vec::new();
+    for (member, preference) in &recipients {
+        let mut lead_times = club_lead_times.clone();
+        lead_times.push(preference.lead_time);
+
+        for event in all_events {
+            let lead_time = due_lead_time(event, since, until, &lead_times);
+            let confirmation_lead_time =
+                due_lead_time(event, since, until, &confirmation_lead_times);
+
+            // at most one reminder per event, so a confirmation reminder due
+            // at the same time as a regular one is merged into it
+            if let Some(due) = lead_time.or(confirmation_lead_time) {
+                let reminder = Reminder {
+                    member,
+                    event,
+                    lead_time: due,
+                    status: Attendance::load_for_member_at_event(
+                        member,
+                        true,
+                        event.event.id,
+                        conn,
+                    )?,
+                };
+                if lead_time.is_some() || reminder.needs_confirmation() {
+                    reminders.push(reminder);
+                }
+            }
+        }
+    }
+
+    // queue all of the reminders or none of them, so a failure partway
+    // through doesn't send some of them twice on the next run
+    conn.transaction(|| {
+        let mut carpools = HashMap::new();
+        for reminder in &reminders {
+            let event_id = reminder.event.event.id;
+            if !carpools.contains_key(&event_id) {
+                carpools.insert(event_id, Carpool::load_for_event(event_id, conn)?);
+            }
+
+            build_email(reminder, &carpools[&event_id], conn)?.queue(conn)?;
+        }
+
+        Ok(reminders.len())
+    })
+}
+
+/// The lead time of the extra reminder for members who need to confirm,
+/// counted from call time like the others.
+fn confirmation_lead_time() -> i32 {
+    Event::RSVP_CLOSES_HOURS_BEFORE as i32 + CONFIRMATION_LEAD_TIME
+}
+
+fn club_lead_times(conn: &MysqlConnection) -> GreaseResult<Vec<i32>> {
+    let lead_times = Variable::load(LEAD_TIMES_VARIABLE, conn)?
+        .map(|variable| variable.value)
+        .unwrap_or_default();
+
+    lead_times
+        .split(',')
+        .map(|lead_time| lead_time.trim())
+        .filter(|lead_time| !lead_time.is_empty())
+        .map(|lead_time| {
+            lead_time
+                .parse::<i32>()
+                .ok()
+                .filter(|hours| *hours >= 0)
+                .ok_or_else(|| {
+                    GreaseError::ServerError(format!(
+                        "The {} variable has an invalid lead time \"{}\" \
+                         (expected a whole number of hours).",
+                        LEAD_TIMES_VARIABLE, lead_time
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// The first of the lead times that the event came within between
+/// `since` and `until`, if any.
+fn due_lead_time(
+    event: &EventWithGig,
+    since: &NaiveDateTime,
+    until: &NaiveDateTime,
+    lead_times: &Vec<i32>,
+) -> Option<i32> {
+    lead_times.iter().cloned().find(|lead_time| {
+        let lead_time = Duration::hours(*lead_time as i64);
+
+        event.event.call_time <= *until + lead_time && event.event.call_time > *since + lead_time
+    })
+}
+
+fn build_email(
+    reminder: &Reminder,
+    carpools: &Vec<EventCarpool>,
+    conn: &MysqlConnection,
+) -> GreaseResult<Email> {
+    let mut context = template_context(reminder.event, conn)?;
+    add_member_context(&mut context, reminder, carpools);
+
+    let mut email = Email::new(
+        reminder.member.email.clone(),
+        email_subject(reminder),
+        Template::load(Template::EVENT_REMINDER_HTML)?.render(&context)?,
+    );
+    email.text = Some(Template::load(Template::EVENT_REMINDER_TEXT)?.render(&context)?);
+    email.attachments.push(Attachment {
+        file_name: format!("event-{}.ics", reminder.event.event.id),
+        content_type: "text/calendar; charset=utf-8; method=PUBLISH".to_owned(),
+        content: reminder.event.to_ics().into_bytes(),
+    });
+
+    Ok(email)
+}
+
+fn email_subject(reminder: &Reminder) -> String {
+    let name = &reminder.event.event.name;
+    let lead_time = reminder.lead_time;
+    let subject = if lead_time == 0 {
+        format!("{} is Starting Now", name)
+    } else if lead_time % 24 == 0 {
+        let days = lead_time / 24;
+        format!(
+            "{} is in {} Day{}",
+            name,
+            days,
+            if days == 1 { "" } else { "s" }
+        )
+    } else {
+        format!("{} is in {} Hours", name, lead_time)
+    };
+
+    if reminder.needs_confirmation() {
+        format!("Please Confirm: {}", subject)
+    } else {
+        subject
+    }
+}
+
+fn format_time(time: &NaiveDateTime) -> String {
+    time.format("%A, %B %-d at %-I:%M %p").to_string()
+}
+
+fn template_context(event: &EventWithGig, conn: &MysqlConnection) -> GreaseResult<TemplateContext> {
+    let uniform = if let Some(gig) = &event.gig {
+        Some(Uniform::load(gig.uniform, conn)?)
+    } else {
+        None
+    };
+
+    let mut context = TemplateContext::new();
+    context.insert(
+        "url",
+        format!(
+            "https://gleeclub.gatech.edu/glubhub/#/events/{}",
+            event.event.id
+        ),
+    );
+    context.insert("name", event.event.name.clone());
+    context.insert("type", event.event.type_.clone());
+    context.insert("callTime", format_time(&event.event.call_time));
+    context.insert(
+        "releaseTime",
+        event
+            .event
+            .release_time
+            .as_ref()
+            .map(format_time)
+            .unwrap_or_default(),
+    );
+    context.insert("location", event.event.location.clone().unwrap_or_default());
+    context.insert(
+        "uniform",
+        uniform.map(|uniform| uniform.name).unwrap_or_default(),
+    );
+    context.insert("comments", event.event.comments.clone().unwrap_or_default());
+
+    Ok(context)
+}
+
+/// Add the variables describing the member's own plans for the event.
+fn add_member_context(
+    context: &mut TemplateContext,
+    reminder: &Reminder,
+    carpools: &Vec<EventCarpool>,
+) {
+    let status = &reminder.status;
+    let flag = |is_set: bool| {
+        if is_set {
+            "yes".to_owned()
+        } else {
+            String::new()
+        }
+    };
+
+    let attendance = match (status.should_attend(), status.confirmed()) {
+        (true, true) => "You are expected to attend, and you have confirmed.",
+        (true, false) => "You are expected to attend, but you haven't confirmed yet.",
+        (false, true) => "You have said you are not coming.",
+        (false, false) => "You are not expected to attend.",
+    };
+    let rsvp_deadline = if status.rsvp_issue.is_none() {
+        format_time(&reminder.event.event.rsvp_deadline())
+    } else {
+        String::new()
+    };
+    let carpool = EventCarpool::by_member(carpools)
+        .get(reminder.member.email.as_str())
+        .map(|carpool| {
+            if carpool.driver.email == reminder.member.email {
+                let passengers = carpool
+                    .passengers
+                    .iter()
+                    .map(|passenger| passenger.full_name())
+                    .collect::<Vec<_>>();
+                if passengers.is_empty() {
+                    "You are driving, with no passengers.".to_owned()
+                } else {
+                    format!("You are driving {}.", passengers.join(", "))
+                }
+            } else {
+                format!("You are riding with {}.", carpool.driver.full_name())
+            }
+        })
+        .unwrap_or_default();
+    let absence_request = status
+        .absence_request
+        .as_ref()
+        .map(|request| match request.state {
+            AbsenceRequestState::Pending => "Your absence request is still pending.",
+            AbsenceRequestState::Approved => "Your absence request was approved.",
+            AbsenceRequestState::Denied => "Your absence request was denied.",
+        })
+        .unwrap_or_default();
+
+    context.insert(
+        "memberName",
+        reminder
+            .member
+            .preferred_name
+            .clone()
+            .unwrap_or_else(|| reminder.member.first_name.clone()),
+    );
+    context.insert("needsConfirmation", flag(reminder.needs_confirmation()));
+    context.insert("attendance", attendance.to_owned());
+    context.insert("rsvpDeadline", rsvp_deadline);
+    context.insert("rsvpIssue", status.rsvp_issue.clone().unwrap_or_default());
+    context.insert("carpool", carpool);
+    context.insert("absenceRequest", absence_request.to_owned());
+}
@@ -0,0 +1,355 @@
+//! Scheduled background jobs.
+//!
+//! When the API is run outside of CGI (by cron, every few minutes), it acts
+//! as a job runner instead:
+//!   * `grease`: Run every job that is due according to its schedule.
+//!   * `grease jobs`: List every job, its schedule, and when it last succeeded.
+//!   * `grease run <job>`: Run a job right away, whether or not it is due.
+//!   * `grease history <job>`: Show the most recent runs of a job.
+//!
+//! Each job remembers when it last succeeded, so if a run is missed or fails,
+//! the next run picks up where the last successful one left off. Jobs are
+//! locked while they run so that overlapping invocations never run the same
+//! job twice at once, and every run is recorded in the job's history.
+
+pub mod event_reminders;
+pub mod officer_digest;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use db::schema::JobRunStatus;
+use db::{connect_to_db, Announcement, Event, JobRun, JobState, OutboxEmail, WebhookDelivery};
+use diesel::MysqlConnection;
+use email::transport;
+use error::*;
+use webhook::HttpClient;
+
+/// When a job should run.
+pub enum Schedule {
+    /// Run once this many minutes have passed since the last success.
+    EveryMinutes(i64),
+    /// Run once a day, as soon as possible after the given hour.
+    DailyAt(u32),
+}
+
+impl Schedule {
+    pub fn is_due(&self, last_success: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+        match (self, last_success) {
+            (_, None) => true,
+            (Schedule::EveryMinutes(minutes), Some(last_success)) => {
+                now - last_success >= Duration::minutes(*minutes)
+            }
+            (Schedule::DailyAt(hour), Some(last_success)) => {
+                let todays_run = now.date().and_hms(*hour, 0, 0);
+                now >= todays_run && last_success < todays_run
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Schedule::EveryMinutes(1) => "every minute".to_owned(),
+            Schedule::EveryMinutes(minutes) => format!("every {} minutes", minutes),
+            Schedule::DailyAt(hour) => format!("daily at {}:00", hour),
+        }
+    }
+}
+
+/// What a job is given to work with when it runs.
+pub struct JobContext<'c> {
+    pub conn: &'c MysqlConnection,
+    /// When this run started
+    pub started: NaiveDateTime,
+    /// When the last successful run started, if there was one
+    pub last_success: Option<NaiveDateTime>,
+}
+
+pub struct Job {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub schedule: Schedule,
+    /// Do the job, returning a short summary of what was done
+    pub run: fn(&JobContext) -> GreaseResult<String>,
+}
+
+/// Every job, in the order they are run.
+pub static JOBS: &[Job] = &[
+    Job {
+        name: "event-emails",
+        description: "Queue reminders about upcoming events",
+        schedule: Schedule::EveryMinutes(5),
+        run: send_event_emails,
+    },
+    Job {
+        name: "publish-announcements",
+        description: "Email and post announcements to webhooks once they are published",
+        schedule: Schedule::EveryMinutes(5),
+        run: email_published_announcements,
+    },
+    Job {
+        name: "officer-digest",
+        description: "Email officers a summary of pending absence and gig requests",
+        schedule: Schedule::DailyAt(8),
+        run: send_officer_digests,
+    },
+    Job {
+        name: "deliver-emails",
+        description: "Send queued emails from the outbox",
+        schedule: Schedule::EveryMinutes(1),
+        run: deliver_queued_emails,
+    },
+    Job {
+        name: "deliver-webhooks",
+        description: "Send queued webhook deliveries",
+        schedule: Schedule::EveryMinutes(1),
+        run: deliver_queued_webhooks,
+    },
+];
+
+/// How long the history of job runs is kept.
+pub const HISTORY_DAYS: i64 = 90;
+
+/// How far back a run of the event email job will look for reminders it
+/// missed, so that a long outage doesn't end in a flood of stale reminders.
+pub const MAX_REMINDER_CATCH_UP_HOURS: i64 = 24;
+
+pub enum JobOutcome {
+    NotDue,
+    AlreadyRunning,
+    Finished(GreaseResult<String>),
+}
+
+pub fn find_job(name: &str) -> Option<&'static Job> {
+    JOBS.iter().find(|job| job.name == name)
+}
+
+/// Run a job if it is due (or regardless, if `force` is set) and nobody else
+/// is running it.
+pub fn run_job(job: &Job, force: bool, conn: &MysqlConnection) -> GreaseResult<JobOutcome> {
+    let started = Local::now().naive_local();
+    let state = JobState::load(job.name, conn)?;
+    if !force && !job.schedule.is_due(state.last_success, started) {
+        return Ok(JobOutcome::NotDue);
+    }
+
+    let token = match JobState::lock(job.name, started, conn)? {
+        Some(token) => token,
+        None => return Ok(JobOutcome::AlreadyRunning),
+    };
+    let result = JobRun::start(job.name, started, conn).and_then(|run_id| {
+        let result = (job.run)(&JobContext {
+            conn,
+            started,
+            last_success: state.last_success,
+        });
+        JobRun::finish(run_id, Local::now().naive_local(), &result, conn)?;
+
+        Ok(result)
+    });
+    let succeeded_run_start = match &result {
+        Ok(Ok(_output)) => Some(started),
+        _ => None,
+    };
+    JobState::unlock(job.name, &token, succeeded_run_start, conn)?;
+
+    result.map(JobOutcome::Finished)
+}
+
+/// Run the job runner with the given command line arguments (not including
+/// the name of the binary), returning the exit code.
+pub fn run_from_command_line(args: Vec<String>) -> i32 {
+    let conn = match connect_to_db() {
+        Ok(conn) => conn,
+        Err(error) => {
+            eprintln!("{}", error.as_response().1);
+            return 1;
+        }
+    };
+    let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+
+    let result = match args.as_slice() {
+        [] => run_due_jobs(&conn),
+        ["jobs"] => list_jobs(&conn),
+        ["run", name] => run_job_now(name, &conn),
+        ["history", name] => show_history(name, &conn),
+        _ => {
+            eprintln!("usage: grease [jobs | run <job> | history <job>]");
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => {
+            eprintln!("{}", error.as_response().1);
+            1
+        }
+    }
+}
+
+fn run_due_jobs(conn: &MysqlConnection) -> GreaseResult<bool> {
+    let mut all_succeeded = true;
+    for job in JOBS {
+        // one job failing to run shouldn't keep the others from running
+        match run_job(job, false, conn) {
+            Ok(JobOutcome::Finished(Ok(output))) => println!("{}: {}", job.name, output),
+            Ok(JobOutcome::Finished(Err(error))) | Err(error) => {
+                eprintln!("{} failed: {}", job.name, error.as_response().1);
+                all_succeeded = false;
+            }
+            Ok(JobOutcome::NotDue) | Ok(JobOutcome::AlreadyRunning) => {}
+        }
+    }
+
+    JobRun::prune(
+        Local::now().naive_local() - Duration::days(HISTORY_DAYS),
+        conn,
+    )?;
+
+    Ok(all_succeeded)
+}
+
+fn list_jobs(conn: &MysqlConnection) -> GreaseResult<bool> {
+    let now = Local::now().naive_local();
+    for job in JOBS {
+        let state = JobState::load(job.name, conn)?;
+        let status = if state.locked_until.map(|until| until > now).unwrap_or(false) {
+            "running"
+        } else if job.schedule.is_due(state.last_success, now) {
+            "due"
+        } else {
+            "waiting"
+        };
+
+        println!(
+            "{:<22} {:<18} {:<8} last succeeded {}\n    {}",
+            job.name,
+            job.schedule.describe(),
+            status,
+            state
+                .last_success
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "never".to_owned()),
+            job.description
+        );
+    }
+
+    Ok(true)
+}
+
+fn run_job_now(name: &str, conn: &MysqlConnection) -> GreaseResult<bool> {
+    let job = find_job(name).ok_or_else(|| unknown_job(name))?;
+
+    match run_job(job, true, conn)? {
+        JobOutcome::Finished(Ok(output)) => {
+            println!("{}", output);
+            Ok(true)
+        }
+        JobOutcome::Finished(Err(error)) => {
+            eprintln!("{} failed: {}", job.name, error.as_response().1);
+            Ok(false)
+        }
+        JobOutcome::AlreadyRunning => {
+            eprintln!("{} is already running.", job.name);
+            Ok(false)
+        }
+        JobOutcome::NotDue => Ok(true),
+    }
+}
+
+fn show_history(name: &str, conn: &MysqlConnection) -> GreaseResult<bool> {
+    let job = find_job(name).ok_or_else(|| unknown_job(name))?;
+
+    for run in JobRun::load_recent(job.name, 20, conn)? {
+        let status = match run.status {
+            JobRunStatus::Running => "running",
+            JobRunStatus::Succeeded => "succeeded",
+            JobRunStatus::Failed => "failed",
+        };
+        let duration = run
+            .finished
+            .map(|finished| format!("{}s", (finished - run.started).num_seconds()))
+            .unwrap_or_default();
+
+        println!(
+            "{} {:<9} {:>6} {}",
+            run.started.format("%Y-%m-%d %H:%M:%S"),
+            status,
+            duration,
+            run.output.unwrap_or_default()
+        );
+    }
+
+    Ok(true)
+}
+
+fn unknown_job(name: &str) -> GreaseError {
+    GreaseError::BadRequest(format!(
+        "No job named \"{}\" (expected one of {}).",
+        name,
+        JOBS.iter()
+            .map(|job| job.name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// Queue reminders for every event that came within a member's lead time
+/// since the last successful run.
+fn send_event_emails(context: &JobContext) -> GreaseResult<String> {
+    let earliest = context.started - Duration::hours(MAX_REMINDER_CATCH_UP_HOURS);
+    let since = context
+        .last_success
+        .unwrap_or(context.started - Duration::hours(1))
+        .max(earliest);
+    let queued = send_event_emails_between(&since, &context.started, context.conn)?;
+
+    Ok(format!("Queued {} event reminders.", queued))
+}
+
+/// Queue reminders for every event that came within a member's lead time
+/// between the two given times, returning how many were queued.
+pub fn send_event_emails_between(
+    since: &NaiveDateTime,
+    until: &NaiveDateTime,
+    conn: &MysqlConnection,
+) -> GreaseResult<usize> {
+    let all_events = Event::load_all_for_current_semester(conn)?;
+
+    event_reminders::send_emails(&all_events, since, until, conn)
+}
+
+fn email_published_announcements(context: &JobContext) -> GreaseResult<String> {
+    let queued = Announcement::email_published(context.conn)?;
+    let posted = Announcement::post_published(context.conn)?;
+
+    Ok(format!(
+        "Queued {} announcement emails and posted {} announcements to webhooks.",
+        queued, posted
+    ))
+}
+
+fn send_officer_digests(context: &JobContext) -> GreaseResult<String> {
+    let queued = officer_digest::send_digests(&context.started, context.conn)?;
+
+    Ok(format!("Queued {} officer digests.", queued))
+}
+
+fn deliver_queued_emails(context: &JobContext) -> GreaseResult<String> {
+    let transport = transport::from_env()?;
+    let report = OutboxEmail::deliver_pending(transport.as_ref(), context.conn)?;
+
+    Ok(format!(
+        "Sent {} emails, {} will be retried, and {} failed for good.",
+        report.sent, report.retrying, report.failed
+    ))
+}
+
+fn deliver_queued_webhooks(context: &JobContext) -> GreaseResult<String> {
+    let report = WebhookDelivery::deliver_pending(&HttpClient::new(), context.conn)?;
+
+    Ok(format!(
+        "Delivered {} webhooks, {} will be retried, and {} failed for good.",
+        report.sent, report.retrying, report.failed
+    ))
+}
@@ -0,0 +1,162 @@
+//! A daily summary of the requests waiting on officers.
+
+use crate::db::schema::{
+    member_role, role_permission, AbsenceRequestState, GigRequestStatus, NotificationCategory,
+};
+use crate::db::{AbsenceRequest, Event, GigRequest, Member, NotificationPreference};
+use crate::email::{template::escape, Email};
+use crate::error::{GreaseError, GreaseResult};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+pub const ABSENCE_REQUEST_PERMISSION: &'static str = "process-absence-requests";
+pub const GIG_REQUEST_PERMISSION: &'static str = "process-gig-requests";
+
+/// Email every officer who can process absence or gig requests a summary of
+/// the ones still pending, returning how many digests were queued.
+///
+/// Officers only hear about the kinds of requests they can process, and
+/// nobody is emailed if nothing is pending.
+pub fn send_digests(now: &NaiveDateTime, conn: &MysqlConnection) -> GreaseResult<usize> {
+    let absence_requests = AbsenceRequest::load_all_for_this_semester(conn)?
+        .into_iter()
+        .filter(|(request, _event)| request.state == AbsenceRequestState::Pending)
+        .collect::<Vec<_>>();
+    let gig_requests = GigRequest::load_all_for_semester_and_pending(conn)?
+        .into_iter()
+        .filter(|request| request.status == GigRequestStatus::Pending)
+        .collect::<Vec<_>>();
+    if absence_requests.is_empty() && gig_requests.is_empty() {
+        return Ok(0);
+    }
+
+    let absence_section = absence_request_section(&absence_requests, now, conn)?;
+    let gig_section = gig_request_section(&gig_requests, now);
+
+    let mut queued = 0;
+    for (officer, permissions) in load_officers(conn)? {
+        let mut sections = Vec::new();
+        if permissions.contains(&ABSENCE_REQUEST_PERMISSION) && !absence_requests.is_empty() {
+            sections.push(absence_section.as_str());
+        }
+        if permissions.contains(&GIG_REQUEST_PERMISSION) && !gig_requests.is_empty() {
+            sections.push(gig_section.as_str());
+        }
+        if sections.is_empty() {
+            continue;
+        }
+
+        let email = Email::new(
+            officer.clone(),
+            "Pending Requests for the Glee Club".to_owned(),
+            sections.join(""),
+        );
+        if NotificationPreference::notify(
+            &officer,
+            NotificationCategory::OfficerDigests,
+            email,
+            conn,
+        )? {
+            queued += 1;
+        }
+    }
+
+    Ok(queued)
+}
+
+/// Load the emails of everyone who can process requests, along with which
+/// of the permissions they have.
+fn load_officers(conn: &MysqlConnection) -> GreaseResult<HashMap<String, Vec<&'static str>>> {
+    let grants = member_role::table
+        .inner_join(role_permission::table.on(member_role::role.eq(role_permission::role)))
+        .filter(
+            role_permission::permission
+                .eq_any(vec![ABSENCE_REQUEST_PERMISSION, GIG_REQUEST_PERMISSION])
+                .and(role_permission::event_type.is_null()),
+        )
+        .select((member_role::member, role_permission::permission))
+        .distinct()
+        .load::<(String, String)>(conn)
+        .map_err(GreaseError::DbError)?;
+
+    let mut officers = HashMap::new();
+    for (officer, permission) in grants {
+        let permission = if permission == ABSENCE_REQUEST_PERMISSION {
+            ABSENCE_REQUEST_PERMISSION
+        } else {
+            GIG_REQUEST_PERMISSION
+        };
+        officers
+            .entry(officer)
+            .or_insert_with(Vec::new)
+            .push(permission);
+    }
+
+    Ok(officers)
+}
+
+fn absence_request_section(
+    requests: &Vec<(AbsenceRequest, Event)>,
+    now: &NaiveDateTime,
+    conn: &MysqlConnection,
+) -> GreaseResult<String> {
+    let mut rows = String::new();
+    for (request, event) in requests {
+        let requester = Member::load(&request.member, conn)?;
+        rows.push_str(&format!(
+            "<li><b>{}</b> for {} ({}), waiting {}<br><i>{}</i></li>",
+            escape(&requester.full_name()),
+            escape(&event.name),
+            event.call_time.format("%A, %B %-d"),
+            waiting_time(&request.time, now),
+            escape(&request.reason)
+        ));
+    }
+
+    Ok(format!(
+        "<h3>{} Pending Absence Request{}</h3><ul>{}</ul>\
+         <p><a href=\"https://gleeclub.gatech.edu/glubhub/\">Process them on the site</a></p>",
+        requests.len(),
+        if requests.len() == 1 { "" } else { "s" },
+        rows
+    ))
+}
+
+fn gig_request_section(requests: &Vec<GigRequest>, now: &NaiveDateTime) -> String {
+    let rows = requests
+        .iter()
+        .map(|request| {
+            format!(
+                "<li><b>{}</b> from {} on {}, waiting {}</li>",
+                escape(&request.name),
+                escape(&request.organization),
+                request.start_time.format("%A, %B %-d"),
+                waiting_time(&request.time, now)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    format!(
+        "<h3>{} Pending Gig Request{}</h3><ul>{}</ul>\
+         <p><a href=\"https://gleeclub.gatech.edu/glubhub/\">Process them on the site</a></p>",
+        requests.len(),
+        if requests.len() == 1 { "" } else { "s" },
+        rows
+    )
+}
+
+/// Describe how long it's been since a request was made, roughly.
+fn waiting_time(requested: &NaiveDateTime, now: &NaiveDateTime) -> String {
+    let waiting = *now - *requested;
+    let (amount, unit) = if waiting.num_days() > 0 {
+        (waiting.num_days(), "day")
+    } else if waiting.num_hours() > 0 {
+        (waiting.num_hours(), "hour")
+    } else {
+        return "less than an hour".to_owned();
+    };
+
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
@@ -47,6 +47,7 @@
 //!   Method   | Route                           | Handler
 //! -----------|---------------------------------|-------------------------------------------------------------------------------------------------------
 //! **GET**    | /user                           | [get_current_user](crate::routes::member_routes::get_current_user)
+//! **POST**   | /user/grades/simulate           | [simulate_grades](crate::routes::member_routes::simulate_grades)
 //! **GET**    | /members/{*email*}              | [get_member](crate::routes::member_routes::get_member)
 //! **GET**    | /members/{*email*}/attendance   | [get_member_attendance_for_semester](crate::routes::event_routes::get_member_attendance_for_semester)
 //! **GET**    | /members                        | [get_members](crate::routes::member_routes::get_members)
@@ -56,34 +57,72 @@
 //! **DELETE** | /members/{*email*}/{*semester*} | [mark_member_inactive_for_semester](crate::routes::member_routes::mark_member_inactive_for_semester)
 //! **POST**   | /members                        | [new_member](crate::routes::member_routes::new_member)
 //! **POST**   | /members/profile                | [update_member_profile](crate::routes::member_routes::update_member_profile)
+//! **GET**    | /notification_preferences       | [get_notification_preferences](crate::routes::member_routes::get_notification_preferences)
+//! **POST**   | /notification_preferences       | [update_notification_preferences](crate::routes::member_routes::update_notification_preferences)
+//! **GET**    | /calendar_feed                  | [get_calendar_feed](crate::routes::member_routes::get_calendar_feed)
+//! **POST**   | /calendar_feed                  | [regenerate_calendar_feed](crate::routes::member_routes::regenerate_calendar_feed)
+//! **DELETE** | /calendar_feed                  | [revoke_calendar_feed](crate::routes::member_routes::revoke_calendar_feed)
+//! **GET**    | /calendar_feeds/{*token*}       | [get_calendar_feed_file](crate::routes::member_routes::get_calendar_feed_file)
 //! **POST**   | /members/{*email*}              | [update_member_as_officer](crate::routes::member_routes::update_member_as_officer)
 //! **POST**   | /members/{*email*}/login_as     | [login_as_member](crate::routes::member_routes::login_as_member)
 //! **DELETE** | /members/{*email*}              | [delete_member](crate::routes::member_routes::delete_member)
 //!
 //! ### Events:
 //!
-//!   Method   | Route          | Handler
-//! -----------|----------------|----------------------------------------------------------
-//! **GET**    | /events/{*id*} | [get_event](crate::routes::event_routes::get_event)
-//! **GET**    | /events        | [get_events](crate::routes::event_routes::get_events)
-//! **POST**   | /events        | [new_event](crate::routes::event_routes::new_event)
-//! **POST**   | /events/{*id*} | [update_event](crate::routes::event_routes::update_event)
-//! **DELETE** | /events/{*id*} | [delete_event](crate::routes::event_routes::delete_event)
+//!   Method   | Route             | Handler
+//! -----------|-------------------|----------------------------------------------------------
+//! **GET**    | /events/{*id*}    | [get_event](crate::routes::event_routes::get_event)
+//! **GET**    | /events           | [get_events](crate::routes::event_routes::get_events)
+//! **GET**    | /events/conflicts | [get_event_conflicts](crate::routes::event_routes::get_event_conflicts)
+//! **POST**   | /events           | [new_event](crate::routes::event_routes::new_event)
+//! **POST**   | /events/import    | [import_events](crate::routes::event_routes::import_events)
+//! **POST**   | /events/{*id*}    | [update_event](crate::routes::event_routes::update_event)
+//! **DELETE** | /events/{*id*}    | [delete_event](crate::routes::event_routes::delete_event)
+//!
+//! ### Public Events:
+//!
+//! These routes don't require logging in.
+//!
+//!   Method   | Route                 | Handler
+//! -----------|-----------------------|----------------------------------------------------------------------
+//! **GET**    | /public_events        | [get_public_events](crate::routes::event_routes::get_public_events)
+//! **GET**    | /public_events.ics    | [get_public_events_feed](crate::routes::event_routes::get_public_events_feed)
+//! **GET**    | /public_events.rss    | [get_public_events_feed](crate::routes::event_routes::get_public_events_feed)
+//! **GET**    | /public_events.atom   | [get_public_events_feed](crate::routes::event_routes::get_public_events_feed)
+//! **GET**    | /public_events.jsonld | [get_public_events_feed](crate::routes::event_routes::get_public_events_feed)
 //!
 //! ### Event Details:
 //!
-//!   Method   | Route                                        | Handler
-//! -----------|----------------------------------------------|------------------------------------------------------------------------------------------
-//! **GET**    | /events/{*id*}/attendance                    | [get_attendance](crate::routes::event_routes::get_attendance)
-//! **GET**    | /events/{*id*}/see_whos_attending            | [see_whos_attending](crate::routes::event_routes::see_whos_attending)
-//! **GET**    | /events/{*id*}/attendance/{*member*}         | [get_member_attendance](crate::routes::event_routes::get_member_attendance)
-//! **POST**   | /events/{*id*}/attendance/{*member*}         | [update_attendance](crate::routes::event_routes::update_attendance)
-//! **POST**   | /events/{*id*}/rsvp/{*attending*}            | [rsvp_for_event](crate::routes::event_routes::rsvp_for_event)
-//! **POST**   | /events/{*id*}/attendance/excuse_unconfirmed | [excuse_unconfirmed_for_event](crate::routes::event_routes::excuse_unconfirmed_for_event)
-//! **GET**    | /events/{*id*}/carpools                      | [get_carpools](crate::routes::event_routes::get_carpools)
-//! **POST**   | /events/{*id*}/carpools                      | [update_carpools](crate::routes::event_routes::update_carpools)
-//! **GET**    | /events/{*id*}/setlist                       | [get_setlist](crate::routes::event_routes::get_setlist)
-//! **POST**   | /events/{*id*}/setlist                       | [edit_setlist](crate::routes::event_routes::edit_setlist)
+//!   Method   | Route                                               | Handler
+//! -----------|-----------------------------------------------------|--------------------------------------------------------------------------------------------
+//! **GET**    | /events/{*id*}/attendance                           | [get_attendance](crate::routes::event_routes::get_attendance)
+//! **GET**    | /events/{*id*}/see_whos_attending                   | [see_whos_attending](crate::routes::event_routes::see_whos_attending)
+//! **GET**    | /events/{*id*}/attendance/{*member*}                | [get_member_attendance](crate::routes::event_routes::get_member_attendance)
+//! **GET**    | /events/{*id*}/attendance/{*member*}/grade_disputes | [get_attendance_grade_disputes](crate::routes::event_routes::get_attendance_grade_disputes)
+//! **POST**   | /events/{*id*}/attendance/{*member*}                | [update_attendance](crate::routes::event_routes::update_attendance)
+//! **POST**   | /events/{*id*}/grade_disputes                       | [submit_grade_dispute](crate::routes::event_routes::submit_grade_dispute)
+//! **POST**   | /events/{*id*}/attendance                           | [update_attendance_in_bulk](crate::routes::event_routes::update_attendance_in_bulk)
+//! **POST**   | /events/{*id*}/attendance/import                    | [import_attendance](crate::routes::event_routes::import_attendance)
+//! **POST**   | /events/{*id*}/rsvp/{*attending*}                   | [rsvp_for_event](crate::routes::event_routes::rsvp_for_event)
+//! **POST**   | /events/{*id*}/attendance/excuse_unconfirmed        | [excuse_unconfirmed_for_event](crate::routes::event_routes::excuse_unconfirmed_for_event)
+//! **POST**   | /events/{*id*}/check_in_window                      | [open_check_in](crate::routes::event_routes::open_check_in)
+//! **GET**    | /events/{*id*}/check_in_window                      | [get_check_in_code](crate::routes::event_routes::get_check_in_code)
+//! **DELETE** | /events/{*id*}/check_in_window                      | [close_check_in](crate::routes::event_routes::close_check_in)
+//! **POST**   | /events/{*id*}/check_in                             | [check_in_to_event](crate::routes::event_routes::check_in_to_event)
+//! **GET**    | /events/{*id*}/not_checked_in                       | [get_not_checked_in](crate::routes::event_routes::get_not_checked_in)
+//! **GET**    | /events/{*id*}/carpools                             | [get_carpools](crate::routes::event_routes::get_carpools)
+//! **POST**   | /events/{*id*}/carpools                             | [update_carpools](crate::routes::event_routes::update_carpools)
+//! **GET**    | /events/{*id*}/setlist                              | [get_setlist](crate::routes::event_routes::get_setlist)
+//! **POST**   | /events/{*id*}/setlist                              | [edit_setlist](crate::routes::event_routes::edit_setlist)
+//!
+//! ### Exports:
+//!
+//!   Method   | Route                                   | Handler
+//! -----------|-----------------------------------------|--------------------------------------------------------------------------------------
+//! **GET**    | /events/{*id*}/attendance/export        | [export_event_attendance](crate::routes::event_routes::export_event_attendance)
+//! **GET**    | /attendance/export                      | [export_semester_attendance](crate::routes::event_routes::export_semester_attendance)
+//! **GET**    | /grades/export                          | [export_grades](crate::routes::event_routes::export_grades)
+//! **GET**    | /semesters/{*name*}/final_grades/export | [export_final_grades](crate::routes::event_routes::export_final_grades)
 //!
 //! ### Absence Requests:
 //!
@@ -96,6 +135,16 @@
 //! **POST**   | /absence_requests/{*eventId*}/{*member*}/deny    | [deny_absence_request](crate::routes::event_routes::deny_absence_request)
 //! **POST**   | /absence_requests/{*eventId*}                    | [submit_absence_request](crate::routes::event_routes::submit_absence_request)
 //!
+//! ### Grade Disputes:
+//!
+//!   Method   | Route                          | Handler
+//! -----------|--------------------------------|----------------------------------------------------------------------------------
+//! **GET**    | /grade_disputes                | [get_grade_disputes](crate::routes::event_routes::get_grade_disputes)
+//! **GET**    | /grade_disputes/{*id*}         | [get_grade_dispute](crate::routes::event_routes::get_grade_dispute)
+//! **POST**   | /grade_disputes/{*id*}/comment | [comment_on_grade_dispute](crate::routes::event_routes::comment_on_grade_dispute)
+//! **POST**   | /grade_disputes/{*id*}/resolve | [resolve_grade_dispute](crate::routes::event_routes::resolve_grade_dispute)
+//! **POST**   | /grade_disputes/{*id*}/reject  | [reject_grade_dispute](crate::routes::event_routes::reject_grade_dispute)
+//!
 //! ### Gig Requests:
 //!
 //!   Method   | Route                             | Handler
@@ -117,12 +166,13 @@
 //!
 //! ### Announcements:
 //!
-//!   Method   | Route                 | Handler
-//! -----------|-----------------------|------------------------------------------------------------------------------
-//! **GET**    | /announcements/{*id*} | [get_announcement](crate::routes::officer_routes::get_announcement)
-//! **GET**    | /announcements        | [get_announcements](crate::routes::officer_routes::get_announcements)
-//! **POST**   | /announcements        | [make_new_announcement](crate::routes::officer_routes::make_new_announcement)
-//! **POST**   | /announcements/{*id*} | [archive_announcement](crate::routes::officer_routes::archive_announcement)
+//!   Method   | Route                      | Handler
+//! -----------|----------------------------|------------------------------------------------------------------------------
+//! **GET**    | /announcements/{*id*}      | [get_announcement](crate::routes::officer_routes::get_announcement)
+//! **GET**    | /announcements             | [get_announcements](crate::routes::officer_routes::get_announcements)
+//! **POST**   | /announcements             | [make_new_announcement](crate::routes::officer_routes::make_new_announcement)
+//! **POST**   | /announcements/{*id*}      | [archive_announcement](crate::routes::officer_routes::archive_announcement)
+//! **POST**   | /announcements/{*id*}/read | [mark_announcement_read](crate::routes::officer_routes::mark_announcement_read)
 //!
 //! ### Google Docs:
 //!
@@ -145,6 +195,24 @@
 //! **GET**    | /meeting_minutes/{*id*}/email | [send_minutes_as_email](crate::routes::officer_routes::send_minutes_as_email)
 //! **DELETE** | /meeting_minutes/{*id*}       | [delete_meeting_minutes](crate::routes::officer_routes::delete_meeting_minutes)
 //!
+//! ### Email Outbox:
+//!
+//!   Method   | Route                      | Handler
+//! -----------|----------------------------|------------------------------------------------------------------------
+//! **GET**    | /email_outbox              | [get_email_outbox](crate::routes::officer_routes::get_email_outbox)
+//! **POST**   | /email_outbox/{*id*}/retry | [retry_queued_email](crate::routes::officer_routes::retry_queued_email)
+//!
+//! ### Webhooks:
+//!
+//!   Method   | Route                            | Handler
+//! -----------|----------------------------------|--------------------------------------------------------------------------------
+//! **GET**    | /webhooks                        | [get_webhooks](crate::routes::officer_routes::get_webhooks)
+//! **POST**   | /webhooks                        | [new_webhook](crate::routes::officer_routes::new_webhook)
+//! **POST**   | /webhooks/{*id*}                 | [update_webhook](crate::routes::officer_routes::update_webhook)
+//! **DELETE** | /webhooks/{*id*}                 | [delete_webhook](crate::routes::officer_routes::delete_webhook)
+//! **GET**    | /webhooks/{*id*}/deliveries      | [get_webhook_deliveries](crate::routes::officer_routes::get_webhook_deliveries)
+//! **POST**   | /webhook_deliveries/{*id*}/retry | [retry_webhook_delivery](crate::routes::officer_routes::retry_webhook_delivery)
+//!
 //! ### Uniforms:
 //!
 //!   Method   | Route            | Handler
@@ -188,15 +256,21 @@
 //!
 //! ### Semesters:
 //!
-//!   Method   | Route                           | Handler
-//! -----------|---------------------------------|----------------------------------------------------------------------------
-//! **GET**    | /semesters                      | [get_semesters](crate::routes::officer_routes::get_semesters)
-//! **GET**    | /semester/current               | [get_current_semester](crate::routes::officer_routes::get_current_semester)
-//! **GET**    | /semesters/{*name*}             | [get_semester](crate::routes::officer_routes::get_semester)
-//! **POST**   | /semesters                      | [new_semester](crate::routes::officer_routes::new_semester)
-//! **POST**   | /semesters/{*name*}             | [edit_semester](crate::routes::officer_routes::edit_semester)
-//! **POST**   | /semesters/{*name*}/set_current | [set_current_semester](crate::routes::officer_routes::set_current_semester)
-//! **DELETE** | /semesters/{*name*}             | [delete_semester](crate::routes::officer_routes::delete_semester)
+//!   Method   | Route                              | Handler
+//! -----------|------------------------------------|----------------------------------------------------------------------------
+//! **GET**    | /semesters                         | [get_semesters](crate::routes::officer_routes::get_semesters)
+//! **GET**    | /semester/current                  | [get_current_semester](crate::routes::officer_routes::get_current_semester)
+//! **GET**    | /semesters/{*name*}                | [get_semester](crate::routes::officer_routes::get_semester)
+//! **POST**   | /semesters                         | [new_semester](crate::routes::officer_routes::new_semester)
+//! **POST**   | /semesters/{*name*}                | [edit_semester](crate::routes::officer_routes::edit_semester)
+//! **POST**   | /semesters/{*name*}/set_current    | [set_current_semester](crate::routes::officer_routes::set_current_semester)
+//! **DELETE** | /semesters/{*name*}                | [delete_semester](crate::routes::officer_routes::delete_semester)
+//! **GET**    | /semesters/{*name*}/grading_policy | [get_grading_policy](crate::routes::officer_routes::get_grading_policy)
+//! **POST**   | /semesters/{*name*}/grading_policy | [set_grading_policy](crate::routes::officer_routes::set_grading_policy)
+//! **DELETE** | /semesters/{*name*}/grading_policy | [reset_grading_policy](crate::routes::officer_routes::reset_grading_policy)
+//! **GET**    | /semesters/{*name*}/final_grades   | [get_final_grades](crate::routes::officer_routes::get_final_grades)
+//! **POST**   | /semesters/{*name*}/finalize       | [finalize_semester](crate::routes::officer_routes::finalize_semester)
+//! **POST**   | /semesters/{*name*}/reopen         | [reopen_semester](crate::routes::officer_routes::reopen_semester)
 //!
 //! ### Permissions and Roles:
 //!
@@ -240,26 +314,38 @@ extern crate base64;
 extern crate bcrypt;
 extern crate cgi;
 extern crate chrono;
+extern crate csv;
 #[macro_use]
 extern crate diesel;
 extern crate diesel_derive_enum;
 extern crate dotenv;
 extern crate glob;
+extern crate hex;
+extern crate hmac;
 extern crate icalendar;
 extern crate itertools;
+extern crate lettre;
 extern crate regex;
 extern crate serde;
 extern crate serde_json;
+extern crate sha2;
+extern crate ureq;
 extern crate url;
 extern crate uuid;
 extern crate zip;
 
 mod auth;
-mod cron;
 mod db;
+mod email;
 mod error;
+mod export;
+mod feeds;
+mod ics;
+mod jobs;
+mod recurrence;
 pub mod routes;
 mod util;
+mod webhook;
 
 fn main() {
     dotenv::dotenv().ok();
@@ -267,6 +353,7 @@ fn main() {
     if std::env::var("REQUEST_METHOD").is_ok() {
         cgi::handle(routes::handle_request);
     } else {
-        cron::send_event_emails(None).ok();
+        let args = std::env::args().skip(1).collect();
+        std::process::exit(jobs::run_from_command_line(args));
     }
 }
@@ -0,0 +1,562 @@
+//! Recurrence rules for repeating events, following the parts of
+//! [RFC 5545](https://tools.ietf.org/html/rfc5545#section-3.3.10) that make
+//! sense for club events.
+//!
+//! Rules are expanded in local time, so a rehearsal at 6:30 PM stays at
+//! 6:30 PM on both sides of a daylight saving time change.
+
+use chrono::{
+    Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use error::{GreaseError, GreaseResult};
+
+/// The most occurrences a single rule may expand to.
+pub const MAX_OCCURRENCES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed `RRULE`, e.g. `FREQ=WEEKLY;BYDAY=TU,TH;UNTIL=20200501`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<usize>,
+    pub until: Option<NaiveDateTime>,
+    /// Weekdays, each optionally with which one in the month or year it is
+    /// (e.g. `1MO` for the first Monday, `-1FR` for the last Friday)
+    pub by_day: Vec<(Option<i32>, Weekday)>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+}
+
+impl RecurrenceRule {
+    /// Parse a rule, with or without a leading `RRULE:`.
+    pub fn parse(rule: &str) -> GreaseResult<RecurrenceRule> {
+        let rule = rule.trim();
+        let rule = if rule.to_uppercase().starts_with("RRULE:") {
+            &rule[6..]
+        } else {
+            rule
+        };
+
+        let mut frequency = None;
+        let mut parsed = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let mut name_and_value = part.splitn(2, '=');
+            let name = name_and_value.next().unwrap_or_default().to_uppercase();
+            let value = name_and_value
+                .next()
+                .ok_or_else(|| bad_rule(format!("\"{}\" has no value", part)))?
+                .to_uppercase();
+
+            match name.as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => {
+                            return Err(bad_rule(format!(
+                                "events can't repeat with frequency {}",
+                                other
+                            )))
+                        }
+                    })
+                }
+                "INTERVAL" => {
+                    parsed.interval = parse_number(&name, &value)?;
+                    if parsed.interval == 0 {
+                        return Err(bad_rule("INTERVAL must be at least 1".to_owned()));
+                    }
+                }
+                "COUNT" => parsed.count = Some(parse_number(&name, &value)?),
+                "UNTIL" => parsed.until = Some(parse_date_or_time(&value)?),
+                "BYDAY" => {
+                    parsed.by_day = value
+                        .split(',')
+                        .map(parse_weekday_with_ordinal)
+                        .collect::<GreaseResult<_>>()?
+                }
+                "BYMONTHDAY" => {
+                    parsed.by_month_day = value
+                        .split(',')
+                        .map(|day| {
+                            parse_number::<i32>(&name, day).and_then(|day| {
+                                if day == 0 || day.abs() > 31 {
+                                    Err(bad_rule(format!("{} isn't a day of the month", day)))
+                                } else {
+                                    Ok(day)
+                                }
+                            })
+                        })
+                        .collect::<GreaseResult<_>>()?
+                }
+                "BYMONTH" => {
+                    parsed.by_month = value
+                        .split(',')
+                        .map(|month| {
+                            parse_number::<u32>(&name, month).and_then(|month| {
+                                if month == 0 || month > 12 {
+                                    Err(bad_rule(format!("{} isn't a month", month)))
+                                } else {
+                                    Ok(month)
+                                }
+                            })
+                        })
+                        .collect::<GreaseResult<_>>()?
+                }
+                // weeks always start on Monday here, so this only matters
+                // for rules we would reject anyway
+                "WKST" => {}
+                other => {
+                    return Err(bad_rule(format!("{} isn't supported", other)));
+                }
+            }
+        }
+
+        parsed.frequency = frequency.ok_or_else(|| bad_rule("FREQ is required".to_owned()))?;
+        if parsed.count.is_some() && parsed.until.is_some() {
+            return Err(bad_rule("COUNT and UNTIL can't both be given".to_owned()));
+        }
+        let has_ordinals = parsed
+            .by_day
+            .iter()
+            .any(|(ordinal, _day)| ordinal.is_some());
+        if has_ordinals
+            && (parsed.frequency == Frequency::Daily || parsed.frequency == Frequency::Weekly)
+        {
+            return Err(bad_rule(
+                "numbered weekdays (like 1MO) only work with monthly or yearly rules".to_owned(),
+            ));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Every time this rule repeats the given start time, minus the given
+    /// exceptions, in order.
+    ///
+    /// If the rule has neither a COUNT nor an UNTIL, it repeats until the end
+    /// of `default_until`. Exceptions match any occurrence on the same day.
+    pub fn occurrences(
+        &self,
+        start: &NaiveDateTime,
+        default_until: Option<NaiveDate>,
+        exceptions: &Vec<NaiveDate>,
+    ) -> GreaseResult<Vec<NaiveDateTime>> {
+        let last_day = match (self.count, self.until, default_until) {
+            (Some(_count), _, _) => None,
+            (None, Some(until), _) => Some(until.date()),
+            (None, None, Some(default_until)) => Some(default_until),
+            (None, None, None) => {
+                return Err(bad_rule(
+                    "a COUNT, an UNTIL, or a date to repeat until is required".to_owned(),
+                ))
+            }
+        };
+
+        let mut occurrences = Vec::new();
+        let mut day = start.date();
+        loop {
+            if last_day.map(|last_day| day > last_day).unwrap_or(false)
+                || self
+                    .count
+                    .map(|count| occurrences.len() >= count)
+                    .unwrap_or(false)
+            {
+                break;
+            }
+
+            if self.matches(start.date(), day) {
+                let occurrence = day.and_time(start.time());
+                if self.until.map(|until| occurrence > until).unwrap_or(false) {
+                    break;
+                }
+                if occurrences.len() == MAX_OCCURRENCES {
+                    return Err(bad_rule(format!(
+                        "it would create more than {} events",
+                        MAX_OCCURRENCES
+                    )));
+                }
+                occurrences.push(occurrence);
+            }
+
+            day = day
+                .succ_opt()
+                .ok_or_else(|| bad_rule("it never ends".to_owned()))?;
+            // a COUNT that can never be reached shouldn't loop forever
+            if day - start.date() > Duration::days(366 * 20) {
+                break;
+            }
+        }
+
+        occurrences.retain(|occurrence| !exceptions.contains(&occurrence.date()));
+
+        Ok(occurrences)
+    }
+
+    /// Whether this rule, started on `start`, falls on the given day.
+    fn matches(&self, start: NaiveDate, day: NaiveDate) -> bool {
+        let in_period = match self.frequency {
+            Frequency::Daily => (day - start).num_days() % self.interval as i64 == 0,
+            Frequency::Weekly => {
+                let week_of = |date: NaiveDate| {
+                    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+                };
+                ((week_of(day) - week_of(start)).num_days() / 7) % self.interval as i64 == 0
+            }
+            Frequency::Monthly => {
+                let months =
+                    (day.year() - start.year()) * 12 + day.month() as i32 - start.month() as i32;
+                months % self.interval as i32 == 0
+            }
+            Frequency::Yearly => (day.year() - start.year()) % self.interval as i32 == 0,
+        };
+        if !in_period {
+            return false;
+        }
+
+        if !self.by_month.is_empty() && !self.by_month.contains(&day.month()) {
+            return false;
+        }
+        if !self.by_month_day.is_empty()
+            && !self
+                .by_month_day
+                .iter()
+                .any(|&month_day| day_of_month_matches(day, month_day))
+        {
+            return false;
+        }
+        if !self.by_day.is_empty()
+            && !self
+                .by_day
+                .iter()
+                .any(|&(ordinal, weekday)| self.weekday_matches(day, ordinal, weekday))
+        {
+            return false;
+        }
+
+        // without any BY* parts, a rule repeats on the same part of its
+        // period as the day it started
+        let no_day_rules = self.by_day.is_empty() && self.by_month_day.is_empty();
+        match self.frequency {
+            Frequency::Daily => true,
+            Frequency::Weekly => !self.by_day.is_empty() || day.weekday() == start.weekday(),
+            Frequency::Monthly => !no_day_rules || day.day() == start.day(),
+            Frequency::Yearly => {
+                if no_day_rules {
+                    day.day() == start.day()
+                        && (!self.by_month.is_empty() || day.month() == start.month())
+                } else {
+                    true
+                }
+            }
+        }
+    }
+
+    fn weekday_matches(&self, day: NaiveDate, ordinal: Option<i32>, weekday: Weekday) -> bool {
+        if day.weekday() != weekday {
+            return false;
+        }
+        let ordinal = match ordinal {
+            Some(ordinal) => ordinal,
+            None => return true,
+        };
+
+        // numbered weekdays count within the month, unless a yearly rule
+        // doesn't pick any months, in which case they count within the year
+        let (first, last) = if self.frequency == Frequency::Yearly && self.by_month.is_empty() {
+            (
+                NaiveDate::from_ymd(day.year(), 1, 1),
+                NaiveDate::from_ymd(day.year(), 12, 31),
+            )
+        } else {
+            (
+                NaiveDate::from_ymd(day.year(), day.month(), 1),
+                last_day_of_month(day),
+            )
+        };
+
+        if ordinal > 0 {
+            (day - first).num_days() / 7 + 1 == ordinal as i64
+        } else {
+            (last - day).num_days() / 7 + 1 == -ordinal as i64
+        }
+    }
+}
+
+/// Parse a list of exceptions, each a date (`20200310`) or a date and time
+/// (`20200310T183000`), as they would appear in an `EXDATE`.
+pub fn parse_exceptions(exceptions: &Vec<String>) -> GreaseResult<Vec<NaiveDate>> {
+    exceptions
+        .iter()
+        .flat_map(|exception| exception.split(','))
+        .map(|exception| {
+            let exception = exception.trim();
+            let exception = if exception.to_uppercase().starts_with("EXDATE:") {
+                &exception[7..]
+            } else {
+                exception
+            };
+            parse_date_or_time(&exception.to_uppercase()).map(|time| time.date())
+        })
+        .collect()
+}
+
+/// Parse an iCalendar `DATE` or `DATE-TIME`. Times in UTC (ending in `Z`) are
+/// converted to local time, and dates are treated as the end of that day.
+pub fn parse_date_or_time(value: &str) -> GreaseResult<NaiveDateTime> {
+    let invalid = || bad_rule(format!("{} isn't a valid date or time", value));
+
+    if value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(|date| date.and_time(NaiveTime::from_hms(23, 59, 59)))
+            .map_err(|_err| invalid())
+    } else if value.ends_with('Z') {
+        NaiveDateTime::parse_from_str(&value[..value.len() - 1], "%Y%m%dT%H%M%S")
+            .map(|time| {
+                Utc.from_utc_datetime(&time)
+                    .with_timezone(&Local)
+                    .naive_local()
+            })
+            .map_err(|_err| invalid())
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_err| invalid())
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> GreaseResult<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_err| bad_rule(format!("{} isn't a valid {}", value, name)))
+}
+
+fn parse_weekday_with_ordinal(value: &str) -> GreaseResult<(Option<i32>, Weekday)> {
+    let value = value.trim();
+    // the day is split off by bytes below, which only works for ASCII
+    if !value.is_ascii() || value.len() < 2 {
+        return Err(bad_rule(format!("{} isn't a day of the week", value)));
+    }
+    let (ordinal, day) = value.split_at(value.len() - 2);
+    let weekday = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        other => return Err(bad_rule(format!("{} isn't a day of the week", other))),
+    };
+    let ordinal = if ordinal.is_empty() {
+        None
+    } else {
+        let ordinal = parse_number::<i32>("BYDAY", ordinal.trim_start_matches('+'))?;
+        if ordinal == 0 || ordinal.abs() > 53 {
+            return Err(bad_rule(format!(
+                "{}{} isn't a valid weekday",
+                ordinal, day
+            )));
+        }
+        Some(ordinal)
+    };
+
+    Ok((ordinal, weekday))
+}
+
+fn last_day_of_month(day: NaiveDate) -> NaiveDate {
+    let (year, month) = if day.month() == 12 {
+        (day.year() + 1, 1)
+    } else {
+        (day.year(), day.month() + 1)
+    };
+
+    NaiveDate::from_ymd(year, month, 1).pred()
+}
+
+/// Whether a day matches a BYMONTHDAY value, which counts from the end of
+/// the month when negative. Months without the day (like the 31st of
+/// April) are skipped rather than rounded.
+fn day_of_month_matches(day: NaiveDate, month_day: i32) -> bool {
+    if month_day > 0 {
+        day.day() as i32 == month_day
+    } else {
+        let from_end = last_day_of_month(day).day() as i32 - day.day() as i32 + 1;
+        from_end == -month_day
+    }
+}
+
+fn bad_rule(reason: String) -> GreaseError {
+    GreaseError::BadRequest(format!("Invalid recurrence rule: {}.", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(year: i32, month: u32, day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(year, month, day).and_hms(18, 30, 0)
+    }
+
+    fn dates(occurrences: Vec<NaiveDateTime>) -> Vec<NaiveDate> {
+        occurrences
+            .into_iter()
+            .map(|occurrence| occurrence.date())
+            .collect()
+    }
+
+    #[test]
+    fn parses_a_weekly_rule() {
+        let rule =
+            RecurrenceRule::parse("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,TH;COUNT=4").unwrap();
+
+        assert_eq!(rule.frequency, Frequency::Weekly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.count, Some(4));
+        assert_eq!(
+            rule.by_day,
+            vec![(None, Weekday::Tue), (None, Weekday::Thu)]
+        );
+    }
+
+    #[test]
+    fn parses_numbered_weekdays() {
+        let rule = RecurrenceRule::parse("freq=monthly;byday=1MO,-1FR,+2WE").unwrap();
+
+        assert_eq!(
+            rule.by_day,
+            vec![
+                (Some(1), Weekday::Mon),
+                (Some(-1), Weekday::Fri),
+                (Some(2), Weekday::Wed)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in &[
+            "",
+            "BYDAY=MO",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=3;UNTIL=20200501",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=YEARLY;BYMONTH=13",
+            "FREQ=DAILY;BYSETPOS=1",
+            "FREQ=WEEKLY;BYDAY=M",
+            "FREQ=WEEKLY;BYDAY=1\u{e9}",
+            "FREQ=WEEKLY;BYDAY=\u{e9}\u{e9}",
+        ] {
+            assert!(RecurrenceRule::parse(rule).is_err(), "{} parsed", rule);
+        }
+    }
+
+    #[test]
+    fn repeats_weekly_on_chosen_days_until_a_date() {
+        let rule = RecurrenceRule::parse("FREQ=WEEKLY;BYDAY=TU,TH;UNTIL=20200317").unwrap();
+        let occurrences = rule
+            .occurrences(&time(2020, 3, 3), None, &Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            dates(occurrences),
+            vec![
+                NaiveDate::from_ymd(2020, 3, 3),
+                NaiveDate::from_ymd(2020, 3, 5),
+                NaiveDate::from_ymd(2020, 3, 10),
+                NaiveDate::from_ymd(2020, 3, 12),
+                NaiveDate::from_ymd(2020, 3, 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_start_time_and_skips_exceptions() {
+        let rule = RecurrenceRule::parse("FREQ=DAILY;INTERVAL=2;COUNT=4").unwrap();
+        let exceptions = parse_exceptions(&vec!["20200305".to_owned()]).unwrap();
+        let occurrences = rule
+            .occurrences(&time(2020, 3, 1), None, &exceptions)
+            .unwrap();
+
+        assert_eq!(
+            occurrences,
+            vec![time(2020, 3, 1), time(2020, 3, 3), time(2020, 3, 7)]
+        );
+    }
+
+    #[test]
+    fn finds_the_last_weekday_of_each_month() {
+        let rule = RecurrenceRule::parse("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3").unwrap();
+        let occurrences = rule
+            .occurrences(&time(2020, 1, 1), None, &Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            dates(occurrences),
+            vec![
+                NaiveDate::from_ymd(2020, 1, 31),
+                NaiveDate::from_ymd(2020, 2, 28),
+                NaiveDate::from_ymd(2020, 3, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_months_without_the_day() {
+        let rule = RecurrenceRule::parse("FREQ=MONTHLY;BYMONTHDAY=31").unwrap();
+        let occurrences = rule
+            .occurrences(
+                &time(2020, 1, 31),
+                Some(NaiveDate::from_ymd(2020, 5, 31)),
+                &Vec::new(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            dates(occurrences),
+            vec![
+                NaiveDate::from_ymd(2020, 1, 31),
+                NaiveDate::from_ymd(2020, 3, 31),
+                NaiveDate::from_ymd(2020, 5, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn needs_somewhere_to_stop() {
+        let rule = RecurrenceRule::parse("FREQ=WEEKLY").unwrap();
+
+        assert!(rule
+            .occurrences(&time(2020, 3, 3), None, &Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn refuses_to_create_too_many_events() {
+        let rule = RecurrenceRule::parse("FREQ=DAILY;COUNT=501").unwrap();
+
+        assert!(rule
+            .occurrences(&time(2020, 3, 3), None, &Vec::new())
+            .is_err());
+    }
+}
@@ -1,13 +1,17 @@
 //! All event-focused routes.
 
-use super::basic_success;
+use super::{basic_success, FileResponse};
 use crate::check_for_permission;
 use auth::User;
+use db::models::attendance::AttendanceRowError;
+use db::models::event::EventScope;
 use db::models::grades::Grades;
 use db::schema::*;
 use db::*;
 use diesel::prelude::*;
 use error::*;
+use export::{ExportFilter, ExportFormat, Spreadsheet};
+use feeds::{self, PublicGig};
 use serde_json::{json, Value};
 
 /// Get a single event.
@@ -76,6 +80,11 @@ pub fn get_events(full: Option<bool>, user: User) -> GreaseResult<Value> {
     }
 }
 
+/// Get the public gigs of the current semester.
+///
+/// ## Return Format:
+///
+/// Returns a list of [PublicEvent](crate::db::models::event::PublicEvent)s.
 pub fn get_public_events() -> GreaseResult<Value> {
     let conn = connect_to_db()?;
     let events = Event::load_all_public_events_for_current_semester(&conn)?;
@@ -83,6 +92,43 @@ pub fn get_public_events() -> GreaseResult<Value> {
     Ok(json!(events))
 }
 
+/// Get every public gig from every semester as a feed.
+///
+/// Each feed has its own route:
+///   * "/public_events.ics": An iCalendar file for calendar apps
+///   * "/public_events.rss": An RSS feed
+///   * "/public_events.atom": An Atom feed
+///   * "/public_events.jsonld": A list of schema.org `MusicEvent`s as JSON-LD
+///
+/// ## Required Permissions:
+///
+/// None, this route is public.
+pub fn get_public_events_feed(file_name: &str) -> GreaseResult<FileResponse> {
+    let content_type = match file_name {
+        "public_events.ics" => "text/calendar; charset=utf-8",
+        "public_events.rss" => "application/rss+xml; charset=utf-8",
+        "public_events.atom" => "application/atom+xml; charset=utf-8",
+        "public_events.jsonld" => "application/ld+json",
+        _other => return Err(GreaseError::NotFound),
+    };
+    let conn = connect_to_db()?;
+    let all_gigs = Event::load_all_public_gigs(&conn)?;
+    let gigs = PublicGig::all(&all_gigs);
+
+    let body = match file_name {
+        "public_events.ics" => feeds::to_ics(&gigs),
+        "public_events.rss" => feeds::to_rss(&gigs),
+        "public_events.atom" => feeds::to_atom(&gigs),
+        _json_ld => feeds::to_json_ld(&gigs).to_string(),
+    };
+
+    Ok(FileResponse {
+        content_type,
+        file_name: None,
+        body: body.into_bytes(),
+    })
+}
+
 pub fn get_weeks_events() -> GreaseResult<Value> {
     use chrono::{Datelike, Duration, Local};
 
@@ -119,15 +165,76 @@ pub fn get_weeks_events() -> GreaseResult<Value> {
 ///
 /// ```json
 /// {
-///     "id": integer
+///     "id": integer,
+///     "conflicts": [EventConflict]
 /// }
 /// ```
 ///
 /// Returns an object containing the id of the newly created event
-/// (the first one if multiple were created).
+/// (the first one if multiple were created), and any events in the same
+/// semester that the new events overlap (see
+/// [EventConflict](crate::db::models::event::EventConflict)). If `strict`
+/// was set, overlapping events are refused instead.
 pub fn new_event(new_event: NewEvent, user: User) -> GreaseResult<Value> {
     check_for_permission!(user => "create-event", &new_event.fields.type_);
-    Event::create(new_event, None, &user.conn).map(|new_id| json!({ "id": new_id }))
+    Event::create(new_event, None, &user.conn)
+        .map(|(new_id, conflicts)| json!({ "id": new_id, "conflicts": conflicts }))
+}
+
+/// Import events from an iCalendar file.
+///
+/// Without `confirm`, nothing is created, and a preview of what would be
+/// imported is returned instead. Events are recognized by their `UID`, so
+/// importing the same file twice skips (or updates) the events from the
+/// first import. Events that start outside of the semester are left out.
+///
+/// ## Input Format:
+///
+/// Expects an [EventImport](crate::db::models::EventImport).
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in, and must be able to "create-event" for
+/// every type that categories are mapped to.
+///
+/// ## Return Format:
+///
+/// When previewing:
+///
+/// ```json
+/// {
+///     "events": [ImportCandidate],
+///     "categories": [string]
+/// }
+/// ```
+///
+/// Returns each event from the calendar (see
+/// [ImportCandidate](crate::db::models::event_import::ImportCandidate)),
+/// along with every category used in the calendar.
+///
+/// When confirming:
+///
+/// ```json
+/// {
+///     "created": integer,
+///     "updated": integer,
+///     "skipped": integer,
+///     "conflicts": [EventConflict]
+/// }
+/// ```
+///
+/// Imported events are allowed to overlap other events, but any that do
+/// are returned (see [EventConflict](crate::db::models::event::EventConflict)).
+pub fn import_events(event_import: EventImport, user: User) -> GreaseResult<Value> {
+    for event_type in event_import.mapped_types() {
+        check_for_permission!(user => "create-event", event_type);
+    }
+
+    if event_import.confirm {
+        event_import.import(&user.conn)
+    } else {
+        event_import.preview_json(&user.conn)
+    }
 }
 
 /// Update an existing event.
@@ -135,6 +242,11 @@ pub fn new_event(new_event: NewEvent, user: User) -> GreaseResult<Value> {
 /// ## Path Parameters:
 ///   * id: integer (*required*) - The ID of the event
 ///
+/// ## Query Parameters:
+///   * scope: string (*optional*) - For repeated events, whether to update
+///       just "this" event (the default), this and the "following" events,
+///       or every event in the "series"
+///
 /// ## Required Permissions:
 ///
 /// The user must be logged in, and must be able to either
@@ -142,14 +254,39 @@ pub fn new_event(new_event: NewEvent, user: User) -> GreaseResult<Value> {
 ///
 /// ## Input Format:
 ///
-/// Expects an [EventUpdate](crate::db::models::EventUpdate).
-pub fn update_event(id: i32, updated_event: EventUpdate, user: User) -> GreaseResult<Value> {
+/// Expects an [EventUpdate](crate::db::models::EventUpdate). When updating
+/// multiple events, each is moved by as much as this event's call time
+/// changed (see [update](crate::db::models::Event::update)).
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "message": "success!",
+///     "conflicts": [EventConflict]
+/// }
+/// ```
+///
+/// Returns any other events in the semester that the updated events
+/// overlap (see [EventConflict](crate::db::models::event::EventConflict)).
+/// If `strict` was set, overlapping events are refused instead.
+pub fn update_event(
+    id: i32,
+    scope: Option<String>,
+    updated_event: EventUpdate,
+    user: User,
+) -> GreaseResult<Value> {
+    let scope = EventScope::parse(scope)?;
     if !user.has_permission("edit-all-events", None) {
         let event = Event::load(id, &user.conn)?;
         check_for_permission!(user => "modify-event", &event.event.type_);
     }
 
-    Event::update(id, updated_event, &user.conn).map(|_| basic_success())
+    let conflicts = Event::update(id, updated_event, scope, &user.conn)?;
+    let mut response = basic_success();
+    response["conflicts"] = json!(conflicts);
+
+    Ok(response)
 }
 
 /// RSVP for an event.
@@ -181,15 +318,71 @@ pub fn confirm_for_event(event_id: i32, user: User) -> GreaseResult<Value> {
 /// ## Path Parameters:
 ///   * id: integer (*required*) - The ID of the event
 ///
+/// ## Query Parameters:
+///   * scope: string (*optional*) - For repeated events, whether to delete
+///       just "this" event (the default), this and the "following" events,
+///       or every event in the "series"
+///
 /// ## Required Permissions:
 ///
 /// The user must be logged in, and must be able to "delete-event"
 /// generally or for the specified event's type.
-pub fn delete_event(id: i32, user: User) -> GreaseResult<Value> {
+pub fn delete_event(id: i32, scope: Option<String>, user: User) -> GreaseResult<Value> {
+    let scope = EventScope::parse(scope)?;
     let event = Event::load(id, &user.conn)?;
     check_for_permission!(user => "delete-event", &event.event.type_);
 
-    Event::delete(id, &user.conn).map(|_| basic_success())
+    Event::delete(id, scope, &user.conn).map(|_| basic_success())
+}
+
+/// Get every pair of overlapping events in a semester.
+///
+/// ## Query Parameters:
+///   * semester: string (*optional*) - The semester to check, defaulting
+///       to the current one
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in. Members who can "view-attendance" generally
+/// see every conflict along with everyone expected at both events. Anyone
+/// else only sees the conflicts that they are expected at both events for.
+///
+/// ## Return Format:
+///
+/// ```json
+/// [
+///     {
+///         "first": Event,
+///         "second": Event,
+///         "members": [string]
+///     },
+///     ...
+/// ]
+/// ```
+///
+/// Returns a list of overlapping pairs of [Event](crate::db::models::Event)s,
+/// ordered by the call time of the first, with the emails of the members
+/// expected at both. Events without a release time are assumed to last an hour.
+pub fn get_event_conflicts(semester: Option<String>, user: User) -> GreaseResult<Value> {
+    let semester = match semester {
+        Some(semester_name) => Semester::load(&semester_name, &user.conn)?,
+        None => Semester::load_current(&user.conn)?,
+    };
+    let conflicts = Event::load_schedule_conflicts(&semester.name, &user.conn)?;
+
+    if user.has_permission("view-attendance", None) {
+        Ok(json!(conflicts))
+    } else {
+        let email = &user.member.member.email;
+        Ok(json!(conflicts
+            .into_iter()
+            .filter(|conflict| conflict.members.contains(email))
+            .map(|mut conflict| {
+                conflict.members = vec![email.clone()];
+                conflict
+            })
+            .collect::<Vec<_>>()))
+    }
 }
 
 /// Load the attendance for an event.
@@ -283,6 +476,153 @@ pub fn get_member_attendance(event_id: i32, member: String, user: User) -> Greas
     Attendance::load(&member, event_id, &user.conn).map(|attendance| json!(attendance))
 }
 
+fn export_file(spreadsheet: Spreadsheet, format: ExportFormat) -> GreaseResult<FileResponse> {
+    Ok(FileResponse {
+        content_type: format.content_type(),
+        file_name: Some(spreadsheet.file_name(format)),
+        body: spreadsheet.to_file(format)?,
+    })
+}
+
+/// Download the attendance for an event as a spreadsheet.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Query Parameters:
+///   * format: string (*optional*) - Either "csv" (the default) or "xlsx"
+///   * section: string (*optional*) - Only include members of this section
+///   * enrollment: string (*optional*) - Only include members in the "class" or the "club"
+///
+/// ## Required Permissions:
+///
+/// The user must be able to "view-attendance" generally or for the event's type.
+///
+/// ## Return Format:
+///
+/// A CSV or XLSX file with a row for each member, with whether they should
+/// have attended, whether they did, whether they confirmed, how many
+/// minutes late they were or early they left, and whether that was excused.
+pub fn export_event_attendance(
+    event_id: i32,
+    format: Option<String>,
+    section: Option<String>,
+    enrollment: Option<String>,
+    user: User,
+) -> GreaseResult<FileResponse> {
+    let event = Event::load(event_id, &user.conn)?;
+    check_for_permission!(user => "view-attendance", &event.event.type_);
+    let format = ExportFormat::parse(format)?;
+    let filter = ExportFilter::parse(section, enrollment)?;
+
+    export_file(
+        Spreadsheet::for_event_attendance(event_id, &filter, &user.conn)?,
+        format,
+    )
+}
+
+/// Download the attendance for every event in a semester as a spreadsheet.
+///
+/// ## Query Parameters:
+///   * semester: string (*optional*) - The semester to export, defaulting
+///       to the current one
+///   * format: string (*optional*) - Either "csv" (the default) or "xlsx"
+///   * section: string (*optional*) - Only include members of this section
+///   * enrollment: string (*optional*) - Only include members in the "class" or the "club"
+///
+/// ## Required Permissions:
+///
+/// The user must be able to "view-attendance".
+///
+/// ## Return Format:
+///
+/// A CSV or XLSX file with a row for each member, and three columns for
+/// each event: whether they should have attended, whether they did, and
+/// how many minutes late they were. Cells are empty when a member has no
+/// attendance for an event.
+pub fn export_semester_attendance(
+    semester: Option<String>,
+    format: Option<String>,
+    section: Option<String>,
+    enrollment: Option<String>,
+    user: User,
+) -> GreaseResult<FileResponse> {
+    check_for_permission!(user => "view-attendance");
+    let semester = match semester {
+        Some(semester_name) => Semester::load(&semester_name, &user.conn)?,
+        None => Semester::load_current(&user.conn)?,
+    };
+    let format = ExportFormat::parse(format)?;
+    let filter = ExportFilter::parse(section, enrollment)?;
+
+    export_file(
+        Spreadsheet::for_semester_attendance(&semester, &filter, &user.conn)?,
+        format,
+    )
+}
+
+/// Download every member's grade for a semester as a spreadsheet.
+///
+/// ## Query Parameters:
+///   * semester: string (*optional*) - The semester to export, defaulting
+///       to the current one
+///   * format: string (*optional*) - Either "csv" (the default) or "xlsx"
+///   * section: string (*optional*) - Only include members of this section
+///   * enrollment: string (*optional*) - Only include members in the "class" or the "club"
+///
+/// ## Required Permissions:
+///
+/// The user must be able to "view-attendance".
+///
+/// ## Return Format:
+///
+/// A CSV or XLSX file with a row for each member, with their grade, how many
+/// volunteer gigs they attended, the semester's gig requirement, and whether
+/// they met it.
+pub fn export_grades(
+    semester: Option<String>,
+    format: Option<String>,
+    section: Option<String>,
+    enrollment: Option<String>,
+    user: User,
+) -> GreaseResult<FileResponse> {
+    check_for_permission!(user => "view-attendance");
+    let semester = match semester {
+        Some(semester_name) => Semester::load(&semester_name, &user.conn)?,
+        None => Semester::load_current(&user.conn)?,
+    };
+    let format = ExportFormat::parse(format)?;
+    let filter = ExportFilter::parse(section, enrollment)?;
+
+    export_file(
+        Spreadsheet::for_grades(&semester, &filter, &user.conn)?,
+        format,
+    )
+}
+
+/// Download the final grades of a finalized semester as a CSV for the registrar.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be able to "view-attendance".
+///
+/// ## Return Format:
+///
+/// A CSV file with a row for each member enrolled in the class, with their
+/// name, email, the semester, their final grade, and their letter grade.
+pub fn export_final_grades(name: String, user: User) -> GreaseResult<FileResponse> {
+    check_for_permission!(user => "view-attendance");
+    let semester = Semester::load(&name, &user.conn)?;
+
+    export_file(
+        Spreadsheet::for_registrar(&semester, &user.conn)?,
+        ExportFormat::Csv,
+    )
+}
+
 // TODO: fix these docs
 
 /// Get the attendance of all active members for an event.
@@ -347,24 +687,182 @@ pub fn update_attendance(
     user: User,
 ) -> GreaseResult<Value> {
     let event = Event::load(event_id, &user.conn)?;
+
+    if can_edit_attendance(&user, &event.event, &member)? {
+        Attendance::update(event_id, &member, &attendance_form, &user.conn).map(|_| basic_success())
+    } else {
+        Err(GreaseError::Forbidden(Some("edit-attendance".to_owned())))
+    }
+}
+
+/// Whether the user can edit a member's attendance at an event, following
+/// the rules of [update_attendance](crate::routes::event_routes::update_attendance).
+fn can_edit_attendance(user: &User, event: &Event, member: &str) -> GreaseResult<bool> {
     let user_section = user
         .member
         .active_semester
         .as_ref()
         .and_then(|active_semester| active_semester.section.clone());
-    let member_section = ActiveSemester::load(&member, &event.event.semester, &user.conn)?
+    let member_section = ActiveSemester::load(member, &event.semester, &user.conn)?
         .and_then(|active_semester| active_semester.section);
 
-    if user.has_permission("edit-attendance", Some(event.event.type_.as_str()))
-        || (user_section == member_section
-            && user.has_permission(
-                "edit-attendance-own-section",
-                Some(event.event.type_.as_str()),
-            ))
-    {
-        Attendance::update(event_id, &member, &attendance_form, &user.conn).map(|_| basic_success())
+    Ok(
+        user.has_permission("edit-attendance", Some(event.type_.as_str()))
+            || (user_section == member_section
+                && user.has_permission("edit-attendance-own-section", Some(event.type_.as_str()))),
+    )
+}
+
+/// Check every row of a bulk update, and apply them all in one transaction
+/// if none have problems. Otherwise, nothing is changed and the problems
+/// are returned.
+fn apply_attendance_updates(
+    event: &Event,
+    rows: Vec<(usize, MemberAttendanceForm)>,
+    user: &User,
+) -> GreaseResult<Vec<AttendanceRowError>> {
+    let mut errors = Vec::new();
+    for (row, update) in &rows {
+        let error = if !can_edit_attendance(user, event, &update.member)? {
+            Some("You aren't allowed to edit this member's attendance.".to_owned())
+        } else {
+            Attendance::check_row(event.id, update, &user.conn)?
+        };
+
+        if let Some(error) = error {
+            errors.push(AttendanceRowError {
+                row: *row,
+                member: update.member.clone(),
+                error,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        let updates = rows.into_iter().map(|(_row, update)| update).collect();
+        Attendance::update_many(event.id, &updates, &user.conn)?;
+    }
+
+    Ok(errors)
+}
+
+/// Update the attendance for many members at an event at once.
+///
+/// Either every update is applied, or none are and the problems with each
+/// row are returned.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The same as [update_attendance](crate::routes::event_routes::update_attendance),
+/// for every member being updated.
+///
+/// ## Input Format:
+///
+/// Expects a list of [MemberAttendanceForm](crate::db::models::MemberAttendanceForm)s.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "updated": integer,
+///     "errors": [
+///         {
+///             "row": integer,
+///             "member": string,
+///             "error": string
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+///
+/// Rows are numbered by their index in the list, starting at 0.
+pub fn update_attendance_in_bulk(
+    event_id: i32,
+    updates: Vec<MemberAttendanceForm>,
+    user: User,
+) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?.event;
+    let num_updates = updates.len();
+    let errors =
+        apply_attendance_updates(&event, updates.into_iter().enumerate().collect(), &user)?;
+
+    Ok(json!({
+        "updated": if errors.is_empty() { num_updates } else { 0 },
+        "errors": errors,
+    }))
+}
+
+/// Import attendance for an event from a paper sign-in sheet.
+///
+/// Members are matched by email, or by name if there is no email column.
+/// Without `confirm`, nothing is changed, and a preview of which rows
+/// matched is returned instead. When confirming, only matched rows are
+/// applied, and like [update_attendance_in_bulk](crate::routes::event_routes::update_attendance_in_bulk),
+/// either all of them are or none are.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The same as [update_attendance](crate::routes::event_routes::update_attendance),
+/// for every member being updated.
+///
+/// ## Input Format:
+///
+/// Expects an [AttendanceImport](crate::db::models::AttendanceImport).
+///
+/// ## Return Format:
+///
+/// When previewing:
+///
+/// ```json
+/// {
+///     "matched": [
+///         {
+///             "row": integer,
+///             "member": Member,
+///             "didAttend": boolean,
+///             "minutesLate": integer
+///         },
+///         ...
+///     ],
+///     "unmatched": [
+///         {
+///             "row": integer,
+///             "values": [string],
+///             "reason": string
+///         },
+///         ...
+///     ]
+/// }
+/// ```
+///
+/// When confirming, the same as [update_attendance_in_bulk](crate::routes::event_routes::update_attendance_in_bulk),
+/// along with the unmatched rows. Rows are numbered by their line in the file.
+pub fn import_attendance(
+    event_id: i32,
+    attendance_import: AttendanceImport,
+    user: User,
+) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?.event;
+    let (matched, unmatched) = attendance_import.match_rows(event_id, &user.conn)?;
+
+    if attendance_import.confirm {
+        let errors =
+            apply_attendance_updates(&event, AttendanceImport::as_updates(&matched), &user)?;
+
+        Ok(json!({
+            "updated": if errors.is_empty() { matched.len() } else { 0 },
+            "errors": errors,
+            "unmatched": unmatched,
+        }))
     } else {
-        Err(GreaseError::Forbidden(Some("edit-attendance".to_owned())))
+        Ok(AttendanceImport::preview_json(&matched, &unmatched))
     }
 }
 
@@ -384,6 +882,146 @@ pub fn excuse_unconfirmed_for_event(event_id: i32, mut user: User) -> GreaseResu
     Attendance::excuse_unconfirmed(event_id, &mut user.conn).map(|_| basic_success())
 }
 
+/// Open a window for members to check themselves in to an event.
+///
+/// Opening a new window replaces the old one, so its codes stop working.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance"
+/// either generally or for the given event's type.
+///
+/// ## Input Format:
+///
+/// Expects a [CheckInWindowForm](crate::db::models::CheckInWindowForm).
+///
+/// ## Return Format:
+///
+/// The same as [get_check_in_code](crate::routes::event_routes::get_check_in_code).
+pub fn open_check_in(event_id: i32, form: CheckInWindowForm, user: User) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", event.event.type_.as_str());
+
+    CheckInWindow::open(
+        event_id,
+        &user.member.member.email,
+        form.minutes,
+        &user.conn,
+    )
+    .map(|window| window.current_code())
+}
+
+/// Get the current check-in code for an event.
+///
+/// The code changes every 30 seconds, so this should be polled while it's
+/// being shown to members.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance"
+/// either generally or for the given event's type.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "window": CheckInWindow,
+///     "code": string,
+///     "expires": datetime,
+///     "qrPayload": string
+/// }
+/// ```
+///
+/// Returns the [CheckInWindow](crate::db::models::CheckInWindow), its
+/// current six digit code, when that code expires, and a string to encode
+/// in a QR code for members to scan.
+pub fn get_check_in_code(event_id: i32, user: User) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", event.event.type_.as_str());
+
+    CheckInWindow::load(event_id, &user.conn)?
+        .filter(|window| window.is_open())
+        .map(|window| window.current_code())
+        .ok_or(GreaseError::BadRequest(
+            "Check-in isn't open for this event.".to_owned(),
+        ))
+}
+
+/// Close the check-in window for an event.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance"
+/// either generally or for the given event's type.
+pub fn close_check_in(event_id: i32, user: User) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", event.event.type_.as_str());
+
+    CheckInWindow::close(event_id, &user.conn).map(|_| basic_success())
+}
+
+/// Check in to an event with the code shown by an officer.
+///
+/// Members are marked as attending, and as late by however many whole
+/// minutes after call time they checked in. After five wrong codes,
+/// members can't check in to the window themselves and have to ask an
+/// officer; opening a new window lets them try again.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and active for the semester of the event.
+///
+/// ## Input Format:
+///
+/// Expects a [CheckIn](crate::db::models::CheckIn).
+///
+/// ## Return Format:
+///
+/// Returns the member's updated [Attendance](crate::db::models::Attendance).
+pub fn check_in_to_event(event_id: i32, check_in: CheckIn, user: User) -> GreaseResult<Value> {
+    CheckInWindow::check_in(event_id, &user.member, &check_in.code, &user.conn)
+        .map(|attendance| json!(attendance))
+}
+
+/// Get the members expected at an event who haven't checked in yet.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance"
+/// either generally or for the given event's type.
+///
+/// ## Return Format:
+///
+/// Returns a list of [Member](crate::db::models::Member)s, with their
+/// sections, ordered by last name.
+pub fn get_not_checked_in(event_id: i32, user: User) -> GreaseResult<Value> {
+    let event = Event::load(event_id, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", event.event.type_.as_str());
+
+    CheckInWindow::not_checked_in(event_id, &user.conn).map(|members| {
+        members
+            .iter()
+            .map(|member| member.to_json())
+            .collect::<Vec<_>>()
+            .into()
+    })
+}
+
 /// Get a the carpools for an event.
 ///
 /// ## Path Parameters:
@@ -550,6 +1188,8 @@ pub fn submit_absence_request(
 
 /// Approve an absence request.
 ///
+/// The member is emailed about the decision, along with the comment if one is given.
+///
 /// ## Required Permissions:
 ///
 /// The user must be logged in and be able to "process-absence-requests" generally.
@@ -557,17 +1197,26 @@ pub fn submit_absence_request(
 /// ## Path Parameters:
 ///   * id: integer (*required*) - The ID of the event
 ///   * member: string (*required*) - The email of the requested member
+///
+/// ## Input Format:
+///
+/// Expects an [AbsenceRequestDecision](crate::db::models::AbsenceRequestDecision),
+/// or an empty body.
 pub fn approve_absence_request(
     event_id: i32,
     member: String,
+    decision: AbsenceRequestDecision,
     mut user: User,
 ) -> GreaseResult<Value> {
     check_for_permission!(user => "process-absence-requests");
-    AbsenceRequest::approve(&member, event_id, &mut user.conn).map(|_| basic_success())
+    AbsenceRequest::approve(&member, event_id, decision.comment, &mut user.conn)
+        .map(|_| basic_success())
 }
 
 /// Deny an absence request.
 ///
+/// The member is emailed about the decision, along with the comment if one is given.
+///
 /// ## Required Permissions:
 ///
 /// The user must be logged in and be able to "process-absence-requests" generally.
@@ -575,9 +1224,249 @@ pub fn approve_absence_request(
 /// ## Path Parameters:
 ///   * id: integer (*required*) - The ID of the event
 ///   * member: string (*required*) - The email of the requested member
-pub fn deny_absence_request(event_id: i32, member: String, mut user: User) -> GreaseResult<Value> {
+///
+/// ## Input Format:
+///
+/// Expects an [AbsenceRequestDecision](crate::db::models::AbsenceRequestDecision),
+/// or an empty body.
+pub fn deny_absence_request(
+    event_id: i32,
+    member: String,
+    decision: AbsenceRequestDecision,
+    mut user: User,
+) -> GreaseResult<Value> {
     check_for_permission!(user => "process-absence-requests");
-    AbsenceRequest::deny(&member, event_id, &mut user.conn).map(|_| basic_success())
+    AbsenceRequest::deny(&member, event_id, decision.comment, &mut user.conn)
+        .map(|_| basic_success())
+}
+
+/// Load a grade dispute, making sure the user either opened it or can
+/// "edit-attendance" for its event's type.
+fn load_grade_dispute_for(dispute_id: i32, user: &User) -> GreaseResult<GradeDispute> {
+    let dispute = GradeDispute::load(dispute_id, &user.conn)?;
+    if dispute.member != user.member.member.email {
+        let event = Event::load(dispute.event, &user.conn)?;
+        check_for_permission!(user => "edit-attendance", &event.event.type_);
+    }
+
+    Ok(dispute)
+}
+
+/// Get grade disputes.
+///
+/// Members that can "edit-attendance" get the queue of open disputes,
+/// oldest first, about events of the types they can edit it for. Everyone
+/// else gets the disputes they opened.
+///
+/// ## Query Parameters:
+///   * all: boolean (*optional*) - Whether to include closed disputes in the queue
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Return Format:
+///
+/// Returns a list of [GradeDispute](crate::db::models::GradeDispute)s.
+pub fn get_grade_disputes(all: Option<bool>, user: User) -> GreaseResult<Value> {
+    let event_types = user.permitted_event_types("edit-attendance");
+    if user.has_permission("edit-attendance", None) {
+        GradeDispute::load_queue(all.unwrap_or(false), None, &user.conn)
+    } else if !event_types.is_empty() {
+        GradeDispute::load_queue(all.unwrap_or(false), Some(&event_types), &user.conn)
+    } else {
+        GradeDispute::load_for_member(&user.member.member.email, &user.conn)
+    }
+    .map(|disputes| json!(disputes))
+}
+
+/// Get a single grade dispute, along with its history.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the dispute
+///
+/// ## Required Permissions:
+///
+/// The user must have opened the dispute, or be able to "edit-attendance"
+/// generally or for the event's type.
+///
+/// ## Return Format:
+///
+/// Returns the [to_json_with_history](crate::db::models::GradeDispute::to_json_with_history)
+/// format of the [GradeDispute](crate::db::models::GradeDispute).
+pub fn get_grade_dispute(dispute_id: i32, user: User) -> GreaseResult<Value> {
+    load_grade_dispute_for(dispute_id, &user)?.to_json_with_history(&user.conn)
+}
+
+/// Get the grade disputes about a member's attendance at an event,
+/// along with their histories.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///   * member: string (*required*) - The email of the member
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in. To see the disputes of another member, they
+/// must be able to "view-attendance" generally or for the event's type.
+///
+/// ## Return Format:
+///
+/// Returns a list of disputes in the
+/// [to_json_with_history](crate::db::models::GradeDispute::to_json_with_history)
+/// format, most recent first.
+pub fn get_attendance_grade_disputes(
+    event_id: i32,
+    member: String,
+    user: User,
+) -> GreaseResult<Value> {
+    if &member != &user.member.member.email {
+        let event = Event::load(event_id, &user.conn)?;
+        check_for_permission!(user => "view-attendance", &event.event.type_);
+    }
+
+    GradeDispute::load_for_attendance(&member, event_id, &user.conn)?
+        .iter()
+        .map(|dispute| dispute.to_json_with_history(&user.conn))
+        .collect::<GreaseResult<Vec<_>>>()
+        .map(|disputes| json!(disputes))
+}
+
+/// Dispute the grade change for the current member's attendance at an event.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the event
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Input Format:
+///
+/// Expects a [NewGradeDispute](crate::db::models::NewGradeDispute).
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "id": integer
+/// }
+/// ```
+///
+/// Returns an object containing the ID of the new dispute.
+pub fn submit_grade_dispute(
+    event_id: i32,
+    new_dispute: NewGradeDispute,
+    user: User,
+) -> GreaseResult<Value> {
+    GradeDispute::create(
+        &user.member.member.email,
+        event_id,
+        &new_dispute.message,
+        &user.conn,
+    )
+    .map(|new_id| json!({ "id": new_id }))
+}
+
+/// Comment on a grade dispute.
+///
+/// The member who opened the dispute is emailed about comments from officers.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the dispute
+///
+/// ## Required Permissions:
+///
+/// The user must have opened the dispute, or be able to "edit-attendance"
+/// generally or for the event's type.
+///
+/// ## Input Format:
+///
+/// Expects a [GradeDisputeComment](crate::db::models::GradeDisputeComment).
+pub fn comment_on_grade_dispute(
+    dispute_id: i32,
+    comment: GradeDisputeComment,
+    user: User,
+) -> GreaseResult<Value> {
+    let dispute = load_grade_dispute_for(dispute_id, &user)?;
+    GradeDispute::comment(
+        dispute.id,
+        &user.member.member.email,
+        &comment.content,
+        &user.conn,
+    )
+    .map(|_| basic_success())
+}
+
+/// Resolve a grade dispute by correcting the member's attendance or
+/// approving an absence for the event.
+///
+/// The member is emailed about the resolution, along with the comment if
+/// one is given. An approved absence is saved as an already approved
+/// absence request, without the emails or webhooks of submitting and
+/// approving one.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the dispute
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance" generally
+/// or for the event's type.
+///
+/// ## Input Format:
+///
+/// Expects a [GradeDisputeResolution](crate::db::models::GradeDisputeResolution).
+pub fn resolve_grade_dispute(
+    dispute_id: i32,
+    resolution: GradeDisputeResolution,
+    user: User,
+) -> GreaseResult<Value> {
+    let dispute = GradeDispute::load(dispute_id, &user.conn)?;
+    let event = Event::load(dispute.event, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", &event.event.type_);
+
+    GradeDispute::resolve(
+        dispute.id,
+        &user.member.member.email,
+        resolution,
+        &user.conn,
+    )
+    .map(|_| basic_success())
+}
+
+/// Reject a grade dispute, leaving the member's attendance as it is.
+///
+/// The member is emailed the reason it was rejected.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the dispute
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-attendance" generally
+/// or for the event's type.
+///
+/// ## Input Format:
+///
+/// Expects a [GradeDisputeComment](crate::db::models::GradeDisputeComment)
+/// with the reason for rejecting it.
+pub fn reject_grade_dispute(
+    dispute_id: i32,
+    reason: GradeDisputeComment,
+    user: User,
+) -> GreaseResult<Value> {
+    let dispute = GradeDispute::load(dispute_id, &user.conn)?;
+    let event = Event::load(dispute.event, &user.conn)?;
+    check_for_permission!(user => "edit-attendance", &event.event.type_);
+
+    GradeDispute::reject(
+        dispute.id,
+        &user.member.member.email,
+        &reason.content,
+        &user.conn,
+    )
+    .map(|_| basic_success())
 }
 
 /// Get all event types.
@@ -681,13 +1570,15 @@ pub fn new_gig_request(new_request: NewGigRequest) -> GreaseResult<Value> {
             .values(&new_request)
             .execute(&conn)?;
 
-        gig_request::table
+        let new_id = gig_request::table
             .select(gig_request::id)
             .order_by(gig_request::id.desc())
-            .first(&conn)
-            .map(|new_id: i32| json!({ "id": new_id }))
+            .first(&conn)?;
+        let submitted_request = GigRequest::load(new_id, &conn)?;
+        Webhook::trigger(WebhookKind::GigRequestSubmitted, &submitted_request, &conn)?;
+
+        Ok(json!({ "id": new_id }))
     })
-    .map_err(GreaseError::DbError)
 }
 
 /// Dismiss a gig request.
@@ -735,12 +1626,14 @@ pub fn reopen_gig_request(request_id: i32, mut user: User) -> GreaseResult<Value
 ///
 /// ```json
 /// {
-///     "id": integer
+///     "id": integer,
+///     "conflicts": [EventConflict]
 /// }
 /// ```
 ///
 /// Returns an object containing the id of the newly created event
-/// (the first one if multiple were created).
+/// (the first one if multiple were created), and any events that it
+/// overlaps (see [new_event](crate::routes::event_routes::new_event)).
 pub fn create_event_from_gig_request(
     request_id: i32,
     form: NewEvent,
@@ -753,6 +1646,7 @@ pub fn create_event_from_gig_request(
             "The gig request must be pending to create an event for it.".to_owned(),
         ))
     } else {
-        Event::create(form, Some(request), &mut user.conn).map(|new_id| json!({ "id": new_id }))
+        Event::create(form, Some(request), &mut user.conn)
+            .map(|(new_id, conflicts)| json!({ "id": new_id, "conflicts": conflicts }))
     }
 }
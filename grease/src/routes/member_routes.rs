@@ -1,14 +1,15 @@
 //! All member-focused routes.
 
-use super::basic_success;
+use super::{basic_success, FileResponse};
 use crate::auth::User;
 use crate::check_for_permission;
+use crate::db::models::grades::Grades;
 use crate::db::models::member::MemberForSemester;
 use crate::db::*;
 use crate::error::*;
 use db::schema::Enrollment;
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Log in to the API.
 ///
@@ -140,6 +141,27 @@ pub fn get_current_user(user: GreaseResult<User>) -> GreaseResult<Value> {
     }
 }
 
+/// See what the current member's grades would be this semester if their
+/// attendance changed, without actually changing it.
+///
+/// Changed events are graded even if they haven't happened yet.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Input Format:
+///
+/// Expects a list of [SimulatedAttendance](crate::db::models::SimulatedAttendance)s.
+///
+/// ## Return Format:
+///
+/// Returns the member's simulated [Grades](crate::db::models::grades::Grades),
+/// with the same breakdown of events with grade changes as their real grades.
+pub fn simulate_grades(changes: Vec<SimulatedAttendance>, user: User) -> GreaseResult<Value> {
+    Grades::simulate(&user.member, &changes, &user.conn).map(|grades| json!(grades))
+}
+
 /// Get all members.
 ///
 /// ## Query Parameters:
@@ -180,7 +202,18 @@ pub fn get_members(
     };
 
     MemberForSemester::load_all(&current_semester.name, &user.conn).and_then(|members| {
-        members
+        let (mut roster_grades, mut all_positions, mut all_permissions) = if grades.unwrap_or(false)
+        {
+            (
+                Grades::for_roster(&members, &current_semester, &user.conn)?,
+                Member::positions_for_all(&user.conn)?,
+                Member::permissions_for_all(&user.conn)?,
+            )
+        } else {
+            (HashMap::new(), HashMap::new(), HashMap::new())
+        };
+
+        let members_json = members
             .into_iter()
             .filter_map(|member| {
                 if let Some(ref active_semester) = member.active_semester {
@@ -193,15 +226,21 @@ pub fn get_members(
                     return None;
                 }
 
-                let json_val = if grades.unwrap_or(false) {
-                    member.to_json_full(&user.conn)
+                let email = &member.member.email;
+                let json_val = if let Some(member_grades) = roster_grades.remove(email) {
+                    member.to_json_with_calculated_grades(
+                        member_grades,
+                        all_positions.remove(email).unwrap_or_default(),
+                        all_permissions.remove(email).unwrap_or_default(),
+                    )
                 } else {
-                    Ok(member.to_json())
+                    member.to_json()
                 };
                 Some(json_val)
             })
-            .collect::<GreaseResult<Vec<_>>>()
-            .map(|members| json!(members))
+            .collect::<Vec<_>>();
+
+        Ok(json!(members_json))
     })
 }
 
@@ -284,6 +323,115 @@ pub fn update_member_profile(update: NewMember, user: User) -> GreaseResult<Valu
     Member::update(&user.member.member.email, true, update, &user.conn).map(|_| basic_success())
 }
 
+/// Get the current member's notification preferences.
+///
+/// Categories the member hasn't set a preference for are filled in with
+/// the defaults.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Return Format:
+///
+/// Returns a list of [NotificationPreference](crate::db::models::NotificationPreference)s,
+/// one for each category of notification.
+pub fn get_notification_preferences(user: User) -> GreaseResult<Value> {
+    NotificationPreference::load_all_for_member(&user.member.member.email, &user.conn)
+        .map(|preferences| json!(preferences))
+}
+
+/// Update the current member's notification preferences.
+///
+/// Only the categories included are changed.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Input Format:
+///
+/// Expects a list of [NotificationPreferenceUpdate](crate::db::models::NotificationPreferenceUpdate)s.
+pub fn update_notification_preferences(
+    updates: Vec<NotificationPreferenceUpdate>,
+    user: User,
+) -> GreaseResult<Value> {
+    NotificationPreference::update_for_member(&user.member.member.email, updates, &user.conn)
+        .map(|_| basic_success())
+}
+
+/// Get the current member's personal calendar subscription.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "url": string,
+///     "created": datetime
+/// }
+/// ```
+///
+/// Returns the `webcal://` URL of the member's calendar feed, or null if
+/// they haven't created one.
+pub fn get_calendar_feed(user: User) -> GreaseResult<Value> {
+    CalendarFeed::load_for_member(&user.member.member.email, &user.conn)
+        .map(|feed| feed.map(|feed| feed.to_json()).unwrap_or(Value::Null))
+}
+
+/// Create a new personal calendar subscription for the current member.
+///
+/// If the member already had one, its old URL stops working.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "url": string,
+///     "created": datetime
+/// }
+/// ```
+pub fn regenerate_calendar_feed(user: User) -> GreaseResult<Value> {
+    CalendarFeed::regenerate(&user.member.member.email, &user.conn).map(|feed| feed.to_json())
+}
+
+/// Revoke the current member's personal calendar subscription.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+pub fn revoke_calendar_feed(user: User) -> GreaseResult<Value> {
+    CalendarFeed::revoke(&user.member.member.email, &user.conn).map(|_| basic_success())
+}
+
+/// Get a member's personal calendar as an iCalendar file.
+///
+/// This is what calendar apps load from the URL given by
+/// [get_calendar_feed](crate::routes::member_routes::get_calendar_feed),
+/// so it doesn't need a login.
+///
+/// ## Path Parameters:
+///   * token: string (*required*) - The secret token of the feed,
+///       optionally followed by ".ics"
+///
+/// ## Return Format:
+///
+/// Returns a "text/calendar" file with every event of the current semester
+/// the member is expected at, including their RSVP status for each.
+pub fn get_calendar_feed_file(token: String) -> GreaseResult<FileResponse> {
+    let conn = connect_to_db()?;
+    let token = token.trim_end_matches(".ics");
+
+    CalendarFeed::build_calendar(token, &conn).map(FileResponse::calendar)
+}
+
 /// Update a member's account as an officer.
 ///
 /// ## Path Parameters:
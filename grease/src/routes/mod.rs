@@ -14,7 +14,7 @@ use crate::auth::User;
 use backtrace::Backtrace;
 use cgi::http::{
     self,
-    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE},
     response,
 };
 use chrono::{Local, NaiveDateTime, TimeZone};
@@ -31,7 +31,8 @@ use std::sync::{Arc, Mutex};
 /// crate handles putting this into a [Request](http::request::Request) from
 /// the [http](http) crate for us.
 ///
-/// This method returns all data as "application/json" responses. On success,
+/// This method returns all data as "application/json" responses, except for
+/// the few routes that return files (see [route_file_request]). On success,
 /// a 200 status code is returned, while all errors return appropriate error
 /// status codes (See [GreaseError](crate::error::GreaseError) for how those
 /// get mapped).
@@ -54,21 +55,30 @@ pub fn handle_request(request: cgi::Request) -> cgi::Response {
             return;
         }
 
-        let (status_code, value) = match route_request(&request) {
-            Ok(resp) => (200, resp),
-            Err(error) => error.as_response(),
+        let (status_code, file) = match route_file_request(&request) {
+            Ok(file) => (200, file),
+            Err(GreaseError::NotFound) => match route_request(&request) {
+                Ok(resp) => (200, FileResponse::json(&resp)),
+                Err(error) => {
+                    let (status_code, value) = error.as_response();
+                    (status_code, FileResponse::json(&value))
+                }
+            },
+            Err(error) => {
+                let (status_code, value) = error.as_response();
+                (status_code, FileResponse::json(&value))
+            }
         };
 
-        let body = serde_json::to_string(&value)
-            .unwrap_or_default()
-            .into_bytes();
+        let disposition = file.content_disposition();
         response = Some(
             response::Builder::new()
                 .status(status_code)
-                .header(CONTENT_TYPE, "application/json")
+                .header(CONTENT_TYPE, file.content_type)
+                .header(CONTENT_DISPOSITION, disposition.as_str())
                 .header("Access-Control-Allow-Origin", "*")
-                .header(CONTENT_LENGTH, body.len().to_string().as_str())
-                .body(body)
+                .header(CONTENT_LENGTH, file.body.len().to_string().as_str())
+                .body(file.body)
                 .unwrap(),
         );
     }))
@@ -103,6 +113,107 @@ fn parse_body<'de, T: Deserialize<'de>>(body: &'de [u8]) -> GreaseResult<T> {
     serde_json::from_slice(body).map_err(|err| GreaseError::BadRequest(err.to_string()))
 }
 
+/// Parse a body that may be left empty, in which case the default is used.
+fn parse_optional_body<'de, T: Deserialize<'de> + Default>(body: &'de [u8]) -> GreaseResult<T> {
+    if body.iter().all(|byte| byte.is_ascii_whitespace()) {
+        Ok(T::default())
+    } else {
+        parse_body(body)
+    }
+}
+
+/// A response that isn't JSON, like a calendar or a spreadsheet.
+pub struct FileResponse {
+    pub content_type: &'static str,
+    /// If set, the file is downloaded under this name instead of shown
+    pub file_name: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl FileResponse {
+    pub fn json(value: &Value) -> FileResponse {
+        FileResponse {
+            content_type: "application/json",
+            file_name: None,
+            body: serde_json::to_string(value)
+                .unwrap_or_default()
+                .into_bytes(),
+        }
+    }
+
+    pub fn calendar(calendar: String) -> FileResponse {
+        FileResponse {
+            content_type: "text/calendar; charset=utf-8",
+            file_name: None,
+            body: calendar.into_bytes(),
+        }
+    }
+
+    /// The `Content-Disposition` header for the file.
+    ///
+    /// The file name is restricted to printable ASCII without quotes or
+    /// backslashes, so it can't break out of the header.
+    pub fn content_disposition(&self) -> String {
+        match &self.file_name {
+            Some(file_name) => {
+                let safe_name = file_name
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_graphic() && c != '"' && c != '\\' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect::<String>();
+
+                format!("attachment; filename=\"{}\"", safe_name)
+            }
+            None => "inline".to_owned(),
+        }
+    }
+}
+
+/// Handles routing of requests for files, which don't return JSON.
+///
+/// Requests that don't match any of these routes are handled by
+/// [route_request] instead.
+pub fn route_file_request(request: &cgi::Request) -> GreaseResult<FileResponse> {
+    use routes::event_routes::*;
+    use routes::member_routes::*;
+
+    let load_user = || crate::auth::User::from_request(request);
+
+    router!(request,
+        (GET) [/calendar_feeds/(token: String)] =>
+            |token| get_calendar_feed_file(token),
+
+        (GET) [/events/(id: i32)/attendance/export?(format: String)?(section: String)?(enrollment: String)] =>
+            |id, format, section, enrollment| export_event_attendance(id, format, section, enrollment, load_user()?),
+
+        (GET) [/attendance/export?(semester: String)?(format: String)?(section: String)?(enrollment: String)] =>
+            |semester, format, section, enrollment| export_semester_attendance(semester, format, section, enrollment, load_user()?),
+
+        (GET) [/grades/export?(semester: String)?(format: String)?(section: String)?(enrollment: String)] =>
+            |semester, format, section, enrollment| export_grades(semester, format, section, enrollment, load_user()?),
+
+        (GET) [/semesters/(name: String)/final_grades/export] =>
+            |name| export_final_grades(name, load_user()?),
+
+        (GET) [/"public_events.ics"] =>
+            || get_public_events_feed("public_events.ics"),
+
+        (GET) [/"public_events.rss"] =>
+            || get_public_events_feed("public_events.rss"),
+
+        (GET) [/"public_events.atom"] =>
+            || get_public_events_feed("public_events.atom"),
+
+        (GET) [/"public_events.jsonld"] =>
+            || get_public_events_feed("public_events.jsonld"),
+    )
+}
+
 /// Handles routing of incoming requests.
 ///
 /// See the root of the crate for the API layout and
@@ -134,6 +245,9 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (GET) [/user] =>
             || get_current_user(load_user()),
 
+        (POST) [/user/grades/simulate] =>
+            || simulate_grades(parse_body(&request.body())?, load_user()?),
+
         (GET) [/members/(email: String)?(grades: bool)?(details: bool)] =>
             |email, grades, details| get_member(email, grades, details, load_user()?),
 
@@ -158,6 +272,21 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (POST) [/members/profile] =>
             || update_member_profile(parse_body(&request.body())?, load_user()?),
 
+        (GET) [/notification_preferences] =>
+            || get_notification_preferences(load_user()?),
+
+        (POST) [/notification_preferences] =>
+            || update_notification_preferences(parse_body(&request.body())?, load_user()?),
+
+        (GET) [/calendar_feed] =>
+            || get_calendar_feed(load_user()?),
+
+        (POST) [/calendar_feed] =>
+            || regenerate_calendar_feed(load_user()?),
+
+        (DELETE) [/calendar_feed] =>
+            || revoke_calendar_feed(load_user()?),
+
         (POST) [/members/(email: String)] =>
             |email| update_member_as_officer(email, parse_body(&request.body())?, load_user()?),
 
@@ -171,17 +300,23 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (GET) [/events/(id: i32)] =>
             |id| get_event(id, load_user()?),
 
+        (GET) [/events/conflicts?(semester: String)] =>
+            |semester| get_event_conflicts(semester, load_user()?),
+
         (GET) [/events?(full: bool)] =>
             |full| get_events(full, load_user()?),
 
         (POST) [/events] =>
             || new_event(parse_body(&request.body())?, load_user()?),
 
-        (POST) [/events/(id: i32)] =>
-            |id| update_event(id, parse_body(&request.body())?, load_user()?),
+        (POST) [/events/import] =>
+            || import_events(parse_body(&request.body())?, load_user()?),
+
+        (POST) [/events/(id: i32)?(scope: String)] =>
+            |id, scope| update_event(id, scope, parse_body(&request.body())?, load_user()?),
 
-        (DELETE) [/events/(id: i32)] =>
-            |id| delete_event(id, load_user()?),
+        (DELETE) [/events/(id: i32)?(scope: String)] =>
+            |id, scope| delete_event(id, scope, load_user()?),
 
         (GET) [/public_events] =>
             || get_public_events(),
@@ -199,15 +334,42 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (GET) [/events/(id: i32)/attendance/(member: String)] =>
             |id, email| get_member_attendance(id, email, load_user()?),
 
+        (GET) [/events/(id: i32)/attendance/(member: String)/grade_disputes] =>
+            |id, email| get_attendance_grade_disputes(id, email, load_user()?),
+
+        (POST) [/events/(id: i32)/attendance] =>
+            |id| update_attendance_in_bulk(id, parse_body(&request.body())?, load_user()?),
+
+        (POST) [/events/(id: i32)/attendance/import] =>
+            |id| import_attendance(id, parse_body(&request.body())?, load_user()?),
+
         (POST) [/events/(id: i32)/attendance/(member: String)] =>
             |id, member| update_attendance(id, member, parse_body(&request.body())?, load_user()?),
 
+        (POST) [/events/(id: i32)/grade_disputes] =>
+            |id| submit_grade_dispute(id, parse_body(&request.body())?, load_user()?),
+
         (POST) [/events/(id: i32)/rsvp/(attending: bool)] =>
             |id, attending| rsvp_for_event(id, attending, load_user()?),
 
         (POST) [/events/(id: i32)/confirm] =>
             |id| confirm_for_event(id, load_user()?),
 
+        (POST) [/events/(id: i32)/check_in_window] =>
+            |id| open_check_in(id, parse_optional_body(&request.body())?, load_user()?),
+
+        (GET) [/events/(id: i32)/check_in_window] =>
+            |id| get_check_in_code(id, load_user()?),
+
+        (DELETE) [/events/(id: i32)/check_in_window] =>
+            |id| close_check_in(id, load_user()?),
+
+        (POST) [/events/(id: i32)/check_in] =>
+            |id| check_in_to_event(id, parse_body(&request.body())?, load_user()?),
+
+        (GET) [/events/(id: i32)/not_checked_in] =>
+            |id| get_not_checked_in(id, load_user()?),
+
         (POST) [/events/(id: i32)/attendance/excuse_unconfirmed] =>
             |id| excuse_unconfirmed_for_event(id, load_user()?),
 
@@ -234,14 +396,30 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
             |id| member_is_excused(id, load_user()?),
 
         (POST) [/absence_requests/(event_id: i32)/(member: String)/approve] =>
-            |id, member| approve_absence_request(id, member, load_user()?),
+            |id, member| approve_absence_request(id, member, parse_optional_body(&request.body())?, load_user()?),
 
         (POST) [/absence_requests/(event_id: i32)/(member: String)/deny] =>
-            |id, member| deny_absence_request(id, member, load_user()?),
+            |id, member| deny_absence_request(id, member, parse_optional_body(&request.body())?, load_user()?),
 
         (POST) [/absence_requests/(event_id: i32)] =>
             |id| submit_absence_request(id, parse_body(&request.body())?, load_user()?),
 
+        // grade disputes
+        (GET) [/grade_disputes?(all: bool)] =>
+            |all| get_grade_disputes(all, load_user()?),
+
+        (GET) [/grade_disputes/(id: i32)] =>
+            |id| get_grade_dispute(id, load_user()?),
+
+        (POST) [/grade_disputes/(id: i32)/comment] =>
+            |id| comment_on_grade_dispute(id, parse_body(&request.body())?, load_user()?),
+
+        (POST) [/grade_disputes/(id: i32)/resolve] =>
+            |id| resolve_grade_dispute(id, parse_body(&request.body())?, load_user()?),
+
+        (POST) [/grade_disputes/(id: i32)/reject] =>
+            |id| reject_grade_dispute(id, parse_body(&request.body())?, load_user()?),
+
         // gig requests
         (GET) [/gig_requests/(id: i32)] =>
             |id| get_gig_request(id, load_user()?),
@@ -275,8 +453,8 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (GET) [/announcements/(id: i32)] =>
             |id| get_announcement(id, load_user()?),
 
-        (GET) [/announcements?(all: bool)] =>
-            |all| get_announcements(all, load_user()?),
+        (GET) [/announcements?(all: bool)?(unread: bool)] =>
+            |all, unread| get_announcements(all, unread, load_user()?),
 
         (POST) [/announcements] =>
             || make_new_announcement(parse_body(&request.body())?, load_user()?),
@@ -284,6 +462,9 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (POST) [/announcements/(id: i32)/archive] =>
             |id| archive_announcement(id, load_user()?),
 
+        (POST) [/announcements/(id: i32)/read] =>
+            |id| mark_announcement_read(id, load_user()?),
+
         // google docs
         (GET) [/google_docs/(name: String)] =>
             |name| get_google_doc(name, load_user()?),
@@ -319,6 +500,32 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (DELETE) [/meeting_minutes/(id: i32)] =>
             |id| delete_meeting_minutes(id, load_user()?),
 
+        // email outbox
+        (GET) [/email_outbox?(status: String)] =>
+            |status| get_email_outbox(status, load_user()?),
+
+        (POST) [/email_outbox/(id: i32)/retry] =>
+            |id| retry_queued_email(id, load_user()?),
+
+        // webhooks
+        (GET) [/webhooks] =>
+            || get_webhooks(load_user()?),
+
+        (POST) [/webhooks] =>
+            || new_webhook(parse_body(&request.body())?, load_user()?),
+
+        (POST) [/webhooks/(id: i32)] =>
+            |id| update_webhook(id, parse_body(&request.body())?, load_user()?),
+
+        (DELETE) [/webhooks/(id: i32)] =>
+            |id| delete_webhook(id, load_user()?),
+
+        (GET) [/webhooks/(id: i32)/deliveries?(status: String)] =>
+            |id, status| get_webhook_deliveries(id, status, load_user()?),
+
+        (POST) [/webhook_deliveries/(id: i32)/retry] =>
+            |id| retry_webhook_delivery(id, load_user()?),
+
         // uniforms
         (GET) [/uniforms/(id: i32)] =>
             |id| get_uniform(id, load_user()?),
@@ -405,6 +612,24 @@ pub fn route_request(request: &cgi::Request) -> GreaseResult<Value> {
         (POST) [/semesters/(name: String)/set_current] =>
             |name| set_current_semester(name, load_user()?),
 
+        (GET) [/semesters/(name: String)/grading_policy] =>
+            |name| get_grading_policy(name, load_user()?),
+
+        (POST) [/semesters/(name: String)/grading_policy] =>
+            |name| set_grading_policy(name, parse_body(&request.body())?, load_user()?),
+
+        (DELETE) [/semesters/(name: String)/grading_policy] =>
+            |name| reset_grading_policy(name, load_user()?),
+
+        (GET) [/semesters/(name: String)/final_grades] =>
+            |name| get_final_grades(name, load_user()?),
+
+        (POST) [/semesters/(name: String)/finalize] =>
+            |name| finalize_semester(name, load_user()?),
+
+        (POST) [/semesters/(name: String)/reopen] =>
+            |name| reopen_semester(name, parse_body(&request.body())?, load_user()?),
+
         (DELETE) [/semesters/(name: String)?(confirm: bool)] =>
             |name, confirm| delete_semester(name, confirm, load_user()?),
 
@@ -497,12 +722,12 @@ struct Since {
     pub timestamp: i64,
 }
 
-fn send_emails(since: Since, _user: User) -> GreaseResult<Value> {
+fn send_emails(since: Since, user: User) -> GreaseResult<Value> {
     let since_time = Local
         .from_utc_datetime(&NaiveDateTime::from_timestamp(since.timestamp / 1000, 0))
         .naive_local();
 
-    crate::cron::send_event_emails(Some(since_time))?;
+    crate::jobs::send_event_emails_between(&since_time, &Local::now().naive_local(), &user.conn)?;
 
     Ok(basic_success())
 }
@@ -530,3 +755,32 @@ pub fn basic_success() -> Value {
 pub fn id_json(id: i32) -> Value {
     json!({ "id": id })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(file_name: &str) -> FileResponse {
+        FileResponse {
+            content_type: "text/csv; charset=utf-8",
+            file_name: Some(file_name.to_owned()),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn file_names_cannot_break_out_of_the_disposition_header() {
+        assert_eq!(
+            download("fall_2019.csv").content_disposition(),
+            "attachment; filename=\"fall_2019.csv\""
+        );
+        assert_eq!(
+            download("a\"; filename=\"b\\c.csv").content_disposition(),
+            "attachment; filename=\"a_;_filename=_b_c.csv\""
+        );
+        assert_eq!(
+            download("caf\u{e9}\r\n.csv").content_disposition(),
+            "attachment; filename=\"caf___.csv\""
+        );
+    }
+}
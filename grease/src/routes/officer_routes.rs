@@ -3,6 +3,7 @@
 use super::basic_success;
 use crate::check_for_permission;
 use auth::*;
+use chrono::Local;
 use db::schema::*;
 use db::*;
 use diesel::prelude::*;
@@ -25,10 +26,16 @@ pub fn get_announcement(id: i32, user: User) -> GreaseResult<Value> {
     Announcement::load(id, &user.conn).map(|announcement| json!(announcement))
 }
 
-/// Get all announcements that aren't archived for the current semester.
+/// Get all announcements for the current semester that are shown to the current member.
+///
+/// Announcements that are archived, scheduled for later, expired, or targeted
+/// at other members are left out, except for members that can
+/// "edit-announcements", who get every unarchived announcement so they can
+/// manage them.
 ///
 /// ## Query Parameters:
 ///   * all: boolean (*optional*) - Simply return all announcements ever made
+///   * unread: boolean (*optional*) - Only return announcements the member hasn't read
 ///
 /// ## Required Permissions:
 ///
@@ -36,19 +43,42 @@ pub fn get_announcement(id: i32, user: User) -> GreaseResult<Value> {
 ///
 /// ## Return Format:
 ///
-/// Returns a list of [Announcement](crate::db::models::Announcement)s.
-pub fn get_announcements(all: Option<bool>, user: User) -> GreaseResult<Value> {
+/// If `all` is set, returns a list of [Announcement](crate::db::models::Announcement)s.
+/// Otherwise, each announcement also has a boolean "read" field for whether
+/// the current member has marked it as read.
+pub fn get_announcements(
+    all: Option<bool>,
+    unread: Option<bool>,
+    user: User,
+) -> GreaseResult<Value> {
     if all.unwrap_or(false) {
         Announcement::load_all(&user.conn).map(|announcements| json!(announcements))
     } else {
         let current_semester = Semester::load_current(&user.conn)?;
-        Announcement::load_all_for_semester(&current_semester.name, &user.conn)
-            .map(|announcements| json!(announcements))
+        let mut announcements = if user.has_permission("edit-announcements", None) {
+            Announcement::load_all_for_member(
+                &user.member.member.email,
+                &current_semester.name,
+                &user.conn,
+            )?
+        } else {
+            Announcement::load_visible_for_member(&user.member, &current_semester.name, &user.conn)?
+        };
+        if unread.unwrap_or(false) {
+            announcements.retain(|announcement| !announcement.read);
+        }
+
+        Ok(json!(announcements))
     }
 }
 
 /// Make a new announcement.
 ///
+/// If the announcement is set to be emailed and is published immediately,
+/// it is emailed to its audience right away. Otherwise, it will be emailed
+/// by the `publish-announcements` job once it is published. Announcements
+/// for everyone are sent to webhooks the same way; targeted ones never are.
+///
 /// ## Required Permissions:
 ///
 /// The user must be logged in and be able to "edit-announcements" generally.
@@ -56,16 +86,47 @@ pub fn get_announcements(all: Option<bool>, user: User) -> GreaseResult<Value> {
 /// ## Input Format:
 ///
 /// Expects a [NewAnnouncement](crate::db::models::NewAnnouncement).
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "id": integer
+/// }
+/// ```
 pub fn make_new_announcement(new_announcement: NewAnnouncement, user: User) -> GreaseResult<Value> {
     check_for_permission!(user => "edit-announcements");
     let current_semester = Semester::load_current(&user.conn)?;
-    Announcement::insert(
-        &new_announcement.content,
+    let new_id = Announcement::insert(
+        &new_announcement,
         &user.member.member.email,
         &current_semester.name,
         &user.conn,
-    )
-    .map(|new_id| json!({ "id": new_id }))
+    )?;
+
+    let announcement = Announcement::load(new_id, &user.conn)?;
+    let now = Local::now().naive_local();
+    if announcement.should_post_at(now) {
+        announcement.post_to_webhooks(&user.conn)?;
+    }
+    if announcement.send_email && announcement.is_visible_at(now) {
+        announcement.email_audience(&user.conn)?;
+    }
+
+    Ok(json!({ "id": new_id }))
+}
+
+/// Mark an announcement as read by the current member.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the announcement
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+pub fn mark_announcement_read(announcement_id: i32, user: User) -> GreaseResult<Value> {
+    Announcement::mark_read(announcement_id, &user.member.member.email, &user.conn)
+        .map(|_| basic_success())
 }
 
 /// Archive an announcement.
@@ -280,6 +341,9 @@ pub fn mark_todo_as_complete(todo_id: i32, user: User) -> GreaseResult<Value> {
 
 /// Send a meeting minutes as an email to the officer's list.
 ///
+/// The email is queued in the outbox and delivered by the next run of the
+/// `deliver-emails` job.
+///
 /// ## Path Parameters:
 ///   * id: integer (*required*) - The ID of the meeting minutes
 ///
@@ -299,10 +363,10 @@ pub fn send_minutes_as_email(minutes_id: i32, user: User) -> GreaseResult<Value>
         ))?
         .value;
 
-    let email = crate::util::Email {
+    let email = crate::email::Email::new(
+        officer_email,
         subject,
-        to_address: officer_email,
-        content: format!(
+        format!(
             "\
             <div>\
                 <p>Notes from the meeting \"{}\" on \"{}\":</p>\
@@ -313,9 +377,9 @@ pub fn send_minutes_as_email(minutes_id: i32, user: User) -> GreaseResult<Value>
         ",
             minutes.name, date, content
         ),
-    };
+    );
 
-    email.send().map(|_| basic_success())
+    email.queue(&user.conn).map(|_| basic_success())
 }
 
 /// Delete a meeting's minutes.
@@ -331,6 +395,175 @@ pub fn delete_meeting_minutes(id: i32, user: User) -> GreaseResult<Value> {
     MeetingMinutes::delete(id, &user.conn).map(|_| basic_success())
 }
 
+/// Get the emails in the outbox, most recently queued first.
+///
+/// ## Query Parameters:
+///   * status: string (*optional*) - Only return emails that are
+///       "pending", "sent", or "failed"
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "view-email-outbox" generally.
+///
+/// ## Return Format:
+///
+/// Returns a list of [OutboxEmail](crate::db::models::OutboxEmail)s.
+pub fn get_email_outbox(status: Option<String>, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "view-email-outbox");
+
+    let status = match status.as_ref().map(|status| status.as_str()) {
+        None => None,
+        Some("pending") => Some(EmailStatus::Pending),
+        Some("sent") => Some(EmailStatus::Sent),
+        Some("failed") => Some(EmailStatus::Failed),
+        Some(other) => {
+            return Err(GreaseError::BadRequest(format!(
+                "\"{}\" is not an email status (expected \"pending\", \"sent\", or \"failed\").",
+                other
+            )))
+        }
+    };
+
+    OutboxEmail::load_all(status, &user.conn).map(|emails| json!(emails))
+}
+
+/// Put an email that failed to send back into the outbox to be retried.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the queued email
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-email-outbox" generally.
+pub fn retry_queued_email(id: i32, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-email-outbox");
+    OutboxEmail::retry(id, &user.conn).map(|_| basic_success())
+}
+
+/// Get all webhooks along with the kinds of things they are subscribed to.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+///
+/// ## Return Format:
+///
+/// Returns a list of [Webhook](crate::db::models::Webhook)s, each with an
+/// additional field `kinds` listing what the webhook is subscribed to.
+pub fn get_webhooks(user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+    Webhook::load_all(&user.conn).map(|webhooks| json!(webhooks))
+}
+
+/// Register a new webhook.
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+///
+/// ## Input Format:
+///
+/// Expects a [NewWebhook](crate::db::models::NewWebhook).
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "id": integer,
+///     "secret": string
+/// }
+/// ```
+///
+/// Returns an object containing the id of the newly created webhook and the
+/// secret its deliveries are signed with. This is the only time the secret
+/// is returned, so it should be saved by whoever set up the webhook.
+pub fn new_webhook(new_webhook: NewWebhook, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+    Webhook::create(new_webhook, &user.conn)
+        .map(|(id, secret)| json!({ "id": id, "secret": secret }))
+}
+
+/// Update a webhook.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the webhook
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+///
+/// ## Input Format:
+///
+/// Expects a [NewWebhook](crate::db::models::NewWebhook). The secret is
+/// left alone if none is given.
+pub fn update_webhook(id: i32, updated_webhook: NewWebhook, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+    Webhook::update(id, updated_webhook, &user.conn).map(|_| basic_success())
+}
+
+/// Delete a webhook along with its delivery history.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the webhook
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+pub fn delete_webhook(id: i32, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+    Webhook::delete(id, &user.conn).map(|_| basic_success())
+}
+
+/// Get the deliveries made to a webhook, most recently queued first.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the webhook
+///
+/// ## Query Parameters:
+///   * status: string (*optional*) - Only return deliveries that are
+///       "pending", "delivered", or "failed"
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+///
+/// ## Return Format:
+///
+/// Returns a list of [WebhookDelivery](crate::db::models::WebhookDelivery)s.
+pub fn get_webhook_deliveries(id: i32, status: Option<String>, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+
+    let status = match status.as_ref().map(|status| status.as_str()) {
+        None => None,
+        Some("pending") => Some(WebhookDeliveryStatus::Pending),
+        Some("delivered") => Some(WebhookDeliveryStatus::Delivered),
+        Some("failed") => Some(WebhookDeliveryStatus::Failed),
+        Some(other) => {
+            return Err(GreaseError::BadRequest(format!(
+            "\"{}\" is not a delivery status (expected \"pending\", \"delivered\", or \"failed\").",
+            other
+        )))
+        }
+    };
+
+    WebhookDelivery::load_for_webhook(id, status, &user.conn).map(|deliveries| json!(deliveries))
+}
+
+/// Queue a failed or pending webhook delivery to be sent again on the next run.
+///
+/// Deliveries that already went through can't be retried.
+///
+/// ## Path Parameters:
+///   * id: integer (*required*) - The ID of the delivery
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "manage-webhooks" generally.
+pub fn retry_webhook_delivery(id: i32, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "manage-webhooks");
+    WebhookDelivery::retry(id, &user.conn).map(|_| basic_success())
+}
+
 /// Get a single uniform.
 ///
 /// ## Path Parameters:
@@ -527,6 +760,162 @@ pub fn edit_semester(
     Semester::update(&name, &updated_semester, &user.conn).map(|_| basic_success())
 }
 
+/// Get the policy that grades are calculated by during a semester.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "policy": GradingPolicy,
+///     "rules": [GradingRule],
+///     "isDefault": boolean
+/// }
+/// ```
+///
+/// Returns the semester's [GradingPolicy](crate::db::models::GradingPolicy)
+/// and a [GradingRule](crate::db::models::GradingRule) for each event type
+/// with one. `isDefault` is true if the semester hasn't set its own policy.
+pub fn get_grading_policy(name: String, user: User) -> GreaseResult<Value> {
+    let semester = Semester::load(&name, &user.conn)?;
+    GradingPolicy::load_for_semester(&semester.name, &user.conn).map(|policy| policy.to_json())
+}
+
+/// Set the policy that grades are calculated by during a semester.
+///
+/// Finalized semesters keep their policy until they are reopened.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-semester" generally.
+///
+/// ## Input Format:
+///
+/// Expects a [GradingPolicyForm](crate::db::models::GradingPolicyForm).
+pub fn set_grading_policy(
+    name: String,
+    policy_form: GradingPolicyForm,
+    user: User,
+) -> GreaseResult<Value> {
+    check_for_permission!(user => "edit-semester");
+    GradingPolicy::set(&name, policy_form, &user.conn).map(|_| basic_success())
+}
+
+/// Go back to grading a semester by the default policy.
+///
+/// Finalized semesters keep their policy until they are reopened.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-semester" generally.
+pub fn reset_grading_policy(name: String, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "edit-semester");
+    GradingPolicy::reset(&name, &user.conn).map(|_| basic_success())
+}
+
+/// Get the final grades of a semester, if it has been finalized.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "view-attendance" generally.
+///
+/// ## Return Format:
+///
+/// ```json
+/// {
+///     "finalization": SemesterFinalization?,
+///     "finalGrades": [FinalGrade],
+///     "history": [FinalizationLog]
+/// }
+/// ```
+///
+/// `finalization` is null if the semester isn't finalized right now. The
+/// history of it being finalized and reopened starts with the most recent.
+/// See [SemesterFinalization](crate::db::models::SemesterFinalization),
+/// [FinalGrade](crate::db::models::FinalGrade), and
+/// [FinalizationLog](crate::db::models::FinalizationLog) for their formats.
+pub fn get_final_grades(name: String, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "view-attendance");
+    let semester = Semester::load(&name, &user.conn)?;
+    let finalization = SemesterFinalization::load(&semester.name, &user.conn)?;
+    let final_grades = FinalGrade::load_for_semester(&semester.name, &user.conn)?
+        .into_iter()
+        .map(|(final_grade, _member)| final_grade.to_json())
+        .collect::<Vec<_>>();
+    let history = FinalizationLog::load_for_semester(&semester.name, &user.conn)?;
+
+    Ok(json!({
+        "finalization": finalization,
+        "finalGrades": final_grades,
+        "history": history,
+    }))
+}
+
+/// Finalize a semester's grades.
+///
+/// The grades of every member enrolled in the class are saved as they are
+/// now. Until the semester is reopened, its events, attendance, RSVPs,
+/// check-ins, absence requests, sections, and grading policy can't be
+/// changed, calendars can't be imported into it, and members' grades are
+/// the saved ones.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-semester" generally.
+///
+/// ## Return Format:
+///
+/// Returns a list of the [FinalGrade](crate::db::models::FinalGrade)s that were saved.
+pub fn finalize_semester(name: String, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "edit-semester");
+    SemesterFinalization::finalize(&name, &user.member.member.email, &user.conn).map(
+        |final_grades| {
+            json!(final_grades
+                .iter()
+                .map(|final_grade| final_grade.to_json())
+                .collect::<Vec<_>>())
+        },
+    )
+}
+
+/// Reopen a finalized semester so that its attendance can be changed.
+///
+/// Who reopened the semester and why are recorded in its history.
+///
+/// ## Path Parameters:
+///   * name: string (*required*) - The name of the semester
+///
+/// ## Required Permissions:
+///
+/// The user must be logged in and be able to "edit-semester" generally.
+///
+/// ## Input Format:
+///
+/// Expects a [ReopenSemesterForm](crate::db::models::ReopenSemesterForm).
+pub fn reopen_semester(name: String, form: ReopenSemesterForm, user: User) -> GreaseResult<Value> {
+    check_for_permission!(user => "edit-semester");
+    SemesterFinalization::reopen(&name, &user.member.member.email, &form.reason, &user.conn)
+        .map(|_| basic_success())
+}
+
 /// Delete a semester from the site permanently.
 ///
 /// WARNING! This is a permanent action, and cannot be undone. Make sure that
@@ -29,6 +29,9 @@
 /// "/foo/3?full=true" or "/foo/6" (an i32 "id" is required and a "full" query
 /// parameter is optionally accepted).
 ///
+/// Path segments that aren't valid identifiers, like file names, can be
+/// matched by quoting them, as in `[/"feed.ics"]`.
+///
 /// The routes are processed in the order written, and the first one matched has
 /// the callback called with the extracted parameters. If none are matched, an
 /// `Err(GreaseError::NotFound)` is returned.
@@ -72,6 +75,14 @@ macro_rules! router {
             }
         }
     );
+    // quoted segment check
+    (@check $segment_iter:ident, $params:expr, $callback:expr, ( $( $f_args:expr, )* ), /$p:literal $($rest:tt)*) => (
+        if let Some(next_segment) = $segment_iter.next() {
+            if next_segment == $p {
+                router!(@check $segment_iter, $params, $callback, ( $( $f_args, )* ), $( $rest )*);
+            }
+        }
+    );
     // terminal check
     (@check $segment_iter:ident, $params:expr, $callback:expr, ( $( $f_args:expr, )* ), ) => (
         if $segment_iter.next().is_none() {
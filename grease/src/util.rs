@@ -9,60 +9,8 @@ use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
 use std::str::FromStr;
 
-pub struct Email {
-    pub to_address: String,
-    pub subject: String,
-    pub content: String,
-}
-
-impl Email {
-    pub const DEFAULT_NAME: &'static str = "Glee Club Officers";
-    pub const DEFAULT_ADDRESS: &'static str = "gleeclub_officers@lists.gatech.edu";
-
-    pub fn send(&self) -> GreaseResult<()> {
-        let mut mail = Command::new("mail")
-            .args(&["-s", &self.subject, &self.to_address])
-            .stdin(Stdio::piped())
-            .spawn()
-            .map_err(|err| {
-                GreaseError::ServerError(format!("Couldn't run mail to send an email: {}", err))
-            })?;
-        mail.stdin
-            .as_mut()
-            .ok_or(GreaseError::ServerError(
-                "No stdin was available for mail.".to_owned(),
-            ))?
-            .write_all(self.content.to_string().as_bytes())
-            .map_err(|err| {
-                GreaseError::ServerError(format!("Couldn't send an email with mail: {}", err))
-            })?;
-        let output = mail.wait_with_output().map_err(|err| {
-            GreaseError::ServerError(format!(
-                "The output of the mail command couldn't be retrieved: {}",
-                err
-            ))
-        })?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            let error_message = std::str::from_utf8(&output.stderr).map_err(|_err| {
-                GreaseError::ServerError(
-                    "mail errored out with a non-utf8 error message.".to_owned(),
-                )
-            })?;
-            Err(GreaseError::ServerError(format!(
-                "mail failed to send an email with error code {}: {}",
-                output.status.code().unwrap_or(1),
-                error_message
-            )))
-        }
-    }
-}
-
 #[derive(Deserialize)]
 pub struct FileUpload {
     pub path: String,
@@ -0,0 +1,139 @@
+//! Outgoing webhooks.
+//!
+//! Whenever something a webhook is subscribed to happens, a delivery is queued
+//! in the [delivery log](crate::db::WebhookDelivery) and later sent by the
+//! `deliver-webhooks` [job](crate::jobs) as a POST request with a JSON body:
+//!
+//! ```json
+//! {
+//!     "kind": string, // e.g. "EventCreated"
+//!     "time": datetime,
+//!     "data": object // the affected model
+//! }
+//! ```
+//!
+//! Each request carries the following headers:
+//!   * `X-Grease-Kind`: The kind of the delivery, the same as in the body.
+//!   * `X-Grease-Delivery`: The ID of the delivery, which is the same across retries.
+//!   * `X-Grease-Signature`: `sha256=` followed by the hex-encoded HMAC-SHA256 of the
+//!       body, keyed with the webhook's secret. Receivers should check this before
+//!       trusting the request.
+//!
+//! Any 2xx response counts as delivered. Anything else is retried with
+//! exponential backoff.
+
+use error::{GreaseError, GreaseResult};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+#[cfg(test)]
+use std::sync::Mutex;
+
+pub const KIND_HEADER: &'static str = "X-Grease-Kind";
+pub const DELIVERY_HEADER: &'static str = "X-Grease-Delivery";
+pub const SIGNATURE_HEADER: &'static str = "X-Grease-Signature";
+
+/// Sign a request body with a webhook's secret, for the signature header.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.input(body.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.result().code()))
+}
+
+/// A way of sending webhook requests.
+pub trait WebhookClient {
+    /// POST the body to the URL, returning the status code of the response.
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> GreaseResult<u16>;
+}
+
+/// Sends webhook requests over HTTP.
+pub struct HttpClient {
+    /// How long to wait to connect and for a response, in milliseconds
+    pub timeout: u64,
+}
+
+impl HttpClient {
+    pub fn new() -> HttpClient {
+        HttpClient { timeout: 10_000 }
+    }
+}
+
+impl WebhookClient for HttpClient {
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> GreaseResult<u16> {
+        let mut request = ureq::post(url);
+        request
+            .timeout_connect(self.timeout)
+            .timeout_read(self.timeout)
+            .set("Content-Type", "application/json");
+        for (name, value) in headers {
+            request.set(name, value);
+        }
+
+        let response = request.send_string(body);
+        if let Some(error) = response.synthetic_error() {
+            Err(GreaseError::ServerError(format!(
+                "Couldn't send a webhook request to {}: {}",
+                url, error
+            )))
+        } else {
+            Ok(response.status())
+        }
+    }
+}
+
+/// A request received by a [MemoryClient](crate::webhook::MemoryClient).
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Keeps every request it is given in memory instead of sending it, and
+/// answers them all with the same status.
+///
+/// Useful as a stand-in for a real receiver when exercising webhook code.
+#[cfg(test)]
+pub struct MemoryClient {
+    pub status: u16,
+    pub received: Mutex<Vec<ReceivedRequest>>,
+}
+
+#[cfg(test)]
+impl MemoryClient {
+    pub fn new(status: u16) -> MemoryClient {
+        MemoryClient {
+            status,
+            received: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// All requests received so far, oldest first.
+    pub fn received_requests(&self) -> Vec<ReceivedRequest> {
+        self.received
+            .lock()
+            .map(|received| received.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl WebhookClient for MemoryClient {
+    fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> GreaseResult<u16> {
+        self.received
+            .lock()
+            .map_err(|_err| GreaseError::ServerError("The webhook log was poisoned.".to_owned()))?
+            .push(ReceivedRequest {
+                url: url.to_owned(),
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+                body: body.to_owned(),
+            });
+
+        Ok(self.status)
+    }
+}
@@ -0,0 +1,3 @@
+DELETE FROM permission WHERE name = 'view-email-outbox';
+DROP TABLE IF EXISTS email_attachment CASCADE;
+DROP TABLE IF EXISTS email_outbox CASCADE;
@@ -0,0 +1,29 @@
+CREATE TABLE email_outbox (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  to_address varchar(255) NOT NULL,
+  subject varchar(255) NOT NULL,
+  html longtext NOT NULL,
+  `text` longtext DEFAULT NULL,
+  reply_to varchar(255) DEFAULT NULL,
+  status enum('pending', 'sent', 'failed') NOT NULL DEFAULT 'pending',
+  attempts int NOT NULL DEFAULT '0',
+  last_error text DEFAULT NULL,
+  created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  next_attempt datetime NOT NULL,
+  sent_at datetime DEFAULT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE email_attachment (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  email int NOT NULL,
+  file_name varchar(255) NOT NULL,
+  content_type varchar(100) NOT NULL,
+  content longblob NOT NULL,
+
+  FOREIGN KEY (email) REFERENCES email_outbox (id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+INSERT INTO permission (name, description) VALUES
+  ('view-email-outbox', 'View the status of outgoing emails and retry failed ones');
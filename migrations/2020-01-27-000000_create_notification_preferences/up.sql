@@ -0,0 +1,9 @@
+CREATE TABLE notification_preference (
+  member varchar(50) NOT NULL,
+  category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes') NOT NULL,
+  channel enum('email', 'none') NOT NULL DEFAULT 'email',
+  lead_time int NOT NULL DEFAULT '0', -- hours
+
+  PRIMARY KEY (member, category),
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
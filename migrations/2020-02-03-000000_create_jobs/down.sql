@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS job_run CASCADE;
+DROP TABLE IF EXISTS job CASCADE;
@@ -0,0 +1,18 @@
+CREATE TABLE job (
+  name varchar(50) NOT NULL PRIMARY KEY,
+  last_success datetime DEFAULT NULL,
+  locked_until datetime DEFAULT NULL,
+  lock_token varchar(36) DEFAULT NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE job_run (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  job varchar(50) NOT NULL,
+  started datetime NOT NULL,
+  finished datetime DEFAULT NULL,
+  status enum('running', 'succeeded', 'failed') NOT NULL DEFAULT 'running',
+  output text DEFAULT NULL,
+
+  FOREIGN KEY (job) REFERENCES job (name) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
@@ -0,0 +1,12 @@
+DROP TABLE IF EXISTS announcement_read CASCADE;
+
+ALTER TABLE announcement
+  DROP FOREIGN KEY announcement_ibfk_3,
+  DROP FOREIGN KEY announcement_ibfk_4,
+  DROP COLUMN publish_at,
+  DROP COLUMN expires_at,
+  DROP COLUMN section,
+  DROP COLUMN enrollment,
+  DROP COLUMN role,
+  DROP COLUMN send_email,
+  DROP COLUMN emailed;
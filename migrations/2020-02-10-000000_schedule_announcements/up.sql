@@ -0,0 +1,21 @@
+ALTER TABLE announcement
+  ADD COLUMN publish_at datetime DEFAULT NULL,
+  ADD COLUMN expires_at datetime DEFAULT NULL,
+  ADD COLUMN section varchar(20) DEFAULT NULL,
+  ADD COLUMN enrollment enum('class', 'club') DEFAULT NULL,
+  ADD COLUMN role varchar(20) DEFAULT NULL,
+  ADD COLUMN send_email bool NOT NULL DEFAULT '0',
+  ADD COLUMN emailed bool NOT NULL DEFAULT '0',
+  ADD FOREIGN KEY (section) REFERENCES section_type (name) ON DELETE SET NULL ON UPDATE CASCADE,
+  ADD FOREIGN KEY (role) REFERENCES role (name) ON DELETE SET NULL ON UPDATE CASCADE;
+
+
+CREATE TABLE announcement_read (
+  member varchar(50) NOT NULL,
+  announcement int NOT NULL,
+  `time` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY (member, announcement),
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (announcement) REFERENCES announcement (id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
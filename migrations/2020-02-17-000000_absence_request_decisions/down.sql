@@ -0,0 +1,7 @@
+DELETE FROM notification_preference WHERE category = 'officer_digests';
+
+ALTER TABLE notification_preference
+  MODIFY category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes') NOT NULL;
+
+ALTER TABLE absence_request
+  DROP COLUMN comment;
@@ -0,0 +1,5 @@
+ALTER TABLE absence_request
+  ADD COLUMN comment varchar(500) DEFAULT NULL;
+
+ALTER TABLE notification_preference
+  MODIFY category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes', 'officer_digests') NOT NULL;
@@ -0,0 +1,4 @@
+DELETE FROM permission WHERE name = 'manage-webhooks';
+DROP TABLE IF EXISTS webhook_delivery CASCADE;
+DROP TABLE IF EXISTS webhook_kind CASCADE;
+DROP TABLE IF EXISTS webhook CASCADE;
@@ -0,0 +1,37 @@
+CREATE TABLE webhook (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  url varchar(255) NOT NULL,
+  secret varchar(64) NOT NULL,
+  active bool NOT NULL DEFAULT '1',
+  created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE webhook_kind (
+  webhook int NOT NULL,
+  kind enum('event_created', 'event_updated', 'event_deleted', 'gig_request_submitted', 'absence_request_submitted', 'absence_request_decided', 'announcement_posted', 'transaction_created') NOT NULL,
+
+  PRIMARY KEY (webhook, kind),
+  FOREIGN KEY (webhook) REFERENCES webhook (id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE webhook_delivery (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  webhook int NOT NULL,
+  kind enum('event_created', 'event_updated', 'event_deleted', 'gig_request_submitted', 'absence_request_submitted', 'absence_request_decided', 'announcement_posted', 'transaction_created') NOT NULL,
+  payload longtext NOT NULL,
+  status enum('pending', 'delivered', 'failed') NOT NULL DEFAULT 'pending',
+  attempts int NOT NULL DEFAULT '0',
+  response_status int DEFAULT NULL,
+  last_error text DEFAULT NULL,
+  created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  next_attempt datetime NOT NULL,
+  delivered_at datetime DEFAULT NULL,
+
+  FOREIGN KEY (webhook) REFERENCES webhook (id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+INSERT INTO permission (name, description) VALUES
+  ('manage-webhooks', 'Manage webhook subscriptions and view their deliveries');
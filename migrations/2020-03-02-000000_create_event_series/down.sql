@@ -0,0 +1,5 @@
+ALTER TABLE event
+  DROP FOREIGN KEY event_ibfk_4,
+  DROP COLUMN series;
+
+DROP TABLE IF EXISTS event_series;
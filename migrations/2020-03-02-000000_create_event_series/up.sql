@@ -0,0 +1,13 @@
+CREATE TABLE event_series (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  semester varchar(32) NOT NULL,
+  period enum('no', 'daily', 'weekly', 'biweekly', 'monthly', 'yearly') NOT NULL,
+  repeat_until date NOT NULL,
+  created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  FOREIGN KEY (semester) REFERENCES semester (name) ON UPDATE CASCADE ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+ALTER TABLE event
+  ADD COLUMN series int DEFAULT NULL,
+  ADD FOREIGN KEY (series) REFERENCES event_series (id) ON UPDATE CASCADE ON DELETE SET NULL;
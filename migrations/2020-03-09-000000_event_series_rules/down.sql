@@ -0,0 +1,3 @@
+ALTER TABLE event_series
+  DROP COLUMN rule,
+  DROP COLUMN exceptions;
@@ -0,0 +1,3 @@
+ALTER TABLE event_series
+  ADD COLUMN rule varchar(255) DEFAULT NULL,
+  ADD COLUMN exceptions text DEFAULT NULL;
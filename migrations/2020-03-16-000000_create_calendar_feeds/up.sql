@@ -0,0 +1,7 @@
+CREATE TABLE calendar_feed (
+  member varchar(50) NOT NULL PRIMARY KEY,
+  token varchar(64) NOT NULL UNIQUE,
+  created timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
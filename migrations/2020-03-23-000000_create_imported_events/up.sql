@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS imported_event;
+CREATE TABLE imported_event (
+  uid varchar(255) NOT NULL PRIMARY KEY,
+  event int NOT NULL,
+  imported timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  FOREIGN KEY (event) REFERENCES event (id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS check_in_window;
+CREATE TABLE check_in_window (
+  event int NOT NULL PRIMARY KEY,
+  secret varchar(64) NOT NULL,
+  opened_by varchar(50) NOT NULL,
+  opened timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  closes datetime NOT NULL,
+
+  FOREIGN KEY (event) REFERENCES event (id) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (opened_by) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
@@ -0,0 +1,5 @@
+ALTER TABLE attendance
+  DROP COLUMN minutes_left_early,
+  DROP COLUMN lateness_excused,
+  DROP COLUMN early_departure_excused,
+  DROP COLUMN excuse_reason;
@@ -0,0 +1,5 @@
+ALTER TABLE attendance
+  ADD COLUMN minutes_left_early int NOT NULL DEFAULT '0',
+  ADD COLUMN lateness_excused boolean NOT NULL DEFAULT '0',
+  ADD COLUMN early_departure_excused boolean NOT NULL DEFAULT '0',
+  ADD COLUMN excuse_reason varchar(255) DEFAULT NULL;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS grading_rule;
+DROP TABLE IF EXISTS grading_policy;
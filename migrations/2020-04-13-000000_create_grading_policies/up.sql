@@ -0,0 +1,25 @@
+DROP TABLE IF EXISTS grading_rule;
+DROP TABLE IF EXISTS grading_policy;
+CREATE TABLE grading_policy (
+  semester varchar(32) NOT NULL PRIMARY KEY,
+  start_grade float NOT NULL DEFAULT '100',
+  max_grade float NOT NULL DEFAULT '100',
+  default_event_minutes int NOT NULL DEFAULT '60',
+  use_type_weights boolean NOT NULL DEFAULT '0',
+  sectional_substitution boolean NOT NULL DEFAULT '1',
+  rehearsal_required_for_gigs boolean NOT NULL DEFAULT '1',
+
+  FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+CREATE TABLE grading_rule (
+  semester varchar(32) NOT NULL,
+  event_type varchar(32) NOT NULL,
+  bonus enum('never', 'always', 'when_optional') NOT NULL DEFAULT 'never',
+  penalize_lateness boolean NOT NULL DEFAULT '1',
+  penalize_absence boolean NOT NULL DEFAULT '1',
+
+  PRIMARY KEY (semester, event_type),
+  FOREIGN KEY (semester) REFERENCES grading_policy (semester) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (event_type) REFERENCES event_type (name) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
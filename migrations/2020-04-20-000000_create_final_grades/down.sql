@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS finalization_log;
+DROP TABLE IF EXISTS final_grade;
+DROP TABLE IF EXISTS semester_finalization;
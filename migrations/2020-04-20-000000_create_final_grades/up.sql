@@ -0,0 +1,35 @@
+CREATE TABLE semester_finalization (
+  semester varchar(32) NOT NULL PRIMARY KEY,
+  finalized_by varchar(50) DEFAULT NULL,
+  finalized_at datetime NOT NULL,
+
+  FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (finalized_by) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE final_grade (
+  member varchar(50) NOT NULL,
+  semester varchar(32) NOT NULL,
+  grade float NOT NULL,
+  letter varchar(2) NOT NULL,
+  volunteer_gigs_attended int NOT NULL,
+  breakdown longtext NOT NULL,
+
+  PRIMARY KEY (member, semester),
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE finalization_log (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  semester varchar(32) NOT NULL,
+  member varchar(50) DEFAULT NULL,
+  action enum('finalized', 'reopened') NOT NULL,
+  reason text DEFAULT NULL,
+  time datetime NOT NULL,
+
+  FOREIGN KEY (semester) REFERENCES semester (name) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
@@ -0,0 +1,7 @@
+DELETE FROM notification_preference WHERE category = 'grade_disputes';
+
+ALTER TABLE notification_preference
+  MODIFY category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes', 'officer_digests') NOT NULL;
+
+DROP TABLE IF EXISTS grade_dispute_entry;
+DROP TABLE IF EXISTS grade_dispute;
@@ -0,0 +1,29 @@
+CREATE TABLE grade_dispute (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  member varchar(50) NOT NULL,
+  event int NOT NULL,
+  message text NOT NULL,
+  disputed_reason varchar(255) DEFAULT NULL,
+  disputed_change float DEFAULT NULL,
+  state enum('open', 'resolved', 'rejected') NOT NULL DEFAULT 'open',
+  created datetime NOT NULL,
+
+  FOREIGN KEY (member, event) REFERENCES attendance (member, event) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+CREATE TABLE grade_dispute_entry (
+  id int NOT NULL AUTO_INCREMENT PRIMARY KEY,
+  dispute int NOT NULL,
+  author varchar(50) DEFAULT NULL,
+  action enum('commented', 'resolved', 'rejected') NOT NULL,
+  content text DEFAULT NULL,
+  time datetime NOT NULL,
+
+  FOREIGN KEY (dispute) REFERENCES grade_dispute (id) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (author) REFERENCES member (email) ON DELETE SET NULL ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
+
+
+ALTER TABLE notification_preference
+  MODIFY category enum('event_reminders', 'announcements', 'absence_decisions', 'dues_reminders', 'carpool_changes', 'officer_digests', 'grade_disputes') NOT NULL;
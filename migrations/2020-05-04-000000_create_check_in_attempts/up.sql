@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS check_in_attempt;
+CREATE TABLE check_in_attempt (
+  event int NOT NULL,
+  member varchar(50) NOT NULL,
+  failures int NOT NULL DEFAULT 0,
+  last_failure datetime NOT NULL,
+
+  PRIMARY KEY (event, member),
+  FOREIGN KEY (event) REFERENCES check_in_window (event) ON DELETE CASCADE ON UPDATE CASCADE,
+  FOREIGN KEY (member) REFERENCES member (email) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
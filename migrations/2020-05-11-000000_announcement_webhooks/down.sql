@@ -0,0 +1,2 @@
+ALTER TABLE announcement
+  DROP COLUMN posted;
@@ -0,0 +1,5 @@
+ALTER TABLE announcement
+  ADD COLUMN posted bool NOT NULL DEFAULT '0';
+
+-- announcements made before this were sent to webhooks when they were made
+UPDATE announcement SET posted = '1';
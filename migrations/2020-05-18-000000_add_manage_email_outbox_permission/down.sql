@@ -0,0 +1,4 @@
+DELETE FROM permission WHERE name = 'manage-email-outbox';
+
+UPDATE permission SET description = 'View the status of outgoing emails and retry failed ones'
+  WHERE name = 'view-email-outbox';
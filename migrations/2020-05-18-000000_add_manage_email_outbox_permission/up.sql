@@ -0,0 +1,5 @@
+INSERT INTO permission (name, description) VALUES
+  ('manage-email-outbox', 'Retry outgoing emails that failed to send');
+
+UPDATE permission SET description = 'View the status of outgoing emails'
+  WHERE name = 'view-email-outbox';